@@ -0,0 +1,565 @@
+//! Automatic encrypted backups to a user-chosen directory.
+//!
+//! A background thread checks every minute whether a backup is due: either
+//! the configured interval has passed or enough changes have piled up since
+//! the last backup. Backups are encrypted to the configured recovery public
+//! keys, or else with a key derived from the user's backup password (stored
+//! in the vault), so no password prompt is needed.
+//! Old files are thinned out with daily/weekly/monthly retention.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::backup::{self, BackupContents, Recipient};
+use crate::settings::Settings;
+use crate::storage::{now_secs, Storage};
+
+const STATUS_FILE: &str = "auto_backup_status.json";
+const FILE_PREFIX: &str = "ghost-auth-auto-";
+const FILE_EXTENSION: &str = "ghostauth";
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// After a failure, wait this long before trying again.
+const RETRY_AFTER_SECS: u64 = 15 * 60;
+const MAX_INTERVAL_HOURS: u64 = 30 * 24;
+const MAX_KEEP: usize = 100;
+const DAY_SECS: u64 = 24 * 60 * 60;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct AutoBackupSettings {
+    pub enabled: bool,
+    /// Absolute path of the directory backups are written to.
+    pub directory: Option<String>,
+    /// Back up at least this often while there are unsaved changes.
+    pub interval_hours: u64,
+    /// Back up as soon as this many changes have been made. 0 disables.
+    pub after_changes: u64,
+    /// Number of most recent days, weeks and months to keep one backup for.
+    pub keep_daily: usize,
+    pub keep_weekly: usize,
+    pub keep_monthly: usize,
+}
+
+impl Default for AutoBackupSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            directory: None,
+            interval_hours: 24,
+            after_changes: 10,
+            keep_daily: 7,
+            keep_weekly: 4,
+            keep_monthly: 12,
+        }
+    }
+}
+
+impl AutoBackupSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=MAX_INTERVAL_HOURS).contains(&self.interval_hours) {
+            return Err(format!(
+                "Backup interval must be between 1 and {MAX_INTERVAL_HOURS} hours"
+            ));
+        }
+        let keep = [self.keep_daily, self.keep_weekly, self.keep_monthly];
+        if keep.iter().any(|&n| n > MAX_KEEP) {
+            return Err(format!(
+                "Cannot keep more than {MAX_KEEP} backups per period"
+            ));
+        }
+        if keep.iter().all(|&n| n == 0) {
+            return Err("At least one backup must be kept".to_string());
+        }
+        if self.enabled {
+            match &self.directory {
+                Some(dir) if Path::new(dir).is_absolute() => {}
+                Some(_) => return Err("Backup directory must be an absolute path".to_string()),
+                None => return Err("Choose a directory for automatic backups".to_string()),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of the most recent automatic backups, kept next to the settings.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct AutoBackupStatus {
+    pub last_success_at: Option<u64>,
+    pub last_file: Option<String>,
+    pub last_attempt_at: Option<u64>,
+    /// Error from the last attempt, cleared by the next success.
+    pub last_error: Option<String>,
+}
+
+impl AutoBackupStatus {
+    pub fn load(data_dir: &Path) -> Self {
+        fs::read_to_string(data_dir.join(STATUS_FILE))
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, data_dir: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| {
+            tracing::error!(error = %e, "Failed to serialize backup status");
+            "Failed to save backup status".to_string()
+        })?;
+        fs::write(data_dir.join(STATUS_FILE), json).map_err(|e| {
+            tracing::error!(error = %e, "Failed to write backup status");
+            "Failed to save backup status".to_string()
+        })
+    }
+}
+
+/// Start the background thread that runs due backups.
+pub fn spawn_scheduler(app: tauri::AppHandle, data_dir: PathBuf) {
+    use tauri::Manager;
+    std::thread::spawn(move || loop {
+        std::thread::sleep(CHECK_INTERVAL);
+        let storage = app.state::<Mutex<Storage>>();
+        run_if_due(&data_dir, &storage, now_secs());
+    });
+}
+
+fn is_due(config: &AutoBackupSettings, status: &AutoBackupStatus, changes: u64, now: u64) -> bool {
+    if !config.enabled || config.directory.is_none() {
+        return false;
+    }
+    if let (Some(attempt), Some(_)) = (status.last_attempt_at, &status.last_error) {
+        if now < attempt + RETRY_AFTER_SECS {
+            return false;
+        }
+    }
+    match status.last_success_at {
+        None => true,
+        Some(_) if changes == 0 => false,
+        Some(_) if config.after_changes > 0 && changes >= config.after_changes => true,
+        Some(last) => now.saturating_sub(last) >= config.interval_hours * 3600,
+    }
+}
+
+/// Run a backup if one is due.
+pub fn run_if_due(data_dir: &Path, storage: &Mutex<Storage>, now: u64) {
+    let settings = Settings::load(data_dir);
+    let status = AutoBackupStatus::load(data_dir);
+    let changes = match storage.lock() {
+        Ok(storage) => storage.changes_since_backup(),
+        Err(_) => return,
+    };
+    if is_due(&settings.auto_backup, &status, changes, now) {
+        // The outcome is recorded in the status file
+        let _ = run_now(data_dir, storage, now);
+    }
+}
+
+/// Write a backup immediately, rotate old ones and record the outcome.
+pub fn run_now(data_dir: &Path, storage: &Mutex<Storage>, now: u64) -> Result<PathBuf, String> {
+    let settings = Settings::load(data_dir);
+    let result = match storage.lock() {
+        Ok(mut storage) => write_backup(&settings, &mut storage, now),
+        Err(_) => {
+            tracing::error!("Storage mutex poisoned");
+            Err("Storage unavailable — please restart the app".to_string())
+        }
+    };
+
+    let mut status = AutoBackupStatus::load(data_dir);
+    status.last_attempt_at = Some(now);
+    match &result {
+        Ok(path) => {
+            status.last_success_at = Some(now);
+            status.last_file = Some(path.display().to_string());
+            status.last_error = None;
+            tracing::info!(event = "auto_backup_written", path = %path.display(), "Automatic backup written");
+        }
+        Err(e) => {
+            status.last_error = Some(e.clone());
+            tracing::error!(event = "auto_backup_failed", error = %e, "Automatic backup failed");
+        }
+    }
+    if let Err(e) = status.save(data_dir) {
+        tracing::warn!(error = %e, "Failed to record automatic backup status");
+    }
+    result
+}
+
+fn write_backup(settings: &Settings, storage: &mut Storage, now: u64) -> Result<PathBuf, String> {
+    let config = &settings.auto_backup;
+    let dir = config
+        .directory
+        .as_deref()
+        .map(PathBuf::from)
+        .ok_or_else(|| "Choose a directory for automatic backups".to_string())?;
+    let contents = BackupContents::from_vault(storage, settings);
+    // Recovery keys take precedence, so the device holds no way to decrypt
+    let data = if settings.backup_recipients.is_empty() {
+        let key = storage
+            .backup_key()
+            .ok_or_else(|| "Set a backup password for automatic backups".to_string())?;
+        backup::export_backup_with_key(&contents, key)?
+    } else {
+        let recipients = settings
+            .backup_recipients
+            .iter()
+            .map(|r| Recipient::parse(r))
+            .collect::<Result<Vec<_>, _>>()?;
+        backup::export_backup_to_recipients(&contents, &recipients)?
+    };
+
+    fs::create_dir_all(&dir).map_err(|e| {
+        tracing::error!(error = %e, path = %dir.display(), "Failed to create backup directory");
+        "Failed to create backup directory".to_string()
+    })?;
+    let path = dir.join(format!("{FILE_PREFIX}{now}.{FILE_EXTENSION}"));
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, &data)
+        .and_then(|_| fs::rename(&tmp_path, &path))
+        .map_err(|e| {
+            tracing::error!(error = %e, "Failed to write backup file");
+            let _ = fs::remove_file(&tmp_path);
+            "Failed to write backup file".to_string()
+        })?;
+
+    storage.record_backup()?;
+    rotate(&dir, config);
+    Ok(path)
+}
+
+/// Delete automatic backups that fall outside the retention policy. Files
+/// not written by the scheduler are never touched.
+fn rotate(dir: &Path, config: &AutoBackupSettings) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            tracing::warn!(error = %e, "Failed to list backup directory for rotation");
+            return;
+        }
+    };
+    let backups: Vec<(u64, PathBuf)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let timestamp = path
+                .file_name()?
+                .to_str()?
+                .strip_prefix(FILE_PREFIX)?
+                .strip_suffix(&format!(".{FILE_EXTENSION}"))?
+                .parse()
+                .ok()?;
+            Some((timestamp, path))
+        })
+        .collect();
+
+    let timestamps: Vec<u64> = backups.iter().map(|(t, _)| *t).collect();
+    let kept = retained(&timestamps, config);
+    for (timestamp, path) in backups {
+        if kept.contains(&timestamp) {
+            continue;
+        }
+        match fs::remove_file(&path) {
+            Ok(()) => tracing::debug!(path = %path.display(), "Removed old automatic backup"),
+            Err(e) => tracing::warn!(error = %e, "Failed to remove old automatic backup"),
+        }
+    }
+}
+
+/// Which backups to keep: the newest backup of each of the last `keep_daily`
+/// days that have one, likewise for weeks and months (UTC).
+fn retained(timestamps: &[u64], config: &AutoBackupSettings) -> HashSet<u64> {
+    let mut sorted = timestamps.to_vec();
+    sorted.sort_unstable_by(|a, b| b.cmp(a));
+
+    let day = |t: u64| t / DAY_SECS;
+    // 1970-01-01 was a Thursday; shift so weeks start on Monday
+    let week = |t: u64| (t / DAY_SECS + 3) / 7;
+    let month = |t: u64| {
+        let (year, month, _) = civil_date(t / DAY_SECS);
+        year * 12 + month
+    };
+    let periods: [(&dyn Fn(u64) -> u64, usize); 3] = [
+        (&day, config.keep_daily),
+        (&week, config.keep_weekly),
+        (&month, config.keep_monthly),
+    ];
+
+    let mut kept = HashSet::new();
+    for (period, keep) in periods {
+        let mut last = None;
+        let mut count = 0;
+        for &timestamp in &sorted {
+            if count == keep {
+                break;
+            }
+            let bucket = period(timestamp);
+            if last != Some(bucket) {
+                kept.insert(timestamp);
+                last = Some(bucket);
+                count += 1;
+            }
+        }
+    }
+    kept
+}
+
+/// The UTC (year, month, day) of a count of days since the Unix epoch.
+pub(crate) fn civil_date(days: u64) -> (u64, u64, u64) {
+    // Howard Hinnant's civil_from_days, for non-negative days
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backup::{BackupKey, Identity, KdfPreset};
+    use crate::storage::Account;
+    use crate::storage_backend::MemoryBackend;
+
+    const HOUR: u64 = 3600;
+
+    fn enabled_config(dir: &Path) -> AutoBackupSettings {
+        AutoBackupSettings {
+            enabled: true,
+            directory: Some(dir.display().to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn make_account(id: &str) -> Account {
+        Account {
+            id: id.to_string(),
+            issuer: "GitHub".to_string(),
+            label: "user@example.com".to_string(),
+            secret: "JBSWY3DPEHPK3PXP".to_string(),
+            algorithm: "SHA1".to_string(),
+            digits: 6,
+            period: 30,
+            icon: None,
+            last_modified: 0,
+            tags: vec![],
+        }
+    }
+
+    #[test]
+    fn test_civil_date() {
+        assert_eq!(civil_date(0), (1970, 1, 1));
+        assert_eq!(civil_date(19_782), (2024, 2, 29));
+        assert_eq!(civil_date(19_783), (2024, 3, 1));
+        assert_eq!(civil_date(11_322), (2000, 12, 31));
+    }
+
+    #[test]
+    fn test_is_due() {
+        let config = enabled_config(Path::new("/backups"));
+        let now = 1_700_000_000;
+        let never = AutoBackupStatus::default();
+        assert!(is_due(&config, &never, 0, now));
+
+        let recent = AutoBackupStatus {
+            last_success_at: Some(now - HOUR),
+            last_attempt_at: Some(now - HOUR),
+            ..Default::default()
+        };
+        assert!(!is_due(&config, &recent, 3, now));
+        assert!(is_due(&config, &recent, 10, now));
+
+        let old = AutoBackupStatus {
+            last_success_at: Some(now - 25 * HOUR),
+            ..recent.clone()
+        };
+        assert!(is_due(&config, &old, 1, now));
+        // Nothing changed, nothing to back up
+        assert!(!is_due(&config, &old, 0, now));
+
+        let disabled = AutoBackupSettings {
+            enabled: false,
+            ..config.clone()
+        };
+        assert!(!is_due(&disabled, &never, 0, now));
+    }
+
+    #[test]
+    fn test_failed_backup_waits_before_retry() {
+        let config = enabled_config(Path::new("/backups"));
+        let now = 1_700_000_000;
+        let failed = AutoBackupStatus {
+            last_attempt_at: Some(now - 60),
+            last_error: Some("Failed to write backup file".into()),
+            ..Default::default()
+        };
+        assert!(!is_due(&config, &failed, 50, now));
+        assert!(is_due(&config, &failed, 50, now + RETRY_AFTER_SECS));
+    }
+
+    #[test]
+    fn test_retention_keeps_daily_weekly_monthly() {
+        let config = AutoBackupSettings {
+            keep_daily: 3,
+            keep_weekly: 2,
+            keep_monthly: 2,
+            ..Default::default()
+        };
+        // Monday 2024-01-01 00:00 UTC, then one backup every 12 hours for 70 days
+        let start = 1_704_067_200;
+        let timestamps: Vec<u64> = (0..140).map(|i| start + i * 12 * HOUR).collect();
+        let kept = retained(&timestamps, &config);
+
+        // Newest is Sunday 2024-03-10 12:00; keep the last three days
+        let newest = *timestamps.last().unwrap();
+        for t in [newest, newest - 24 * HOUR, newest - 48 * HOUR] {
+            assert!(kept.contains(&t));
+        }
+        // Newest of the previous week: Sunday 2024-03-03 12:00
+        assert!(kept.contains(&1_709_467_200));
+        // Newest of the previous month: 2024-02-29 12:00
+        assert!(kept.contains(&1_709_208_000));
+        // Daily/weekly/monthly picks overlap on the newest backup
+        assert_eq!(kept.len(), 5);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(AutoBackupSettings::default().validate().is_ok());
+        let dir = tempfile::tempdir().unwrap();
+        assert!(enabled_config(dir.path()).validate().is_ok());
+
+        let relative = AutoBackupSettings {
+            directory: Some("backups".into()),
+            ..enabled_config(dir.path())
+        };
+        assert!(relative.validate().is_err());
+        let no_dir = AutoBackupSettings {
+            enabled: true,
+            ..Default::default()
+        };
+        assert!(no_dir.validate().is_err());
+        let keep_nothing = AutoBackupSettings {
+            keep_daily: 0,
+            keep_weekly: 0,
+            keep_monthly: 0,
+            ..Default::default()
+        };
+        assert!(keep_nothing.validate().is_err());
+        let no_interval = AutoBackupSettings {
+            interval_hours: 0,
+            ..Default::default()
+        };
+        assert!(no_interval.validate().is_err());
+    }
+
+    #[test]
+    fn test_run_writes_rotates_and_records_status() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let backup_dir = tempfile::tempdir().unwrap();
+        let settings = Settings {
+            auto_backup: AutoBackupSettings {
+                keep_daily: 1,
+                keep_weekly: 0,
+                keep_monthly: 0,
+                ..enabled_config(backup_dir.path())
+            },
+            ..Default::default()
+        };
+        settings.save(data_dir.path()).unwrap();
+
+        let mut store = Storage::with_backend(Box::new(MemoryBackend::new([1; 32]))).unwrap();
+        store.add(make_account("a")).unwrap();
+        let storage = Mutex::new(store);
+
+        // No password configured yet
+        let now = 1_700_000_000;
+        assert!(run_now(data_dir.path(), &storage, now).is_err());
+        let status = AutoBackupStatus::load(data_dir.path());
+        assert!(status.last_error.is_some());
+        assert_eq!(status.last_success_at, None);
+
+        let key = BackupKey::derive("password1234", KdfPreset::Standard).unwrap();
+        storage.lock().unwrap().set_backup_key(Some(key)).unwrap();
+        // A foreign file in the directory must survive rotation
+        fs::write(backup_dir.path().join("notes.txt"), "keep me").unwrap();
+
+        let first = run_now(data_dir.path(), &storage, now).unwrap();
+        assert_eq!(storage.lock().unwrap().changes_since_backup(), 0);
+        storage.lock().unwrap().add(make_account("b")).unwrap();
+        let second = run_now(data_dir.path(), &storage, now + HOUR).unwrap();
+
+        // Same day, so only the newest is kept
+        assert!(!first.exists());
+        let data = fs::read(&second).unwrap();
+        let contents = backup::import_backup(&data, "password1234").unwrap();
+        assert_eq!(contents.accounts.len(), 2);
+        assert!(backup_dir.path().join("notes.txt").exists());
+
+        let status = AutoBackupStatus::load(data_dir.path());
+        assert_eq!(status.last_success_at, Some(now + HOUR));
+        assert_eq!(status.last_error, None);
+        assert_eq!(status.last_file, Some(second.display().to_string()));
+    }
+
+    #[test]
+    fn test_run_encrypts_to_recipients_without_password() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let backup_dir = tempfile::tempdir().unwrap();
+        let identity = Identity::generate();
+        let settings = Settings {
+            backup_recipients: vec![identity.recipient().to_string()],
+            auto_backup: enabled_config(backup_dir.path()),
+            ..Default::default()
+        };
+        settings.save(data_dir.path()).unwrap();
+
+        let mut store = Storage::with_backend(Box::new(MemoryBackend::new([1; 32]))).unwrap();
+        store.add(make_account("a")).unwrap();
+        let storage = Mutex::new(store);
+
+        let path = run_now(data_dir.path(), &storage, 1_700_000_000).unwrap();
+        let data = fs::read(path).unwrap();
+        let contents = backup::import_backup_with_identity(&data, &identity).unwrap();
+        assert_eq!(contents.accounts.len(), 1);
+    }
+
+    #[test]
+    fn test_run_if_due_respects_change_threshold() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let backup_dir = tempfile::tempdir().unwrap();
+        let settings = Settings {
+            auto_backup: AutoBackupSettings {
+                after_changes: 2,
+                ..enabled_config(backup_dir.path())
+            },
+            ..Default::default()
+        };
+        settings.save(data_dir.path()).unwrap();
+
+        let mut store = Storage::with_backend(Box::new(MemoryBackend::new([1; 32]))).unwrap();
+        let key = BackupKey::derive("password1234", KdfPreset::Standard).unwrap();
+        store.set_backup_key(Some(key)).unwrap();
+        let storage = Mutex::new(store);
+
+        let now = 1_700_000_000;
+        let last_success = || AutoBackupStatus::load(data_dir.path()).last_success_at;
+        run_if_due(data_dir.path(), &storage, now);
+        assert_eq!(last_success(), Some(now));
+
+        storage.lock().unwrap().add(make_account("a")).unwrap();
+        run_if_due(data_dir.path(), &storage, now + 60);
+        assert_eq!(last_success(), Some(now));
+
+        storage.lock().unwrap().add(make_account("b")).unwrap();
+        run_if_due(data_dir.path(), &storage, now + 120);
+        assert_eq!(last_success(), Some(now + 120));
+    }
+}
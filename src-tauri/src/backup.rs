@@ -1,16 +1,35 @@
 use aes_gcm::{
-    aead::{Aead, KeyInit},
+    aead::{Aead, KeyInit, Payload},
     Aes256Gcm, Nonce,
 };
 use argon2::{Algorithm, Argon2, Params, Version};
+use hkdf::Hkdf;
 use rand::{rngs::OsRng, RngCore};
 use serde::{Deserialize, Serialize};
-use zeroize::Zeroizing;
+use sha2::{Digest, Sha256};
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
-use crate::storage::Account;
+use crate::password_strength::MIN_PASSWORD_LEN;
+use crate::settings::Settings;
+use crate::storage::{now_secs, Account, Storage, Tombstone, VaultMeta};
 
 const MAGIC: &[u8; 4] = b"GHST";
-const FORMAT_VERSION: u8 = 1;
+const FORMAT_VERSION_V1: u8 = 1;
+const FORMAT_VERSION: u8 = 2;
+
+const KDF_ARGON2ID: &str = "argon2id";
+/// Argon2id parameters of version 1 backups, which don't record them.
+const V1_ARGON2_MEMORY_KIB: u32 = 65536;
+const V1_ARGON2_ITERATIONS: u32 = 3;
+const V1_ARGON2_PARALLELISM: u32 = 1;
+/// Largest header accepted on import, so a corrupt length can't cause a huge allocation.
+const MAX_HEADER_LEN: usize = 64 * 1024;
+
+const RECIPIENT_PREFIX: &str = "ghostpub1";
+const IDENTITY_PREFIX: &str = "GHOST-SECRET-KEY-1";
+const RECIPIENT_WRAP_INFO: &[u8] = b"ghost-auth backup x25519 v1";
+const MAX_RECIPIENTS: usize = 32;
 
 #[derive(Serialize, Deserialize)]
 struct BackupPayload {
@@ -19,13 +38,293 @@ struct BackupPayload {
     accounts: Vec<Account>,
 }
 
+/// Plaintext header of a v2 backup. It is authenticated as AES-GCM associated
+/// data, so it can't be altered without failing decryption. Unknown fields are
+/// ignored, which lets later versions add to it without breaking older readers.
+///
+/// The payload key comes from a password (`kdf`), or is random and either
+/// wrapped for each public key in `recipients` or split into shares held by
+/// custodians (`key_split`).
+#[derive(Serialize, Deserialize)]
+struct BackupHeader {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kdf: Option<KdfParams>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    recipients: Vec<RecipientStanza>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key_split: Option<KeySplit>,
+    /// Base64 AES-256-GCM nonce.
+    nonce: String,
+}
+
+/// How the key of a recovery kit backup was split. The shares themselves are
+/// never stored in the backup.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct KeySplit {
+    pub kit_id: String,
+    pub threshold: u8,
+    pub share_count: u8,
+}
+
+/// The payload key wrapped for one recipient, age-style: an ephemeral X25519
+/// key agreement, HKDF-SHA256, then AES-256-GCM with a zero nonce (the
+/// wrapping key is never reused).
+#[derive(Serialize, Deserialize, Clone)]
+struct RecipientStanza {
+    /// Base64 ephemeral public key.
+    ephemeral: String,
+    /// Base64 encrypted payload key.
+    wrapped_key: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct KdfParams {
+    algorithm: String,
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+    /// Base64 salt.
+    salt: String,
+}
+
+/// Encrypted payload of a v2 backup. New fields must use `#[serde(default)]`
+/// so older backups keep importing.
+#[derive(Serialize, Deserialize)]
+struct BackupPayloadV2 {
+    exported_at: u64,
+    accounts: Vec<Account>,
+    #[serde(default)]
+    tombstones: Vec<Tombstone>,
+    #[serde(default)]
+    meta: VaultMeta,
+    #[serde(default)]
+    settings: Option<Settings>,
+}
+
+/// Everything a backup restores. Version 1 backups only carry accounts, so
+/// the other fields are empty when importing one.
+#[derive(Clone, Debug, Default)]
+pub struct BackupContents {
+    pub exported_at: u64,
+    pub accounts: Vec<Account>,
+    pub tombstones: Vec<Tombstone>,
+    pub meta: VaultMeta,
+    pub settings: Option<Settings>,
+}
+
+impl BackupContents {
+    /// Everything a backup of the vault should contain.
+    pub fn from_vault(storage: &Storage, settings: &Settings) -> Self {
+        Self {
+            exported_at: now_secs(),
+            accounts: storage.list().to_vec(),
+            tombstones: storage.tombstones().to_vec(),
+            meta: storage.meta().clone(),
+            settings: Some(settings.clone()),
+        }
+    }
+}
+
+/// Key derivation strength for new backups. The chosen parameters are stored
+/// in the backup header, so changing presets never affects existing files.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum KdfPreset {
+    /// 64 MiB, 3 passes. Fast enough for older phones.
+    #[default]
+    Standard,
+    /// 256 MiB, 4 passes.
+    Strong,
+    /// 1 GiB, 4 passes. Can take several seconds and may fail on low-memory devices.
+    Maximum,
+}
+
+impl KdfPreset {
+    fn kdf(self, salt: &[u8]) -> KdfParams {
+        let (memory_kib, iterations) = match self {
+            KdfPreset::Standard => (64 * 1024, 3),
+            KdfPreset::Strong => (256 * 1024, 4),
+            KdfPreset::Maximum => (1024 * 1024, 4),
+        };
+        KdfParams {
+            algorithm: KDF_ARGON2ID.to_string(),
+            memory_kib,
+            iterations,
+            parallelism: 1,
+            salt: data_encoding::BASE64.encode(salt),
+        }
+    }
+}
+
+/// A password-derived backup key together with the KDF parameters that
+/// produced it. Lets backups be written without asking for the password, e.g.
+/// by the automatic backup scheduler; the password still opens them.
+#[derive(Serialize, Deserialize, Clone, Zeroize, ZeroizeOnDrop)]
+pub struct BackupKey {
+    #[zeroize(skip)]
+    kdf: KdfParams,
+    key: [u8; 32],
+}
+
+impl BackupKey {
+    pub fn derive(password: &str, preset: KdfPreset) -> Result<Self, String> {
+        if password.chars().count() < MIN_PASSWORD_LEN {
+            return Err(format!(
+                "Backup password must be at least {MIN_PASSWORD_LEN} characters"
+            ));
+        }
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        Self::derive_with_salt(password, preset, &salt)
+    }
+
+    fn derive_with_salt(password: &str, preset: KdfPreset, salt: &[u8]) -> Result<Self, String> {
+        let kdf = preset.kdf(salt);
+        let key = derive_key(password, salt, &kdf)?;
+        Ok(Self { kdf, key: *key })
+    }
+}
+
+/// Public key that backups can be encrypted to, written as `ghostpub1…`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Recipient(PublicKey);
+
+impl Recipient {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid recovery public key: {}", s.trim());
+        let encoded = s
+            .trim()
+            .to_lowercase()
+            .strip_prefix(RECIPIENT_PREFIX)
+            .map(str::to_uppercase)
+            .ok_or_else(invalid)?;
+        let bytes: [u8; 32] = data_encoding::BASE32_NOPAD
+            .decode(encoded.as_bytes())
+            .ok()
+            .and_then(|b| b.try_into().ok())
+            .ok_or_else(invalid)?;
+        Ok(Self(PublicKey::from(bytes)))
+    }
+}
+
+impl std::fmt::Display for Recipient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let encoded = data_encoding::BASE32_NOPAD.encode(self.0.as_bytes());
+        write!(f, "{RECIPIENT_PREFIX}{}", encoded.to_lowercase())
+    }
+}
+
+/// Private key that opens backups encrypted to its `Recipient`, written as
+/// `GHOST-SECRET-KEY-1…`. Meant to be kept offline.
+pub struct Identity(StaticSecret);
+
+impl Identity {
+    pub fn generate() -> Self {
+        Self(StaticSecret::random_from_rng(OsRng))
+    }
+
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let invalid = || "Invalid recovery private key".to_string();
+        let upper = Zeroizing::new(s.trim().to_uppercase());
+        let encoded = upper.strip_prefix(IDENTITY_PREFIX).ok_or_else(invalid)?;
+        let bytes = Zeroizing::new(
+            data_encoding::BASE32_NOPAD
+                .decode(encoded.as_bytes())
+                .map_err(|_| invalid())?,
+        );
+        let key: [u8; 32] = bytes.as_slice().try_into().map_err(|_| invalid())?;
+        Ok(Self(StaticSecret::from(key)))
+    }
+
+    pub fn recipient(&self) -> Recipient {
+        Recipient(PublicKey::from(&self.0))
+    }
+
+    pub fn to_secret_string(&self) -> Zeroizing<String> {
+        let encoded = Zeroizing::new(data_encoding::BASE32_NOPAD.encode(self.0.as_bytes()));
+        Zeroizing::new(format!("{IDENTITY_PREFIX}{}", encoded.as_str()))
+    }
+}
+
+/// Key for wrapping the payload key, from an X25519 shared secret. Both
+/// public keys are mixed in so a stanza is bound to its recipient.
+fn recipient_wrap_key(
+    shared: &[u8; 32],
+    ephemeral: &PublicKey,
+    recipient: &PublicKey,
+) -> Result<Zeroizing<[u8; 32]>, String> {
+    let mut salt = [0u8; 64];
+    salt[..32].copy_from_slice(ephemeral.as_bytes());
+    salt[32..].copy_from_slice(recipient.as_bytes());
+    let mut key = Zeroizing::new([0u8; 32]);
+    Hkdf::<Sha256>::new(Some(&salt), shared)
+        .expand(RECIPIENT_WRAP_INFO, &mut *key)
+        .map_err(|_| "Key derivation failed".to_string())?;
+    Ok(key)
+}
+
+fn wrap_for_recipient(
+    file_key: &[u8; 32],
+    recipient: &Recipient,
+) -> Result<RecipientStanza, String> {
+    let ephemeral_secret = EphemeralSecret::random_from_rng(OsRng);
+    let ephemeral = PublicKey::from(&ephemeral_secret);
+    let shared = ephemeral_secret.diffie_hellman(&recipient.0);
+    let wrap_key = recipient_wrap_key(shared.as_bytes(), &ephemeral, &recipient.0)?;
+
+    let cipher = Aes256Gcm::new_from_slice(&*wrap_key).map_err(|e| {
+        tracing::error!(error = %e, "Cipher initialization failed");
+        "Failed to create backup".to_string()
+    })?;
+    let wrapped = cipher
+        .encrypt(Nonce::from_slice(&[0u8; 12]), file_key.as_slice())
+        .map_err(|e| {
+            tracing::error!(error = %e, "Key wrapping failed");
+            "Failed to create backup".to_string()
+        })?;
+    Ok(RecipientStanza {
+        ephemeral: data_encoding::BASE64.encode(ephemeral.as_bytes()),
+        wrapped_key: data_encoding::BASE64.encode(&wrapped),
+    })
+}
+
+/// Recover the payload key from a stanza, or `None` if it isn't for `identity`.
+fn unwrap_for_identity(
+    stanza: &RecipientStanza,
+    identity: &Identity,
+) -> Option<Zeroizing<[u8; 32]>> {
+    let ephemeral: [u8; 32] = data_encoding::BASE64
+        .decode(stanza.ephemeral.as_bytes())
+        .ok()?
+        .try_into()
+        .ok()?;
+    let ephemeral = PublicKey::from(ephemeral);
+    let wrapped = data_encoding::BASE64
+        .decode(stanza.wrapped_key.as_bytes())
+        .ok()?;
+    let shared = identity.0.diffie_hellman(&ephemeral);
+    let wrap_key =
+        recipient_wrap_key(shared.as_bytes(), &ephemeral, &identity.recipient().0).ok()?;
+
+    let cipher = Aes256Gcm::new_from_slice(&*wrap_key).ok()?;
+    let file_key = Zeroizing::new(
+        cipher
+            .decrypt(Nonce::from_slice(&[0u8; 12]), wrapped.as_slice())
+            .ok()?,
+    );
+    let mut key = Zeroizing::new([0u8; 32]);
+    key.copy_from_slice(file_key.get(..32)?);
+    Some(key)
+}
+
 /// Derive a 32-byte key from a password and salt using Argon2id.
 /// The returned key is wrapped in `Zeroizing` to ensure it is zeroed on drop.
-fn derive_key(password: &str, salt: &[u8; 16]) -> Result<Zeroizing<[u8; 32]>, String> {
-    let params = Params::new(65536, 3, 1, Some(32)).map_err(|e| {
-        tracing::error!(error = %e, "Argon2 parameter construction failed");
-        "Key derivation failed".to_string()
-    })?;
+fn derive_key(password: &str, salt: &[u8], kdf: &KdfParams) -> Result<Zeroizing<[u8; 32]>, String> {
+    let params =
+        Params::new(kdf.memory_kib, kdf.iterations, kdf.parallelism, Some(32)).map_err(|e| {
+            tracing::error!(error = %e, "Argon2 parameter construction failed");
+            "Key derivation failed".to_string()
+        })?;
     let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
 
     let mut key = Zeroizing::new([0u8; 32]);
@@ -38,60 +337,249 @@ fn derive_key(password: &str, salt: &[u8; 16]) -> Result<Zeroizing<[u8; 32]>, St
     Ok(key)
 }
 
-/// Create an encrypted backup of the given accounts.
-/// Returns raw bytes in the Ghost Auth backup format:
-/// MAGIC(4) + VERSION(1) + SALT(16) + NONCE(12) + CIPHERTEXT
-pub fn export_accounts(accounts: &[Account], password: &str) -> Result<Vec<u8>, String> {
-    if password.len() < 8 {
-        return Err("Backup password must be at least 8 characters".to_string());
+fn v1_kdf(salt: &[u8]) -> KdfParams {
+    KdfParams {
+        algorithm: KDF_ARGON2ID.to_string(),
+        memory_kib: V1_ARGON2_MEMORY_KIB,
+        iterations: V1_ARGON2_ITERATIONS,
+        parallelism: V1_ARGON2_PARALLELISM,
+        salt: data_encoding::BASE64.encode(salt),
     }
+}
 
-    let mut salt = [0u8; 16];
-    OsRng.fill_bytes(&mut salt);
-    let mut nonce_bytes = [0u8; 12];
-    OsRng.fill_bytes(&mut nonce_bytes);
+/// Reject KDF settings from an untrusted header that would be unsafe or
+/// would make key derivation take unreasonably long.
+fn validate_kdf(kdf: &KdfParams) -> Result<(), String> {
+    if kdf.algorithm != KDF_ARGON2ID {
+        return Err(format!("Unsupported key derivation: {}", kdf.algorithm));
+    }
+    if !(8 * 1024..=1024 * 1024).contains(&kdf.memory_kib)
+        || !(1..=10).contains(&kdf.iterations)
+        || !(1..=8).contains(&kdf.parallelism)
+    {
+        return Err("Backup key derivation settings are out of range".to_string());
+    }
+    Ok(())
+}
 
-    let key = derive_key(password, &salt)?;
+/// Create an encrypted backup of a vault.
+/// Returns raw bytes in the Ghost Auth backup format (version 2):
+/// MAGIC(4) + VERSION(1) + HEADER_LEN(4, big-endian) + HEADER(JSON) + CIPHERTEXT
+pub fn export_backup(
+    contents: &BackupContents,
+    password: &str,
+    preset: KdfPreset,
+) -> Result<Vec<u8>, String> {
+    export_backup_with_key(contents, &BackupKey::derive(password, preset)?)
+}
 
-    let exported_at = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_secs();
+/// Create an encrypted backup with an already derived key. The result is
+/// identical in format to `export_backup` and opens with the same password.
+pub fn export_backup_with_key(
+    contents: &BackupContents,
+    key: &BackupKey,
+) -> Result<Vec<u8>, String> {
+    let header = BackupHeader {
+        kdf: Some(key.kdf.clone()),
+        recipients: Vec::new(),
+        key_split: None,
+        nonce: random_nonce(),
+    };
+    encrypt_v2(&payload_v2(contents), &header, &key.key)
+}
 
-    let payload = BackupPayload {
-        version: FORMAT_VERSION,
-        exported_at,
-        accounts: accounts.to_vec(),
+/// Create a backup that opens with the private key of any of `recipients`,
+/// instead of a password. Writing one needs no secret at all.
+pub fn export_backup_to_recipients(
+    contents: &BackupContents,
+    recipients: &[Recipient],
+) -> Result<Vec<u8>, String> {
+    if recipients.is_empty() {
+        return Err("Add at least one recovery public key".to_string());
+    }
+    if recipients.len() > MAX_RECIPIENTS {
+        return Err(format!(
+            "A backup can have at most {MAX_RECIPIENTS} recovery keys"
+        ));
+    }
+
+    let mut file_key = Zeroizing::new([0u8; 32]);
+    OsRng.fill_bytes(&mut *file_key);
+    let header = BackupHeader {
+        kdf: None,
+        key_split: None,
+        recipients: recipients
+            .iter()
+            .map(|recipient| wrap_for_recipient(&file_key, recipient))
+            .collect::<Result<_, _>>()?,
+        nonce: random_nonce(),
     };
-    let plaintext = serde_json::to_vec(&payload).map_err(|e| {
+    encrypt_v2(&payload_v2(contents), &header, &file_key)
+}
+
+/// Create a backup encrypted with a random `key` that the caller splits into
+/// shares (see `recovery_kit`).
+pub fn export_backup_with_split_key(
+    contents: &BackupContents,
+    key: &[u8; 32],
+    split: &KeySplit,
+) -> Result<Vec<u8>, String> {
+    let header = BackupHeader {
+        kdf: None,
+        recipients: Vec::new(),
+        key_split: Some(split.clone()),
+        nonce: random_nonce(),
+    };
+    encrypt_v2(&payload_v2(contents), &header, key)
+}
+
+fn random_nonce() -> String {
+    let mut nonce_bytes = [0u8; 12];
+    OsRng.fill_bytes(&mut nonce_bytes);
+    data_encoding::BASE64.encode(&nonce_bytes)
+}
+
+fn payload_v2(contents: &BackupContents) -> BackupPayloadV2 {
+    BackupPayloadV2 {
+        exported_at: contents.exported_at,
+        accounts: contents.accounts.clone(),
+        tombstones: contents.tombstones.clone(),
+        meta: contents.meta.clone(),
+        settings: contents.settings.clone(),
+    }
+}
+
+fn encrypt_v2(
+    payload: &BackupPayloadV2,
+    header: &BackupHeader,
+    key: &[u8; 32],
+) -> Result<Vec<u8>, String> {
+    let plaintext = Zeroizing::new(serde_json::to_vec(payload).map_err(|e| {
         tracing::error!(error = %e, "Backup serialization failed");
         "Failed to create backup".to_string()
+    })?);
+    seal_v2(&plaintext, header, key)
+}
+
+/// Write the header and encrypt an already serialized payload after it.
+fn seal_v2(plaintext: &[u8], header: &BackupHeader, key: &[u8; 32]) -> Result<Vec<u8>, String> {
+    let nonce_bytes = data_encoding::BASE64
+        .decode(header.nonce.as_bytes())
+        .map_err(|_| "Failed to create backup".to_string())?;
+    let header_json = serde_json::to_vec(header).map_err(|e| {
+        tracing::error!(error = %e, "Backup header serialization failed");
+        "Failed to create backup".to_string()
     })?;
 
-    let cipher = Aes256Gcm::new_from_slice(&*key).map_err(|e| {
+    let mut output = Vec::with_capacity(4 + 1 + 4 + header_json.len());
+    output.extend_from_slice(MAGIC);
+    output.push(FORMAT_VERSION);
+    output.extend_from_slice(&(header_json.len() as u32).to_be_bytes());
+    output.extend_from_slice(&header_json);
+
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|e| {
         tracing::error!(error = %e, "Cipher initialization failed");
         "Failed to create backup".to_string()
     })?;
     let nonce = Nonce::from_slice(&nonce_bytes);
-    let ciphertext = cipher.encrypt(nonce, plaintext.as_ref()).map_err(|e| {
-        tracing::error!(error = %e, "Backup encryption failed");
-        "Failed to create backup".to_string()
-    })?;
+    // Everything before the ciphertext is authenticated
+    let ciphertext = cipher
+        .encrypt(
+            nonce,
+            Payload {
+                msg: plaintext,
+                aad: &output,
+            },
+        )
+        .map_err(|e| {
+            tracing::error!(error = %e, "Backup encryption failed");
+            "Failed to create backup".to_string()
+        })?;
 
-    let mut output = Vec::with_capacity(4 + 1 + 16 + 12 + ciphertext.len());
-    output.extend_from_slice(MAGIC);
-    output.push(FORMAT_VERSION);
-    output.extend_from_slice(&salt);
-    output.extend_from_slice(&nonce_bytes);
     output.extend(ciphertext);
-
     Ok(output)
 }
 
-/// Decrypt a backup file and return the accounts.
-pub fn import_accounts(data: &[u8], password: &str) -> Result<Vec<Account>, String> {
-    // Minimum: 4 (magic) + 1 (version) + 16 (salt) + 12 (nonce) + 16 (min AES-GCM tag)
-    if data.len() < 49 {
+/// Decrypt a backup file of any supported version.
+pub fn import_backup(data: &[u8], password: &str) -> Result<BackupContents, String> {
+    match check_magic(data)? {
+        FORMAT_VERSION_V1 => import_v1(data, password),
+        _ => import_v2(data, password),
+    }
+}
+
+/// Decrypt a backup that was encrypted to the public key of `identity`.
+pub fn import_backup_with_identity(
+    data: &[u8],
+    identity: &Identity,
+) -> Result<BackupContents, String> {
+    if check_magic(data)? == FORMAT_VERSION_V1 {
+        return Err(WRONG_PROTECTION_PASSWORD.to_string());
+    }
+    let parsed = parse_v2(data)?;
+    if parsed.header.recipients.is_empty() {
+        return Err(wrong_protection(&parsed.header));
+    }
+    let key = parsed
+        .header
+        .recipients
+        .iter()
+        .find_map(|stanza| unwrap_for_identity(stanza, identity))
+        .ok_or_else(|| "This backup was not encrypted to this recovery key".to_string())?;
+    // The key unwrapped, so the stanza was authentic; only the payload can be damaged
+    decrypt_v2(&parsed, &key, "Decryption failed — corrupted file")
+}
+
+/// How a recovery kit backup was split, so the import flow can tell how many
+/// shares to ask for. `None` for other backups.
+pub fn key_split(data: &[u8]) -> Result<Option<KeySplit>, String> {
+    if check_magic(data)? == FORMAT_VERSION_V1 {
+        return Ok(None);
+    }
+    Ok(parse_v2(data)?.header.key_split)
+}
+
+/// Decrypt a recovery kit backup with the key recovered from its shares.
+pub fn import_backup_with_split_key(
+    data: &[u8],
+    kit_id: &str,
+    key: &[u8; 32],
+) -> Result<BackupContents, String> {
+    if check_magic(data)? == FORMAT_VERSION_V1 {
+        return Err(WRONG_PROTECTION_PASSWORD.to_string());
+    }
+    let parsed = parse_v2(data)?;
+    match &parsed.header.key_split {
+        None => return Err(wrong_protection(&parsed.header)),
+        Some(split) if split.kit_id != kit_id => {
+            return Err("These shares belong to a different recovery kit".to_string())
+        }
+        Some(_) => {}
+    }
+    decrypt_v2(
+        &parsed,
+        key,
+        "Decryption failed — wrong shares or corrupted file",
+    )
+}
+
+const WRONG_PROTECTION_PASSWORD: &str = "This backup is protected by a password";
+
+/// Error for opening a backup with the wrong kind of secret.
+fn wrong_protection(header: &BackupHeader) -> String {
+    if header.kdf.is_some() {
+        WRONG_PROTECTION_PASSWORD.to_string()
+    } else if !header.recipients.is_empty() {
+        "This backup is protected by a recovery key — open it with the private key".to_string()
+    } else {
+        "This backup is protected by a recovery kit — open it with the custodians' shares"
+            .to_string()
+    }
+}
+
+/// Check the magic bytes and return the format version.
+fn check_magic(data: &[u8]) -> Result<u8, String> {
+    if data.len() < 5 {
         return Err("File is too small to be a valid backup".to_string());
     }
 
@@ -99,9 +587,17 @@ pub fn import_accounts(data: &[u8], password: &str) -> Result<Vec<Account>, Stri
         return Err("Not a Ghost Auth backup file".to_string());
     }
 
-    let version = data[4];
-    if version != FORMAT_VERSION {
-        return Err(format!("Unsupported backup version: {}", version));
+    match data[4] {
+        version @ (FORMAT_VERSION_V1 | FORMAT_VERSION) => Ok(version),
+        version => Err(format!("Unsupported backup version: {}", version)),
+    }
+}
+
+/// Version 1: MAGIC(4) + VERSION(1) + SALT(16) + NONCE(12) + CIPHERTEXT
+fn import_v1(data: &[u8], password: &str) -> Result<BackupContents, String> {
+    // Minimum: 4 (magic) + 1 (version) + 16 (salt) + 12 (nonce) + 16 (min AES-GCM tag)
+    if data.len() < 49 {
+        return Err("File is too small to be a valid backup".to_string());
     }
 
     let salt: [u8; 16] = data[5..21]
@@ -112,28 +608,344 @@ pub fn import_accounts(data: &[u8], password: &str) -> Result<Vec<Account>, Stri
         .map_err(|_| "Invalid backup file".to_string())?;
     let ciphertext = &data[33..];
 
-    let key = derive_key(password, &salt)?;
+    let key = derive_key(password, &salt, &v1_kdf(&salt))?;
 
     let cipher = Aes256Gcm::new_from_slice(&*key).map_err(|e| {
         tracing::error!(error = %e, "Cipher initialization failed");
         "Failed to decrypt backup".to_string()
     })?;
     let nonce = Nonce::from_slice(&nonce_bytes);
-    let plaintext = cipher
-        .decrypt(nonce, ciphertext)
-        .map_err(|_| "Decryption failed — wrong password or corrupted file".to_string())?;
+    let plaintext = Zeroizing::new(
+        cipher
+            .decrypt(nonce, ciphertext)
+            .map_err(|_| "Decryption failed — wrong password or corrupted file".to_string())?,
+    );
 
     let payload: BackupPayload = serde_json::from_slice(&plaintext).map_err(|e| {
         tracing::error!(error = %e, "Backup deserialization failed");
         "Invalid backup data".to_string()
     })?;
 
-    Ok(payload.accounts)
+    Ok(BackupContents {
+        exported_at: payload.exported_at,
+        accounts: payload.accounts,
+        ..Default::default()
+    })
+}
+
+/// A v2 backup split into its parts, before decryption.
+struct ParsedV2<'a> {
+    header: BackupHeader,
+    /// Everything before the ciphertext, authenticated as associated data.
+    authenticated: &'a [u8],
+    nonce: [u8; 12],
+    ciphertext: &'a [u8],
+}
+
+fn parse_v2(data: &[u8]) -> Result<ParsedV2<'_>, String> {
+    if data.len() < 9 {
+        return Err("File is too small to be a valid backup".to_string());
+    }
+    let header_len = u32::from_be_bytes([data[5], data[6], data[7], data[8]]) as usize;
+    if header_len > MAX_HEADER_LEN || data.len() < 9 + header_len + 16 {
+        return Err("Invalid backup file".to_string());
+    }
+    let (authenticated, ciphertext) = data.split_at(9 + header_len);
+
+    let header: BackupHeader = serde_json::from_slice(&authenticated[9..]).map_err(|e| {
+        tracing::warn!(error = %e, "Invalid backup header");
+        "Invalid backup file".to_string()
+    })?;
+    let nonce = data_encoding::BASE64
+        .decode(header.nonce.as_bytes())
+        .ok()
+        .and_then(|n| n.try_into().ok())
+        .ok_or_else(|| "Invalid backup file".to_string())?;
+    Ok(ParsedV2 {
+        header,
+        authenticated,
+        nonce,
+        ciphertext,
+    })
+}
+
+fn import_v2(data: &[u8], password: &str) -> Result<BackupContents, String> {
+    let parsed = parse_v2(data)?;
+    let kdf = parsed
+        .header
+        .kdf
+        .as_ref()
+        .ok_or_else(|| wrong_protection(&parsed.header))?;
+    validate_kdf(kdf)?;
+    let salt = data_encoding::BASE64
+        .decode(kdf.salt.as_bytes())
+        .map_err(|_| "Invalid backup file".to_string())?;
+    if salt.len() < 16 {
+        return Err("Invalid backup file".to_string());
+    }
+
+    let key = derive_key(password, &salt, kdf)?;
+    decrypt_v2(
+        &parsed,
+        &key,
+        "Decryption failed — wrong password or corrupted file",
+    )
+}
+
+/// Decrypt the payload. `failure` is the error for a wrong key or damaged data.
+fn decrypt_v2(parsed: &ParsedV2, key: &[u8; 32], failure: &str) -> Result<BackupContents, String> {
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|e| {
+        tracing::error!(error = %e, "Cipher initialization failed");
+        "Failed to decrypt backup".to_string()
+    })?;
+    let plaintext = Zeroizing::new(
+        cipher
+            .decrypt(
+                Nonce::from_slice(&parsed.nonce),
+                Payload {
+                    msg: parsed.ciphertext,
+                    aad: parsed.authenticated,
+                },
+            )
+            .map_err(|_| failure.to_string())?,
+    );
+
+    let payload: BackupPayloadV2 = serde_json::from_slice(&plaintext).map_err(|e| {
+        tracing::error!(error = %e, "Backup deserialization failed");
+        "Invalid backup data".to_string()
+    })?;
+
+    Ok(BackupContents {
+        exported_at: payload.exported_at,
+        accounts: payload.accounts,
+        tombstones: payload.tombstones,
+        meta: payload.meta,
+        settings: payload.settings,
+    })
+}
+
+/// Whether an account in a backup is also in the vault.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum VerifyStatus {
+    /// In the vault and identical.
+    Present,
+    /// Not in the vault.
+    Missing,
+    /// In the vault, but its secret, name or code settings differ.
+    Different,
+}
+
+/// One backed-up account in a verification report. Carries no secret.
+#[derive(Serialize, Clone, Debug)]
+pub struct AccountCheck {
+    pub issuer: String,
+    pub label: String,
+    pub fingerprint: String,
+    pub status: VerifyStatus,
+}
+
+/// Result of checking that a backup opens, compared against the vault.
+#[derive(Serialize, Clone, Debug)]
+pub struct BackupVerification {
+    pub version: u8,
+    pub exported_at: u64,
+    pub account_count: usize,
+    pub accounts: Vec<AccountCheck>,
+    pub present: usize,
+    pub missing: usize,
+    pub different: usize,
+    /// Vault accounts the backup doesn't contain.
+    pub not_in_backup: usize,
+}
+
+/// The format version of a backup file.
+pub fn backup_version(data: &[u8]) -> Result<u8, String> {
+    check_magic(data)
+}
+
+/// Compare the accounts of an opened backup with the vault's. Accounts are
+/// matched by id, then by issuer and label.
+pub fn verify_contents(
+    version: u8,
+    contents: &BackupContents,
+    vault: &[Account],
+) -> BackupVerification {
+    let mut matched = vec![false; vault.len()];
+    let mut accounts = Vec::with_capacity(contents.accounts.len());
+    for account in &contents.accounts {
+        let fingerprint = account_fingerprint(account);
+        let position = vault.iter().position(|a| a.id == account.id).or_else(|| {
+            vault
+                .iter()
+                .position(|a| a.issuer == account.issuer && a.label == account.label)
+        });
+        let status = match position {
+            Some(i) => {
+                matched[i] = true;
+                if account_fingerprint(&vault[i]) == fingerprint {
+                    VerifyStatus::Present
+                } else {
+                    VerifyStatus::Different
+                }
+            }
+            None => VerifyStatus::Missing,
+        };
+        accounts.push(AccountCheck {
+            issuer: account.issuer.clone(),
+            label: account.label.clone(),
+            fingerprint,
+            status,
+        });
+    }
+
+    let count = |status| accounts.iter().filter(|a| a.status == status).count();
+    BackupVerification {
+        version,
+        exported_at: contents.exported_at,
+        account_count: contents.accounts.len(),
+        present: count(VerifyStatus::Present),
+        missing: count(VerifyStatus::Missing),
+        different: count(VerifyStatus::Different),
+        not_in_backup: matched.iter().filter(|m| !**m).count(),
+        accounts,
+    }
+}
+
+/// A short hash of what makes an account produce its codes, safe to show.
+/// Secrets that differ only in case, spacing or padding hash the same.
+pub fn account_fingerprint(account: &Account) -> String {
+    let secret = crate::totp::decode_secret(&account.secret)
+        .map(|bytes| data_encoding::BASE32_NOPAD.encode(&bytes))
+        .unwrap_or_else(|| account.secret.clone());
+    let mut hasher = Sha256::new();
+    for field in [
+        account.issuer.as_str(),
+        account.label.as_str(),
+        secret.as_str(),
+        &account.algorithm.to_uppercase(),
+        &account.digits.to_string(),
+        &account.period.to_string(),
+    ] {
+        hasher.update(field.as_bytes());
+        hasher.update([0]);
+    }
+    data_encoding::HEXUPPER.encode(&hasher.finalize()[..4])
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_verify_against_vault() {
+        let password = "password1234";
+        let mut vault = sample_accounts();
+        let mut extra = vault[0].clone();
+        extra.id = "3".into();
+        extra.issuer = "Slack".into();
+        vault.push(extra);
+
+        let mut backed_up = sample_accounts();
+        backed_up[1].secret = "JBSWY3DPEHPK3PXP".into();
+        let mut old = backed_up[0].clone();
+        old.id = "9".into();
+        old.issuer = "Old".into();
+        backed_up.push(old);
+        let backup = BackupContents {
+            accounts: backed_up,
+            ..Default::default()
+        };
+        let data = export_backup(&backup, password, KdfPreset::Standard).unwrap();
+
+        let contents = import_backup(&data, password).unwrap();
+        let report = verify_contents(backup_version(&data).unwrap(), &contents, &vault);
+        assert_eq!(report.version, FORMAT_VERSION);
+        assert_eq!(report.account_count, 3);
+        let statuses: Vec<_> = report.accounts.iter().map(|a| a.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                VerifyStatus::Present,
+                VerifyStatus::Different,
+                VerifyStatus::Missing
+            ]
+        );
+        assert_eq!(
+            (report.present, report.different, report.missing),
+            (1, 1, 1)
+        );
+        assert_eq!(report.not_in_backup, 1);
+
+        // Nothing secret is reported
+        let json = serde_json::to_string(&report).unwrap();
+        assert!(!json.contains("JBSWY3DPEHPK3PXP"));
+        assert!(!json.contains("GEZDGNBVGY3TQOJQ"));
+    }
+
+    #[test]
+    fn test_fingerprint_normalizes_secret() {
+        let account = sample_accounts().remove(0);
+        let mut retyped = account.clone();
+        retyped.id = "other".into();
+        retyped.secret = "jbsw y3dp ehpk 3pxp".into();
+        assert_eq!(account_fingerprint(&account), account_fingerprint(&retyped));
+        retyped.period = 60;
+        assert_ne!(account_fingerprint(&account), account_fingerprint(&retyped));
+    }
+
+    fn from_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    /// The payload of the first v2 golden file, byte for byte as serialized
+    /// when it was created.
+    const GOLDEN_V2_PLAINTEXT: &str = r#"{"exported_at":1700000100,"accounts":[{"id":"a1b2c3d4","issuer":"GitHub","label":"user@example.com","secret":"JBSWY3DPEHPK3PXP","algorithm":"SHA1","digits":6,"period":30,"icon":"github","last_modified":1700000000,"tags":["work"]}],"tombstones":[{"id":"deleted1","deleted_at":1700000050}],"meta":{"usage":{"a1b2c3d4":5},"last_backup_at":1699990000,"tombstones_pruned_through":null},"settings":{"tombstone_max_retention_days":200}}"#;
+
+    fn golden_v2_header(key: &BackupKey) -> BackupHeader {
+        BackupHeader {
+            kdf: Some(key.kdf.clone()),
+            recipients: Vec::new(),
+            key_split: None,
+            nonce: data_encoding::BASE64.encode(&[9u8; 12]),
+        }
+    }
+
+    fn golden_v2_payload() -> BackupPayloadV2 {
+        BackupPayloadV2 {
+            exported_at: 1700000100,
+            accounts: vec![Account {
+                id: "a1b2c3d4".into(),
+                issuer: "GitHub".into(),
+                label: "user@example.com".into(),
+                secret: "JBSWY3DPEHPK3PXP".into(),
+                algorithm: "SHA1".into(),
+                digits: 6,
+                period: 30,
+                icon: Some("github".into()),
+                last_modified: 1700000000,
+                tags: vec!["work".into()],
+            }],
+            tombstones: vec![Tombstone {
+                id: "deleted1".into(),
+                deleted_at: 1700000050,
+            }],
+            meta: VaultMeta {
+                usage: HashMap::from([("a1b2c3d4".to_string(), 5)]),
+                last_backup_at: Some(1699990000),
+                tombstones_pruned_through: None,
+                ..Default::default()
+            },
+            settings: Some(Settings {
+                tombstone_max_retention_days: 200,
+                ..Default::default()
+            }),
+        }
+    }
 
     fn sample_accounts() -> Vec<Account> {
         vec![
@@ -147,6 +959,7 @@ mod tests {
                 period: 30,
                 icon: None,
                 last_modified: 0,
+                tags: Vec::new(),
             },
             Account {
                 id: "2".into(),
@@ -158,6 +971,7 @@ mod tests {
                 period: 30,
                 icon: Some("google".into()),
                 last_modified: 0,
+                tags: Vec::new(),
             },
         ]
     }
@@ -167,8 +981,8 @@ mod tests {
         let accounts = sample_accounts();
         let password = "strongpassword123";
 
-        let exported = export_accounts(&accounts, password).unwrap();
-        let imported = import_accounts(&exported, password).unwrap();
+        let exported = export_backup(&sample_contents(), password, KdfPreset::Standard).unwrap();
+        let imported = import_backup(&exported, password).unwrap().accounts;
 
         assert_eq!(imported.len(), accounts.len());
         for (got, want) in imported.iter().zip(accounts.iter()) {
@@ -186,50 +1000,55 @@ mod tests {
 
     #[test]
     fn test_wrong_password_fails() {
-        let accounts = sample_accounts();
-        let exported = export_accounts(&accounts, "correctpassword").unwrap();
-        let result = import_accounts(&exported, "wrongpassword1");
+        let exported =
+            export_backup(&sample_contents(), "correctpassword", KdfPreset::Standard).unwrap();
+        let result = import_backup(&exported, "wrongpassword1");
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("wrong password"));
     }
 
     #[test]
     fn test_corrupted_data_fails() {
-        let accounts = sample_accounts();
-        let mut exported = export_accounts(&accounts, "password1234").unwrap();
+        let mut exported =
+            export_backup(&sample_contents(), "password1234", KdfPreset::Standard).unwrap();
         let last = exported.len() - 1;
         exported[last] ^= 0xFF;
-        assert!(import_accounts(&exported, "password1234").is_err());
+        assert!(import_backup(&exported, "password1234").is_err());
     }
 
     #[test]
     fn test_too_short_data_fails() {
-        assert!(import_accounts(&[0u8; 10], "password").is_err());
+        assert!(import_backup(&[0u8; 10], "password").is_err());
     }
 
     #[test]
     fn test_wrong_magic_fails() {
         let mut data = vec![0u8; 100];
         data[0..4].copy_from_slice(b"XXXX");
-        let err = import_accounts(&data, "password").unwrap_err();
+        let err = import_backup(&data, "password").unwrap_err();
         assert!(err.contains("Not a Ghost Auth backup"));
     }
 
     #[test]
     fn test_short_password_rejected() {
-        let result = export_accounts(&sample_accounts(), "short1");
+        let result = export_backup(&sample_contents(), "short1", KdfPreset::Standard);
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("at least 8"));
     }
 
     #[test]
     fn test_empty_accounts_roundtrip() {
-        let exported = export_accounts(&[], "password1234").unwrap();
-        let imported = import_accounts(&exported, "password1234").unwrap();
+        let exported = export_backup(
+            &BackupContents::default(),
+            "password1234",
+            KdfPreset::Standard,
+        )
+        .unwrap();
+        let imported = import_backup(&exported, "password1234").unwrap().accounts;
         assert!(imported.is_empty());
     }
 
-    /// Golden file test: a hardcoded backup blob created by export_accounts() must
+    /// Golden file test: a hardcoded backup blob created by the version 1 exporter must
     /// always decrypt to the exact same accounts. If this test breaks, the binary
     /// format has drifted and existing .ghostauth files in the wild will be unreadable.
     #[test]
@@ -241,7 +1060,7 @@ mod tests {
             .collect();
 
         let password = "ghost-test-password-1234";
-        let accounts = import_accounts(&data, password).unwrap();
+        let accounts = import_backup(&data, password).unwrap().accounts;
 
         assert_eq!(accounts.len(), 2);
 
@@ -267,4 +1086,294 @@ mod tests {
         assert_eq!(accounts[1].icon, Some("google".into()));
         assert_eq!(accounts[1].last_modified, 1700000001);
     }
+
+    #[test]
+    fn test_v2_roundtrip_full_contents() {
+        let payload = golden_v2_payload();
+        let contents = BackupContents {
+            exported_at: 1700000200,
+            accounts: payload.accounts,
+            tombstones: payload.tombstones,
+            meta: payload.meta,
+            settings: payload.settings,
+        };
+
+        let exported = export_backup(&contents, "password1234", KdfPreset::Standard).unwrap();
+        assert_eq!(&exported[0..5], b"GHST\x02");
+
+        let imported = import_backup(&exported, "password1234").unwrap();
+        assert_eq!(imported.exported_at, 1700000200);
+        assert_eq!(imported.accounts.len(), 1);
+        assert_eq!(imported.accounts[0].tags, vec!["work"]);
+        assert_eq!(imported.tombstones.len(), 1);
+        assert_eq!(imported.tombstones[0].id, "deleted1");
+        assert_eq!(imported.meta.usage.get("a1b2c3d4"), Some(&5));
+        assert_eq!(imported.settings, contents.settings);
+    }
+
+    #[test]
+    fn test_v2_header_is_authenticated() {
+        let mut exported =
+            export_backup(&sample_contents(), "password1234", KdfPreset::Standard).unwrap();
+        // Swap the "argon2id" header value's case; the header still parses
+        // as JSON but no longer matches the authenticated data
+        let pos = exported.windows(8).position(|w| w == b"argon2id").unwrap();
+        exported[pos + 7] = b'D';
+        assert!(import_backup(&exported, "password1234").is_err());
+
+        let mut exported =
+            export_backup(&sample_contents(), "password1234", KdfPreset::Standard).unwrap();
+        let pos = exported.windows(5).position(|w| w == b"nonce").unwrap();
+        exported[pos + 9] ^= 0x01;
+        assert!(import_backup(&exported, "password1234").is_err());
+    }
+
+    #[test]
+    fn test_v2_rejects_bad_header_length() {
+        let mut exported =
+            export_backup(&sample_contents(), "password1234", KdfPreset::Standard).unwrap();
+        exported[5..9].copy_from_slice(&u32::MAX.to_be_bytes());
+        let err = import_backup(&exported, "password1234").unwrap_err();
+        assert!(err.contains("Invalid backup"));
+    }
+
+    #[test]
+    fn test_export_with_derived_key_opens_with_password() {
+        let key = BackupKey::derive("password1234", KdfPreset::Standard).unwrap();
+        let contents = BackupContents {
+            accounts: sample_accounts(),
+            ..Default::default()
+        };
+        let first = export_backup_with_key(&contents, &key).unwrap();
+        let second = export_backup_with_key(&contents, &key).unwrap();
+        // Fresh nonce every time
+        assert_ne!(first, second);
+        assert_eq!(
+            import_backup(&first, "password1234")
+                .unwrap()
+                .accounts
+                .len(),
+            2
+        );
+        assert!(import_backup(&second, "wrong-password").is_err());
+    }
+
+    fn sample_contents() -> BackupContents {
+        BackupContents {
+            accounts: sample_accounts(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_recipient_backup_opens_with_each_identity() {
+        let alice = Identity::generate();
+        let bob = Identity::generate();
+        let exported =
+            export_backup_to_recipients(&sample_contents(), &[alice.recipient(), bob.recipient()])
+                .unwrap();
+
+        for identity in [&alice, &bob] {
+            let contents = import_backup_with_identity(&exported, identity).unwrap();
+            assert_eq!(contents.accounts.len(), 2);
+            assert_eq!(contents.accounts[0].secret, "JBSWY3DPEHPK3PXP");
+        }
+    }
+
+    #[test]
+    fn test_recipient_backup_rejects_wrong_identity() {
+        let exported =
+            export_backup_to_recipients(&sample_contents(), &[Identity::generate().recipient()])
+                .unwrap();
+        let err = import_backup_with_identity(&exported, &Identity::generate()).unwrap_err();
+        assert!(err.contains("not encrypted to this recovery key"));
+
+        // And it can't be opened with a password at all
+        let err = import_backup(&exported, "password1234").unwrap_err();
+        assert!(err.contains("recovery key"));
+    }
+
+    #[test]
+    fn test_identity_rejects_password_backup() {
+        let exported =
+            export_backup(&sample_contents(), "password1234", KdfPreset::Standard).unwrap();
+        let err = import_backup_with_identity(&exported, &Identity::generate()).unwrap_err();
+        assert!(err.contains("protected by a password"));
+    }
+
+    #[test]
+    fn test_recipient_stanza_is_authenticated() {
+        let identity = Identity::generate();
+        let exported =
+            export_backup_to_recipients(&sample_contents(), &[identity.recipient()]).unwrap();
+        let header_len = u32::from_be_bytes(exported[5..9].try_into().unwrap()) as usize;
+        let mut header: BackupHeader =
+            serde_json::from_slice(&exported[9..9 + header_len]).unwrap();
+
+        // Point the stanza at a different ephemeral key
+        header.recipients[0].ephemeral =
+            data_encoding::BASE64.encode(Identity::generate().recipient().0.as_bytes());
+        let header_json = serde_json::to_vec(&header).unwrap();
+        let mut tampered = exported[..5].to_vec();
+        tampered.extend_from_slice(&(header_json.len() as u32).to_be_bytes());
+        tampered.extend_from_slice(&header_json);
+        tampered.extend_from_slice(&exported[9 + header_len..]);
+        assert!(import_backup_with_identity(&tampered, &identity).is_err());
+    }
+
+    #[test]
+    fn test_recipient_requires_at_least_one_key() {
+        assert!(export_backup_to_recipients(&sample_contents(), &[]).is_err());
+    }
+
+    #[test]
+    fn test_key_strings_roundtrip() {
+        let identity = Identity::generate();
+        let public = identity.recipient().to_string();
+        assert!(public.starts_with("ghostpub1"));
+        assert_eq!(Recipient::parse(&public).unwrap(), identity.recipient());
+        assert_eq!(
+            Recipient::parse(&format!("  {}\n", public.to_uppercase())).unwrap(),
+            identity.recipient()
+        );
+
+        let secret = identity.to_secret_string();
+        assert!(secret.starts_with("GHOST-SECRET-KEY-1"));
+        let parsed = Identity::parse(&secret.to_lowercase()).unwrap();
+        assert_eq!(parsed.recipient(), identity.recipient());
+
+        assert!(Recipient::parse("ghostpub1abc").is_err());
+        assert!(Recipient::parse(&secret).is_err());
+        assert!(Identity::parse(&public).is_err());
+    }
+
+    #[test]
+    fn test_kdf_presets_are_importable() {
+        let presets = [KdfPreset::Standard, KdfPreset::Strong, KdfPreset::Maximum];
+        for pair in presets.windows(2) {
+            let (weaker, stronger) = (pair[0].kdf(&[0u8; 16]), pair[1].kdf(&[0u8; 16]));
+            assert!(stronger.memory_kib > weaker.memory_kib);
+            assert!(stronger.iterations >= weaker.iterations);
+        }
+        for preset in presets {
+            assert!(validate_kdf(&preset.kdf(&[0u8; 16])).is_ok());
+        }
+        // Version 1 backups were written with what is now the standard preset
+        assert_eq!(KdfPreset::Standard.kdf(&[1u8; 16]), v1_kdf(&[1u8; 16]));
+    }
+
+    #[test]
+    fn test_export_records_kdf_in_header() {
+        let exported =
+            export_backup(&sample_contents(), "password1234", KdfPreset::Standard).unwrap();
+        let header_len = u32::from_be_bytes(exported[5..9].try_into().unwrap()) as usize;
+        let header: BackupHeader = serde_json::from_slice(&exported[9..9 + header_len]).unwrap();
+        let kdf = header.kdf.unwrap();
+        assert_eq!(kdf.memory_kib, 64 * 1024);
+        assert_eq!(kdf.iterations, 3);
+        assert_eq!(kdf.parallelism, 1);
+        assert!(header.recipients.is_empty());
+    }
+
+    #[test]
+    fn test_validate_kdf_bounds() {
+        let kdf = KdfPreset::Standard.kdf(&[0u8; 16]);
+        assert!(validate_kdf(&kdf).is_ok());
+        assert!(validate_kdf(&KdfParams {
+            memory_kib: 4 * 1024 * 1024,
+            ..kdf.clone()
+        })
+        .is_err());
+        assert!(validate_kdf(&KdfParams {
+            iterations: 0,
+            ..kdf.clone()
+        })
+        .is_err());
+        assert!(validate_kdf(&KdfParams {
+            algorithm: "scrypt".into(),
+            ..kdf
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_unsupported_version_fails() {
+        let mut exported =
+            export_backup(&sample_contents(), "password1234", KdfPreset::Standard).unwrap();
+        exported[4] = 9;
+        let err = import_backup(&exported, "password1234").unwrap_err();
+        assert!(err.contains("Unsupported backup version"));
+    }
+
+    /// Golden file test for format version 2, created by `encrypt_v2` with a
+    /// fixed salt and nonce. If this test breaks, v2 backups in the wild will
+    /// be unreadable.
+    #[test]
+    fn test_golden_file_import_v2() {
+        let hex = "47485354020000008f7b226b6466223a7b22616c676f726974686d223a226172676f6e326964222c226d656d6f72795f6b6962223a36353533362c22697465726174696f6e73223a332c22706172616c6c656c69736d223a312c2273616c74223a22427763484277634842776348427763484277634842773d3d227d2c226e6f6e6365223a2243516b4a43516b4a43516b4a43516b4a227d8b9967a470801930556c56528377659f33995224f66924a56beda21e3a2625b1586fd9cba44400564f51ae87fae8ff065c67d7cd97a6c7d4ea2cd13960d166ecfa9a9288ed8eb2bb62945c143c169ae271ebce14b0be33e8d00caba46436d86e1b22ce43e6b2a2a679b453107be2348ffe2518b39a16246629df0693b9f824bc3523dc430cd87e4ab4a21abd2968a4adbffe5a150c643368911428a0efb7a0d391f44b1510c300caa1e47b9a417b519bccdb2a292fb3899b8451201cecfd8923796de948ce7df7610f5448362f74194faa625be3e48db2c64675dee295cd9a86996f6ffcd19136ba8ce93b8a866f437994e5c5b23732d6c491f337968aa20b144251e09a0da70ecfab2bac00db6ceca8f074c4db9a6502eee62a6ecd72f1cf47f367f77ee15b564ad018e1ef134153099414c7c3a558531c64aa23c0dab145a70f6cc6704e23b1a7dbf2f0cbaf9415641e63d8c51ee4c4738a8431e9535ab2a156ec3763de2ec4e122f19540c8f659292a45b5ace28c373ee30ee32c509b7f90c081a179ce74a58c7a416e325a1bf3284005d318caa624473d875d4a1d1c2c50d2239b15abb9a146021a7b01e20ee6771409fc409f4cdd631ca59a75ad";
+        let data = from_hex(hex);
+
+        let contents = import_backup(&data, "ghost-test-password-1234").unwrap();
+
+        assert_eq!(contents.exported_at, 1700000100);
+        assert_eq!(contents.accounts.len(), 1);
+        let account = &contents.accounts[0];
+        assert_eq!(account.id, "a1b2c3d4");
+        assert_eq!(account.issuer, "GitHub");
+        assert_eq!(account.label, "user@example.com");
+        assert_eq!(account.secret, "JBSWY3DPEHPK3PXP");
+        assert_eq!(account.algorithm, "SHA1");
+        assert_eq!(account.digits, 6);
+        assert_eq!(account.period, 30);
+        assert_eq!(account.icon, Some("github".into()));
+        assert_eq!(account.last_modified, 1700000000);
+        assert_eq!(account.tags, vec!["work"]);
+
+        assert_eq!(contents.tombstones.len(), 1);
+        assert_eq!(contents.tombstones[0].id, "deleted1");
+        assert_eq!(contents.tombstones[0].deleted_at, 1700000050);
+        assert_eq!(contents.meta.usage.get("a1b2c3d4"), Some(&5));
+        assert_eq!(contents.meta.last_backup_at, Some(1699990000));
+        assert_eq!(
+            contents.settings,
+            Some(Settings {
+                tombstone_max_retention_days: 200,
+                ..Default::default()
+            })
+        );
+
+        // The fixed salt and nonce must still produce the same bytes from
+        // the payload as it was serialized when this file was made
+        let key = BackupKey::derive_with_salt(
+            "ghost-test-password-1234",
+            KdfPreset::Standard,
+            &[7u8; 16],
+        )
+        .unwrap();
+        let reencoded = seal_v2(
+            GOLDEN_V2_PLAINTEXT.as_bytes(),
+            &golden_v2_header(&key),
+            &key.key,
+        )
+        .unwrap();
+        assert_eq!(reencoded, data);
+    }
+
+    /// Golden file for the current v2 payload, which has gained settings and
+    /// metadata fields since the first one. Add a new vector rather than
+    /// updating this one when the payload changes again.
+    #[test]
+    fn test_golden_file_import_v2_current_payload() {
+        let hex = "47485354020000008f7b226b6466223a7b22616c676f726974686d223a226172676f6e326964222c226d656d6f72795f6b6962223a36353533362c22697465726174696f6e73223a332c22706172616c6c656c69736d223a312c2273616c74223a22427763484277634842776348427763484277634842773d3d227d2c226e6f6e6365223a2243516b4a43516b4a43516b4a43516b4a227d8b9967a470801930556c56528377659f33995224f66924a56beda21e3a2625b1586fd9cba44400564f51ae87fae8ff065c67d7cd97a6c7d4ea2cd13960d166ecfa9a9288ed8eb2bb62945c143c169ae271ebce14b0be33e8d00caba46436d86e1b22ce43e6b2a2a679b453107be2348ffe2518b39a16246629df0693b9f824bc3523dc430cd87e4ab4a21abd2968a4adbffe5a150c643368911428a0efb7a0d391f44b1510c300caa1e47b9a417b519bccdb2a292fb3899b8451201cecfd8923796de948ce7df7610f5448362f74194faa625be3e48db2c64675dee295cd9a86996f6ffcd19136ba8ce93b8a866f437994e5c5b23732d6c491f337968aa20b144251e09a0da70ecfab2bac00db6ceca8f074c4db9a6502eee62a6ecd72f1cf47f367f77ee15b564ad018e1ef134153099414c7c3a558531c64aa23c0dab145a70f6cc6704e23b1a7dbf2f0cbaf9415641e63d8c51ee4c4738a8431e9535ab2a156ec3763de2ec4e122f19540c8f659292a45b5ace28c373ee30ee37d5eda6494da92ad64f674eed86206453f561aeb295f498c77daeb7e6b2a965d461d0f361d8607dd00bda7e10f44452519c0794fb8bd027c34e9a5403750b47bcdfdc723a5e0ed05f5cebd303e1d3bc5ed08ed3ff2a8452bedda0ed0eb5565ae1dc526e8248bcc978d9a71def065ce67ba09b3e0668a865158a7a29b1041462d32b8ed5a550270b7342ae17faa45878eb60d2b800f5f2cfb0c38d3ea7e8dc4e3998fcddd5a0e4678cb72e269f842be342263c6dc91ee5377dc0cbcf407809857655b9fb26654e7a4dda06f63d45186af0f087b2a8572050dd88aa989ef2aa0676172f3fb00579c3c8cca225b668f70d241405cbccdec6fc583fda9b730a5a5287c077756bed05b6c288bdc9778cc78186175ea02ea0ca40cde78a4bdaaa5393b47ce4ba4a780ad8e90c490cee0dd8d7cf91f61a2a0a47f46";
+        let data = from_hex(hex);
+
+        let contents = import_backup(&data, "ghost-test-password-1234").unwrap();
+        let payload = golden_v2_payload();
+        assert_eq!(contents.exported_at, payload.exported_at);
+        assert_eq!(contents.accounts.len(), 1);
+        assert_eq!(contents.accounts[0].secret, "JBSWY3DPEHPK3PXP");
+        assert_eq!(contents.tombstones.len(), 1);
+        assert_eq!(contents.meta.usage.get("a1b2c3d4"), Some(&5));
+        assert_eq!(contents.settings, payload.settings);
+    }
 }
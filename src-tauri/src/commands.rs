@@ -1,4 +1,6 @@
+use crate::duplicates::{self, DuplicateKind, ImportMatch};
 use crate::pin::PinManager;
+use crate::search;
 use crate::storage::{Account, Storage};
 use crate::totp;
 use serde::{Deserialize, Serialize};
@@ -48,6 +50,7 @@ pub struct AccountDisplay {
     pub digits: u32,
     pub period: u32,
     pub icon: Option<String>,
+    pub tags: Vec<String>,
 }
 
 impl From<Account> for AccountDisplay {
@@ -60,6 +63,7 @@ impl From<Account> for AccountDisplay {
             digits: a.digits,
             period: a.period,
             icon: a.icon,
+            tags: a.tags,
         }
     }
 }
@@ -108,6 +112,7 @@ pub fn add_account_manual(
     {
         return Err("Secret key is not valid Base32".to_string());
     }
+    let icon = crate::icons::resolve(&issuer, &label).map(str::to_string);
     let account = Account {
         id: uuid::Uuid::new_v4().to_string(),
         issuer,
@@ -116,8 +121,9 @@ pub fn add_account_manual(
         algorithm,
         digits,
         period,
-        icon: None,
+        icon,
         last_modified: 0,
+        tags: Vec::new(),
     };
 
     // Validate by trying to generate a code
@@ -150,6 +156,13 @@ pub fn generate_code(
     totp::generate_code(account)
 }
 
+#[tauri::command]
+pub fn get_issuer_icon(slug: String) -> Result<String, String> {
+    crate::icons::icon_svg(&slug)
+        .map(str::to_string)
+        .ok_or_else(|| "Unknown icon".to_string())
+}
+
 #[tauri::command]
 pub fn generate_all_codes(
     storage: State<Mutex<Storage>>,
@@ -160,95 +173,485 @@ pub fn generate_all_codes(
 
 // --- Shared helpers ---
 
-fn deduplicate_and_import(
-    accounts: Vec<Account>,
+/// A vault account and an imported account with the same issuer and label
+/// but a different secret or parameters.
+#[derive(Serialize, Clone)]
+pub struct ImportConflict {
+    /// The imported entry, which decisions refer to. Several entries can
+    /// conflict with the same vault account.
+    pub entry: usize,
+    /// The vault account's id.
+    pub account_id: String,
+    pub existing: AccountDisplay,
+    pub imported: AccountDisplay,
+    /// Which of "secret", "algorithm", "digits" and "period" differ.
+    pub changed: Vec<String>,
+}
+
+impl ImportConflict {
+    fn new(entry: usize, existing: &Account, imported: Account) -> Self {
+        let mut changed = Vec::new();
+        if totp::decode_secret(&existing.secret) != totp::decode_secret(&imported.secret) {
+            changed.push("secret".to_string());
+        }
+        if existing.algorithm != imported.algorithm {
+            changed.push("algorithm".to_string());
+        }
+        if existing.digits != imported.digits {
+            changed.push("digits".to_string());
+        }
+        if existing.period != imported.period {
+            changed.push("period".to_string());
+        }
+        Self {
+            entry,
+            account_id: existing.id.clone(),
+            existing: AccountDisplay::from(existing.clone()),
+            imported: AccountDisplay::from(imported),
+            changed,
+        }
+    }
+}
+
+/// How to settle one import conflict.
+#[derive(Deserialize)]
+pub struct ImportDecision {
+    /// `ImportConflict::entry` of the conflict.
+    pub entry: usize,
+    /// "keep_existing", "replace" or "keep_both".
+    pub action: String,
+}
+
+/// Import accounts, each with its entry number, into the vault. Accounts
+/// identical to a vault account are skipped. A conflict with a vault account
+/// is settled by the decision for that entry: "keep_existing" skips the
+/// imported account, "replace" overwrites the vault account in place (keeping
+/// its id, so sync updates it on other devices) and "keep_both", the default,
+/// adds it alongside. Only one entry may replace a given vault account.
+/// Returns the added and replaced accounts.
+fn import_accounts(
+    accounts: impl IntoIterator<Item = (usize, Account)>,
+    decisions: &[ImportDecision],
     storage: &mut Storage,
 ) -> Result<Vec<AccountDisplay>, String> {
-    let existing: Vec<(String, String, String)> = storage
-        .list()
+    let vault = storage.list().to_vec();
+    let decision_map: std::collections::HashMap<usize, &str> = decisions
         .iter()
-        .map(|e| (e.issuer.clone(), e.label.clone(), e.secret.clone()))
+        .map(|d| (d.entry, d.action.as_str()))
         .collect();
-    let mut added = Vec::new();
 
-    for account in accounts {
-        let is_duplicate = existing
-            .iter()
-            .any(|e| e.0 == account.issuer && e.1 == account.label && e.2 == account.secret);
-        if !is_duplicate {
-            let display = AccountDisplay::from(account.clone());
-            let mut new_account = account;
-            new_account.id = uuid::Uuid::new_v4().to_string();
-            storage.add(new_account)?;
-            added.push(display);
+    // One save for the whole batch; nothing is imported if any change fails
+    storage.transaction(|storage| {
+        let mut imported = Vec::new();
+        // Vault account id -> the entry replacing it
+        let mut replaced: std::collections::HashMap<String, usize> =
+            std::collections::HashMap::new();
+        for (entry, mut account) in accounts {
+            crate::icons::apply(&mut account);
+            match duplicates::match_import(&account, &vault) {
+                (ImportMatch::Identical, _) => continue,
+                (ImportMatch::Conflict, Some(existing)) => match decision_map.get(&entry) {
+                    Some(&"keep_existing") => continue,
+                    Some(&"replace") => {
+                        if let Some(first) = replaced.insert(existing.id.clone(), entry) {
+                            return Err(format!(
+                                "Entries {} and {} both replace {}; choose one",
+                                first + 1,
+                                entry + 1,
+                                existing.issuer
+                            ));
+                        }
+                        let replacement = Account {
+                            id: existing.id.clone(),
+                            icon: account.icon.or_else(|| existing.icon.clone()),
+                            tags: if account.tags.is_empty() {
+                                existing.tags.clone()
+                            } else {
+                                account.tags
+                            },
+                            last_modified: crate::storage::now_secs(),
+                            ..account
+                        };
+                        imported.push(AccountDisplay::from(replacement.clone()));
+                        storage.replace_account(replacement)?;
+                        continue;
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+
+            account.id = uuid::Uuid::new_v4().to_string();
+            imported.push(AccountDisplay::from(account.clone()));
+            storage.add(account)?;
         }
-    }
-    Ok(added)
+        Ok(imported)
+    })
 }
 
 // --- Backup commands ---
 
+#[derive(Serialize)]
+pub struct PasswordCheck {
+    #[serde(flatten)]
+    pub strength: crate::password_strength::PasswordStrength,
+    pub min_score: u8,
+    /// Whether the password meets the backup password policy.
+    pub acceptable: bool,
+}
+
+/// Score a prospective backup password and check it against the policy.
 #[tauri::command]
-pub fn export_backup(password: String, storage: State<Mutex<Storage>>) -> Result<Vec<u8>, String> {
+pub fn check_password_strength(
+    mut password: String,
+    app_handle: tauri::AppHandle,
+) -> Result<PasswordCheck, String> {
+    use tauri::Manager;
+    let data_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|_| "Failed to resolve data directory".to_string())?;
+    let min_score = crate::settings::Settings::load(&data_dir).backup_min_password_score;
+    let strength = crate::password_strength::estimate(&password);
+    let acceptable = crate::password_strength::check_policy(&password, min_score).is_ok();
+    password.zeroize();
+    Ok(PasswordCheck {
+        strength,
+        min_score,
+        acceptable,
+    })
+}
+
+#[tauri::command]
+pub fn export_backup(
+    password: String,
+    storage: State<Mutex<Storage>>,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<u8>, String> {
+    use tauri::Manager;
+    let data_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|_| "Failed to resolve data directory".to_string())?;
+
+    let settings = crate::settings::Settings::load(&data_dir);
+    crate::password_strength::check_policy(&password, settings.backup_min_password_score)?;
     let storage = lock_storage(&storage)?;
-    let accounts = storage.list();
-    let result = crate::backup::export_accounts(accounts, &password)?;
+    let contents = crate::backup::BackupContents::from_vault(&storage, &settings);
+    let result = crate::backup::export_backup(&contents, &password, settings.backup_kdf_preset)?;
     tracing::info!(
         event = "backup_exported",
-        count = accounts.len(),
+        count = contents.accounts.len(),
         "Backup exported"
     );
     Ok(result)
 }
 
+/// Export a backup that opens with the private key of any of the given
+/// recovery public keys instead of a password.
+#[tauri::command]
+pub fn export_backup_to_recipients(
+    recipients: Vec<String>,
+    storage: State<Mutex<Storage>>,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<u8>, String> {
+    use tauri::Manager;
+    let data_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|_| "Failed to resolve data directory".to_string())?;
+    let recipients = recipients
+        .iter()
+        .map(|r| crate::backup::Recipient::parse(r))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let settings = crate::settings::Settings::load(&data_dir);
+    let storage = lock_storage(&storage)?;
+    let contents = crate::backup::BackupContents::from_vault(&storage, &settings);
+    let result = crate::backup::export_backup_to_recipients(&contents, &recipients)?;
+    tracing::info!(
+        event = "backup_exported",
+        count = contents.accounts.len(),
+        recipients = recipients.len(),
+        "Backup exported to recovery keys"
+    );
+    Ok(result)
+}
+
+#[derive(Serialize)]
+pub struct RecoveryKeypair {
+    pub public_key: String,
+    /// Shown once for the user to store offline; never saved by the app.
+    pub private_key: String,
+}
+
+#[tauri::command]
+pub fn generate_recovery_keypair() -> RecoveryKeypair {
+    let identity = crate::backup::Identity::generate();
+    tracing::info!(
+        event = "recovery_keypair_generated",
+        "Recovery keypair generated"
+    );
+    RecoveryKeypair {
+        public_key: identity.recipient().to_string(),
+        private_key: identity.to_secret_string().to_string(),
+    }
+}
+
+/// Decrypt a backup with its password, a recovery private key, or the
+/// shares of a recovery kit, depending on how it was protected.
+fn open_backup(
+    data: &[u8],
+    password: Option<String>,
+    identity: Option<String>,
+    shares: Option<Vec<String>>,
+) -> Result<crate::backup::BackupContents, String> {
+    match (password, identity, shares) {
+        (_, _, Some(mut shares)) => {
+            let result = crate::recovery_kit::open_kit(data, &shares);
+            shares.iter_mut().for_each(|share| share.zeroize());
+            result
+        }
+        (_, Some(mut identity), None) => {
+            let parsed = crate::backup::Identity::parse(&identity);
+            identity.zeroize();
+            crate::backup::import_backup_with_identity(data, &parsed?)
+        }
+        (Some(password), None, None) => crate::backup::import_backup(data, &password),
+        (None, None, None) => {
+            Err("Enter the backup password, recovery key, or recovery kit shares".to_string())
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct RecoveryKitView {
+    pub kit_id: String,
+    pub threshold: u8,
+    /// One printable share per custodian; each string is also the QR payload.
+    pub shares: Vec<String>,
+    pub backup: Vec<u8>,
+}
+
+/// Export a backup that opens with any `threshold` of `share_count`
+/// custodian shares.
+#[tauri::command]
+pub fn create_recovery_kit(
+    threshold: u8,
+    share_count: u8,
+    storage: State<Mutex<Storage>>,
+    app_handle: tauri::AppHandle,
+) -> Result<RecoveryKitView, String> {
+    use tauri::Manager;
+    let data_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|_| "Failed to resolve data directory".to_string())?;
+
+    let settings = crate::settings::Settings::load(&data_dir);
+    let storage = lock_storage(&storage)?;
+    let contents = crate::backup::BackupContents::from_vault(&storage, &settings);
+    let kit = crate::recovery_kit::create_kit(&contents, threshold, share_count)?;
+    tracing::info!(
+        event = "recovery_kit_created",
+        kit_id = %kit.kit_id,
+        threshold,
+        share_count,
+        "Recovery kit created"
+    );
+    Ok(RecoveryKitView {
+        kit_id: kit.kit_id,
+        threshold: kit.threshold,
+        shares: kit.shares,
+        backup: kit.backup,
+    })
+}
+
+/// How many shares a recovery kit backup needs, or `None` for other backups.
+#[tauri::command]
+pub fn get_recovery_kit_info(data: Vec<u8>) -> Result<Option<crate::backup::KeySplit>, String> {
+    crate::backup::key_split(&data)
+}
+
+/// Render a printable paper backup of the given accounts, or all of them.
+/// With a password, the sheet also carries the encrypted backup as QR codes.
+/// The sheet shows plaintext secrets, so the user must have confirmed the
+/// warning and, when a PIN is set, entered it again.
+#[tauri::command]
+pub fn generate_paper_backup(
+    account_ids: Option<Vec<String>>,
+    password: Option<String>,
+    confirmed: bool,
+    pin: Option<String>,
+    storage: State<Mutex<Storage>>,
+    pin_manager: State<PinManager>,
+    app_handle: tauri::AppHandle,
+) -> Result<crate::paper_backup::PaperBackup, String> {
+    use tauri::Manager;
+    if !confirmed {
+        return Err("Confirm that the paper backup will show your secrets unencrypted".to_string());
+    }
+    reverify_pin(&pin_manager, pin)?;
+    let data_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|_| "Failed to resolve data directory".to_string())?;
+
+    let settings = crate::settings::Settings::load(&data_dir);
+    let storage = lock_storage(&storage)?;
+    let mut contents = crate::backup::BackupContents::from_vault(&storage, &settings);
+    if let Some(ids) = &account_ids {
+        contents.accounts.retain(|a| ids.contains(&a.id));
+        if contents.accounts.is_empty() {
+            return Err("Select at least one account".to_string());
+        }
+    }
+
+    let encrypted = match password {
+        Some(mut password) => {
+            let result = crate::password_strength::check_policy(
+                &password,
+                settings.backup_min_password_score,
+            )
+            .and_then(|()| {
+                crate::backup::export_backup(&contents, &password, settings.backup_kdf_preset)
+            });
+            password.zeroize();
+            Some(result?)
+        }
+        None => None,
+    };
+    let sheet = crate::paper_backup::generate(
+        &contents.accounts,
+        encrypted.as_deref(),
+        contents.exported_at,
+    )?;
+    tracing::info!(
+        event = "paper_backup_generated",
+        count = sheet.account_count,
+        backup_parts = sheet.backup_parts,
+        "Paper backup generated"
+    );
+    Ok(sheet)
+}
+
+/// Rebuild the encrypted backup from a paper backup's scanned QR codes. The
+/// result opens like a backup file.
+#[tauri::command]
+pub fn assemble_paper_backup(parts: Vec<String>) -> Result<Vec<u8>, String> {
+    crate::paper_backup::assemble_backup(&parts)
+}
+
+/// Whether the vault's accounts, or the given subset, match the checksum
+/// printed on a paper backup.
+#[tauri::command]
+pub fn verify_paper_backup_checksum(
+    checksum: String,
+    account_ids: Option<Vec<String>>,
+    storage: State<Mutex<Storage>>,
+) -> Result<bool, String> {
+    let storage = lock_storage(&storage)?;
+    let accounts: Vec<_> = storage
+        .list()
+        .iter()
+        .filter(|a| account_ids.as_ref().is_none_or(|ids| ids.contains(&a.id)))
+        .cloned()
+        .collect();
+    let expected = checksum.trim().to_uppercase();
+    Ok(crate::paper_backup::accounts_checksum(&accounts) == expected)
+}
+
+/// Check that a backup opens and compare it with the vault, without
+/// importing anything.
+#[tauri::command]
+pub fn verify_backup(
+    data: Vec<u8>,
+    password: Option<String>,
+    identity: Option<String>,
+    shares: Option<Vec<String>>,
+    storage: State<Mutex<Storage>>,
+) -> Result<crate::backup::BackupVerification, String> {
+    let version = crate::backup::backup_version(&data)?;
+    let contents = open_backup(&data, password, identity, shares)?;
+    let storage = lock_storage(&storage)?;
+    let report = crate::backup::verify_contents(version, &contents, storage.list());
+    tracing::info!(
+        event = "backup_verified",
+        count = report.account_count,
+        missing = report.missing,
+        different = report.different,
+        "Backup verified"
+    );
+    Ok(report)
+}
+
 #[derive(Serialize)]
 pub struct BackupPreview {
+    /// Accounts not yet in the vault.
     pub accounts: Vec<AccountDisplay>,
     pub duplicates: usize,
+    pub conflicts: Vec<ImportConflict>,
+    /// When the backup was made (Unix seconds).
+    pub exported_at: u64,
 }
 
 #[tauri::command]
 pub fn import_backup(
     data: Vec<u8>,
-    password: String,
+    password: Option<String>,
+    identity: Option<String>,
+    shares: Option<Vec<String>>,
     storage: State<Mutex<Storage>>,
 ) -> Result<BackupPreview, String> {
-    let accounts = crate::backup::import_accounts(&data, &password)?;
+    let contents = open_backup(&data, password, identity, shares)?;
     let storage = lock_storage(&storage)?;
-    let existing: Vec<(&str, &str, &str)> = storage
-        .list()
-        .iter()
-        .map(|e| (e.issuer.as_str(), e.label.as_str(), e.secret.as_str()))
-        .collect();
 
     let mut new_accounts = Vec::new();
     let mut duplicates = 0usize;
-    for account in accounts {
-        let is_dup = existing
-            .iter()
-            .any(|e| e.0 == account.issuer && e.1 == account.label && e.2 == account.secret);
-        if is_dup {
-            duplicates += 1;
-        } else {
-            new_accounts.push(AccountDisplay::from(account));
+    let mut conflicts = Vec::new();
+    // Entries of a backup are numbered by position
+    for (entry, account) in contents.accounts.into_iter().enumerate() {
+        match duplicates::match_import(&account, storage.list()) {
+            (ImportMatch::Identical, _) => duplicates += 1,
+            (ImportMatch::Conflict, Some(existing)) => {
+                conflicts.push(ImportConflict::new(entry, existing, account));
+            }
+            _ => new_accounts.push(AccountDisplay::from(account)),
         }
     }
 
     Ok(BackupPreview {
         accounts: new_accounts,
         duplicates,
+        conflicts,
+        exported_at: contents.exported_at,
     })
 }
 
 #[tauri::command]
 pub fn import_backup_confirm(
     data: Vec<u8>,
-    password: String,
+    password: Option<String>,
+    identity: Option<String>,
+    shares: Option<Vec<String>>,
+    decisions: Option<Vec<ImportDecision>>,
     storage: State<Mutex<Storage>>,
+    app_handle: tauri::AppHandle,
 ) -> Result<Vec<AccountDisplay>, String> {
-    let accounts = crate::backup::import_accounts(&data, &password)?;
+    let contents = open_backup(&data, password, identity, shares)?;
+    let decisions = decisions.unwrap_or_default();
     let mut storage = lock_storage(&storage)?;
-    let added = deduplicate_and_import(accounts, &mut storage)?;
+    // Restore tombstones too, so a later sync doesn't resurrect accounts
+    // that were deleted before the backup was made
+    let added = storage.transaction(|storage| {
+        let entries = contents.accounts.into_iter().enumerate();
+        let added = import_accounts(entries, &decisions, storage)?;
+        storage.restore_metadata(&contents.tombstones, &contents.meta)?;
+        Ok(added)
+    })?;
+    drop(storage);
+    restore_settings(contents.settings, &app_handle)?;
+
     tracing::info!(
         event = "backup_imported",
         count = added.len(),
@@ -257,25 +660,134 @@ pub fn import_backup_confirm(
     Ok(added)
 }
 
-// --- Backup file save (mobile-compatible) ---
+/// Settings are only restored onto a device that hasn't configured any.
+fn restore_settings(
+    settings: Option<crate::settings::Settings>,
+    app_handle: &tauri::AppHandle,
+) -> Result<(), String> {
+    use tauri::Manager;
+    if let Some(settings) = settings.filter(|s| s.validate().is_ok()) {
+        let data_dir = app_handle
+            .path()
+            .app_data_dir()
+            .map_err(|_| "Failed to resolve data directory".to_string())?;
+        if !crate::settings::Settings::is_saved(&data_dir) {
+            settings.restorable().save(&data_dir)?;
+        }
+    }
+    Ok(())
+}
+
+/// Merge a backup's accounts into the vault with the sync merge engine,
+/// keeping their original ids and modification times, so restoring onto a
+/// device that syncs doesn't duplicate accounts on its peers.
+fn merge_backup(
+    storage: &Storage,
+    contents: &crate::backup::BackupContents,
+) -> crate::sync::MergeResult {
+    // The vault and the backup last agreed when the backup was made, so an
+    // account changed on both sides since then is a conflict
+    let mut merge_result = crate::sync::merge(
+        storage.list(),
+        storage.tombstones(),
+        contents.accounts.clone(),
+        &contents.tombstones,
+        Some(contents.exported_at),
+    );
+    // Accounts in a backup made before tombstones were pruned here may have
+    // been deleted since, so hold them back like a stale peer's
+    crate::sync::flag_stale_peer(
+        &mut merge_result,
+        Some(contents.exported_at),
+        storage.tombstones_pruned_through(),
+    );
+    merge_result
+}
 
+/// Preview a merge-aware restore: the same changes and decisions as a sync.
 #[tauri::command]
-pub fn save_backup_file(data: Vec<u8>, app_handle: tauri::AppHandle) -> Result<String, String> {
-    use tauri::Manager;
+pub fn restore_backup_preview(
+    data: Vec<u8>,
+    password: Option<String>,
+    identity: Option<String>,
+    shares: Option<Vec<String>>,
+    storage: State<Mutex<Storage>>,
+) -> Result<MergePreview, String> {
+    let contents = open_backup(&data, password, identity, shares)?;
+    let storage = lock_storage(&storage)?;
+    Ok(merge_result_to_preview(&merge_backup(&storage, &contents)))
+}
 
+/// Apply a merge-aware restore with the user's decisions from the preview.
+#[tauri::command]
+pub fn restore_backup_confirm(
+    data: Vec<u8>,
+    password: Option<String>,
+    identity: Option<String>,
+    shares: Option<Vec<String>>,
+    decisions: Vec<MergeDecision>,
+    storage: State<Mutex<Storage>>,
+    app_handle: tauri::AppHandle,
+) -> Result<SyncConfirmResult, String> {
+    let contents = open_backup(&data, password, identity, shares)?;
+    let mut storage = lock_storage(&storage)?;
+    let result = storage.transaction(|storage| {
+        let merge_result = merge_backup(storage, &contents);
+        let result = apply_merge(storage, merge_result, &decisions)?;
+        storage.restore_metadata(&contents.tombstones, &contents.meta)?;
+        Ok(result)
+    })?;
+    drop(storage);
+    restore_settings(contents.settings, &app_handle)?;
+
+    tracing::info!(
+        event = "backup_restored",
+        added = result.added,
+        updated = result.updated,
+        deleted = result.deleted,
+        "Backup restored with merge"
+    );
+    Ok(result)
+}
+
+// --- Backup file save (mobile-compatible) ---
+
+/// Save an exported backup. The backup only counts as taken once the file
+/// has been written.
+#[tauri::command]
+pub fn save_backup_file(
+    data: Vec<u8>,
+    storage: State<Mutex<Storage>>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, String> {
+    crate::backup::backup_version(&data)?;
     let timestamp = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap_or_default()
         .as_secs();
     let filename = format!("ghost-auth-backup-{}.ghostauth", timestamp);
+    let path = save_export_file(&data, &filename, "application/octet-stream", &app_handle)?;
+    lock_storage(&storage)?.record_backup()?;
+    Ok(path)
+}
+
+/// Save an exported file where the user can reach it: the share sheet on
+/// iOS, the Downloads directory elsewhere.
+fn save_export_file(
+    data: &[u8],
+    filename: &str,
+    #[cfg_attr(not(target_os = "ios"), allow(unused_variables))] mime_type: &str,
+    app_handle: &tauri::AppHandle,
+) -> Result<String, String> {
+    use tauri::Manager;
 
     #[cfg(target_os = "ios")]
     {
         // iOS: write to temp dir, then present the native share sheet
         let temp_dir = std::env::temp_dir();
-        let path = temp_dir.join(&filename);
+        let path = temp_dir.join(filename);
 
-        std::fs::write(&path, &data).map_err(|e| {
+        std::fs::write(&path, data).map_err(|e| {
             tracing::error!(error = %e, "Failed to write backup file to temp dir");
             "Failed to save backup".to_string()
         })?;
@@ -285,7 +797,7 @@ pub fn save_backup_file(data: Vec<u8>, app_handle: tauri::AppHandle) -> Result<S
         use tauri_plugin_share_file::ShareFileExt;
         app_handle
             .share_file_plugin()
-            .share_file(&path_str, "application/octet-stream")
+            .share_file(&path_str, mime_type)
             .map_err(|e| {
                 tracing::error!(error = %e, "Failed to present share sheet");
                 "Failed to share backup file".to_string()
@@ -307,9 +819,9 @@ pub fn save_backup_file(data: Vec<u8>, app_handle: tauri::AppHandle) -> Result<S
             "Failed to save backup".to_string()
         })?;
 
-        let path = backup_dir.join(&filename);
+        let path = backup_dir.join(filename);
 
-        std::fs::write(&path, &data).map_err(|e| {
+        std::fs::write(&path, data).map_err(|e| {
             tracing::error!(error = %e, "Failed to write backup file");
             "Failed to save backup".to_string()
         })?;
@@ -326,6 +838,58 @@ pub fn save_backup_file(data: Vec<u8>, app_handle: tauri::AppHandle) -> Result<S
     }
 }
 
+/// Save a paper backup sheet. `full_vault` says whether the sheet holds every
+/// account, in which case it counts as a backup of the vault.
+#[tauri::command]
+pub fn save_paper_backup_file(
+    html: String,
+    full_vault: bool,
+    storage: State<Mutex<Storage>>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, String> {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let filename = format!("ghost-auth-paper-backup-{}.html", timestamp);
+    let path = save_export_file(html.as_bytes(), &filename, "text/html", &app_handle)?;
+    if full_vault {
+        lock_storage(&storage)?.record_backup()?;
+    }
+    Ok(path)
+}
+
+/// Save every account as a CSV file KeePassXC can import. The file holds
+/// plaintext secrets, so the user must have confirmed the warning and, when
+/// a PIN is set, entered it again.
+#[tauri::command]
+pub fn export_keepass_csv(
+    confirmed: bool,
+    pin: Option<String>,
+    storage: State<Mutex<Storage>>,
+    pin_manager: State<PinManager>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, String> {
+    if !confirmed {
+        return Err(
+            "Confirm that the export file will contain your secrets unencrypted".to_string(),
+        );
+    }
+    reverify_pin(&pin_manager, pin)?;
+    let csv = {
+        let storage = lock_storage(&storage)?;
+        crate::keepass::export_csv(storage.list())?
+    };
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let filename = format!("ghost-auth-keepassxc-{}.csv", timestamp);
+    let path = save_export_file(csv.as_bytes(), &filename, "text/csv", &app_handle)?;
+    tracing::info!(event = "keepass_csv_exported", "KeePassXC CSV export saved");
+    Ok(path)
+}
+
 // --- Export QR commands ---
 
 const EXPORT_BATCH_SIZE: usize = 8;
@@ -378,7 +942,12 @@ fn account_to_otp_params(account: &Account) -> Result<crate::google_auth_proto::
     })
 }
 
-fn build_migration_uri(params: Vec<crate::google_auth_proto::OtpParameters>, batch_size: i32, batch_index: i32) -> Result<String, String> {
+fn build_migration_uri(
+    params: Vec<crate::google_auth_proto::OtpParameters>,
+    batch_size: i32,
+    batch_index: i32,
+    batch_id: i32,
+) -> Result<String, String> {
     use prost::Message;
 
     let payload = crate::google_auth_proto::MigrationPayload {
@@ -386,7 +955,7 @@ fn build_migration_uri(params: Vec<crate::google_auth_proto::OtpParameters>, bat
         version: 1,
         batch_size,
         batch_index,
-        batch_id: 0,
+        batch_id,
     };
 
     let mut buf = Vec::new();
@@ -402,9 +971,22 @@ fn build_migration_uri(params: Vec<crate::google_auth_proto::OtpParameters>, bat
 #[tauri::command]
 pub fn get_export_accounts(storage: State<Mutex<Storage>>) -> Result<Vec<ExportBatch>, String> {
     let storage = lock_storage(&storage)?;
-    let accounts = storage.list();
+    let batches = export_batches(storage.list())?;
+    tracing::info!(
+        event = "export_qr_generated",
+        accounts = storage.list().len(),
+        batches = batches.len(),
+        "Export QR migration URIs generated"
+    );
+    Ok(batches)
+}
+
+/// Split `accounts` into migration QR codes that share one random batch id.
+fn export_batches(accounts: &[Account]) -> Result<Vec<ExportBatch>, String> {
     let total = accounts.len();
     let batch_count = if total == 0 { 0 } else { (total + EXPORT_BATCH_SIZE - 1) / EXPORT_BATCH_SIZE };
+    // Google Authenticator tells the codes of one export apart by this id
+    let batch_id = rand::Rng::gen_range(&mut rand::thread_rng(), 1..=i32::MAX);
 
     let mut batches = Vec::new();
 
@@ -420,117 +1002,519 @@ pub fn get_export_accounts(storage: State<Mutex<Storage>>) -> Result<Vec<ExportB
             });
         }
 
-        let migration_uri = build_migration_uri(otp_params, batch_count as i32, batch_index as i32)?;
+        let migration_uri =
+            build_migration_uri(otp_params, batch_count as i32, batch_index as i32, batch_id)?;
+
+        batches.push(ExportBatch {
+            migration_uri,
+            accounts: account_infos,
+            batch_index,
+            batch_count,
+        });
+    }
+
+    Ok(batches)
+}
+
+// --- External import commands ---
+
+#[derive(Serialize)]
+pub struct ImportPreview {
+    pub format: String,
+    /// Accounts not yet in the vault.
+    pub accounts: Vec<AccountDisplay>,
+    pub skipped: usize,
+    pub duplicates: usize,
+    pub conflicts: Vec<ImportConflict>,
+    /// Every entry that won't be imported and why, duplicates included, in
+    /// source order.
+    pub skipped_entries: Vec<crate::import::SkippedEntry>,
+}
+
+/// Parse an export from another app. `password` opens encrypted exports.
+fn parse_external(
+    data: &[u8],
+    mut password: Option<String>,
+) -> Result<crate::import::ImportResult, String> {
+    let result = crate::import::parse_import(data, password.as_deref());
+    password.zeroize();
+    result
+}
+
+#[tauri::command]
+pub fn import_external_preview(
+    data: Vec<u8>,
+    password: Option<String>,
+    storage: State<Mutex<Storage>>,
+) -> Result<ImportPreview, String> {
+    let result = parse_external(&data, password)?;
+    let storage = lock_storage(&storage)?;
+    Ok(import_preview(result, storage.list()))
+}
+
+/// Sort parsed accounts into new ones, ones already in the vault and ones
+/// conflicting with a vault account, and report the duplicates alongside the
+/// entries the parser skipped.
+fn import_preview(result: crate::import::ImportResult, vault: &[Account]) -> ImportPreview {
+    let skipped = result.skipped.len();
+    let mut skipped_entries = result.skipped;
+    let mut new_accounts = Vec::new();
+    let mut duplicates = 0usize;
+    let mut conflicts = Vec::new();
+    for (account, index) in result.accounts.into_iter().zip(result.sources) {
+        match duplicates::match_import(&account, vault) {
+            (ImportMatch::Identical, _) => {
+                duplicates += 1;
+                skipped_entries.push(crate::import::SkippedEntry {
+                    index,
+                    issuer: account.issuer,
+                    label: account.label,
+                    reason: crate::import::SkipReason::Duplicate,
+                });
+            }
+            (ImportMatch::Conflict, Some(existing)) => {
+                conflicts.push(ImportConflict::new(index, existing, account));
+            }
+            _ => new_accounts.push(AccountDisplay::from(account)),
+        }
+    }
+    skipped_entries.sort_by_key(|entry| entry.index);
+
+    ImportPreview {
+        format: result.format,
+        accounts: new_accounts,
+        skipped,
+        duplicates,
+        conflicts,
+        skipped_entries,
+    }
+}
+
+#[tauri::command]
+pub fn import_external_confirm(
+    data: Vec<u8>,
+    password: Option<String>,
+    decisions: Option<Vec<ImportDecision>>,
+    storage: State<Mutex<Storage>>,
+) -> Result<Vec<AccountDisplay>, String> {
+    let result = parse_external(&data, password)?;
+    let mut storage = lock_storage(&storage)?;
+    let decisions = decisions.unwrap_or_default();
+    let format = result.format.clone();
+    let added = import_accounts(result.into_entries(), &decisions, &mut storage)?;
+    tracing::info!(
+        event = "external_import",
+        format = %format,
+        count = added.len(),
+        "External import completed"
+    );
+    Ok(added)
+}
+
+// --- Multi-code Google Authenticator import ---
+
+fn lock_migration(
+    session: &Mutex<crate::import::MigrationSession>,
+) -> Result<MutexGuard<'_, crate::import::MigrationSession>, String> {
+    session.lock().map_err(|_| {
+        tracing::error!("Migration import mutex poisoned");
+        "Import unavailable — please restart the app".to_string()
+    })
+}
+
+/// Add one scanned QR code of a Google Authenticator export and report which
+/// codes are still missing.
+#[tauri::command]
+pub fn migration_import_add(
+    uri: String,
+    session: State<Mutex<crate::import::MigrationSession>>,
+) -> Result<crate::import::MigrationProgress, String> {
+    lock_migration(&session)?.add(&uri)
+}
+
+/// Compare the export with the vault once all its codes are scanned.
+#[tauri::command]
+pub fn migration_import_preview(
+    session: State<Mutex<crate::import::MigrationSession>>,
+    storage: State<Mutex<Storage>>,
+) -> Result<ImportPreview, String> {
+    let result = lock_migration(&session)?.result()?;
+    let storage = lock_storage(&storage)?;
+    Ok(import_preview(result, storage.list()))
+}
+
+/// Import every account of the export once all its codes are scanned.
+#[tauri::command]
+pub fn migration_import_confirm(
+    decisions: Option<Vec<ImportDecision>>,
+    session: State<Mutex<crate::import::MigrationSession>>,
+    storage: State<Mutex<Storage>>,
+) -> Result<Vec<AccountDisplay>, String> {
+    let mut session = lock_migration(&session)?;
+    let result = session.result()?;
+    let mut storage = lock_storage(&storage)?;
+    let decisions = decisions.unwrap_or_default();
+    let format = result.format.clone();
+    let added = import_accounts(result.into_entries(), &decisions, &mut storage)?;
+    // Keep the scanned codes if the import failed, so the user can retry
+    *session = Default::default();
+    tracing::info!(
+        event = "external_import",
+        format = %format,
+        count = added.len(),
+        "Multi-code Google Authenticator import completed"
+    );
+    Ok(added)
+}
+
+#[tauri::command]
+pub fn migration_import_cancel(
+    session: State<Mutex<crate::import::MigrationSession>>,
+) -> Result<(), String> {
+    *lock_migration(&session)? = Default::default();
+    Ok(())
+}
+
+// --- QR code images (desktop) ---
+
+/// Read the accounts from every QR code in a PNG, JPEG or WebP image.
+fn parse_qr_image(data: &[u8]) -> Result<crate::import::ImportResult, String> {
+    let result = crate::qr_image::parse_image(data)?;
+    if result.accounts.is_empty() {
+        return Err("No authenticator accounts found in the image".to_string());
+    }
+    Ok(result)
+}
+
+/// Compare the accounts in a QR code image, such as a screenshot of an
+/// enrollment page or of Google Authenticator's export codes, with the vault.
+#[tauri::command]
+pub fn scan_qr_image_preview(
+    data: Vec<u8>,
+    storage: State<Mutex<Storage>>,
+) -> Result<ImportPreview, String> {
+    let result = parse_qr_image(&data)?;
+    let storage = lock_storage(&storage)?;
+    Ok(import_preview(result, storage.list()))
+}
+
+/// Add the accounts from every QR code in an image.
+#[tauri::command]
+pub fn scan_qr_image_confirm(
+    data: Vec<u8>,
+    decisions: Option<Vec<ImportDecision>>,
+    storage: State<Mutex<Storage>>,
+) -> Result<Vec<AccountDisplay>, String> {
+    let result = parse_qr_image(&data)?;
+    let mut storage = lock_storage(&storage)?;
+    let decisions = decisions.unwrap_or_default();
+    let skipped = result.skipped.len();
+    let added = import_accounts(result.into_entries(), &decisions, &mut storage)?;
+    tracing::info!(
+        event = "qr_image_import",
+        count = added.len(),
+        skipped,
+        "QR code image import completed"
+    );
+    Ok(added)
+}
+
+// --- Account editing commands ---
+
+#[tauri::command]
+pub fn edit_account(
+    id: String,
+    issuer: String,
+    label: String,
+    storage: State<Mutex<Storage>>,
+) -> Result<AccountDisplay, String> {
+    if issuer.len() > 255 {
+        return Err("Issuer name is too long (max 255 characters)".to_string());
+    }
+    if label.len() > 255 {
+        return Err("Label is too long (max 255 characters)".to_string());
+    }
+    let mut storage = lock_storage(&storage)?;
+    storage.update(&id, issuer, label)?;
+    let account = storage
+        .get(&id)
+        .ok_or_else(|| "Account not found".to_string())?;
+    Ok(AccountDisplay::from(account.clone()))
+}
+
+const MAX_TAGS: usize = 20;
+const MAX_TAG_LEN: usize = 32;
+
+/// Trim tags, drop empty ones, and remove case-insensitive duplicates.
+fn normalize_tags(tags: Vec<String>) -> Result<Vec<String>, String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim();
+        if tag.is_empty() {
+            continue;
+        }
+        if tag.chars().count() > MAX_TAG_LEN {
+            return Err(format!("Tag is too long (max {MAX_TAG_LEN} characters)"));
+        }
+        if !normalized
+            .iter()
+            .any(|t| t.to_lowercase() == tag.to_lowercase())
+        {
+            normalized.push(tag.to_string());
+        }
+    }
+    if normalized.len() > MAX_TAGS {
+        return Err(format!("Too many tags (max {MAX_TAGS})"));
+    }
+    Ok(normalized)
+}
+
+#[tauri::command]
+pub fn edit_account_tags(
+    id: String,
+    tags: Vec<String>,
+    storage: State<Mutex<Storage>>,
+) -> Result<AccountDisplay, String> {
+    let tags = normalize_tags(tags)?;
+    let mut storage = lock_storage(&storage)?;
+    storage.set_tags(&id, tags)?;
+    let account = storage
+        .get(&id)
+        .ok_or_else(|| "Account not found".to_string())?;
+    Ok(AccountDisplay::from(account.clone()))
+}
+
+#[tauri::command]
+pub fn reorder_accounts(ids: Vec<String>, storage: State<Mutex<Storage>>) -> Result<(), String> {
+    let mut storage = lock_storage(&storage)?;
+    storage.reorder(&ids)
+}
+
+// --- Duplicate commands ---
+
+#[derive(Serialize)]
+pub struct DuplicateGroupDisplay {
+    pub kind: DuplicateKind,
+    pub accounts: Vec<AccountDisplay>,
+}
+
+#[tauri::command]
+pub fn find_duplicate_accounts(
+    storage: State<Mutex<Storage>>,
+) -> Result<Vec<DuplicateGroupDisplay>, String> {
+    let storage = lock_storage(&storage)?;
+    Ok(duplicates::find_duplicates(storage.list())
+        .into_iter()
+        .map(|group| DuplicateGroupDisplay {
+            kind: group.kind,
+            accounts: group
+                .account_ids
+                .iter()
+                .filter_map(|id| storage.get(id))
+                .map(|a| AccountDisplay::from(a.clone()))
+                .collect(),
+        })
+        .collect())
+}
 
-        batches.push(ExportBatch {
-            migration_uri,
-            accounts: account_infos,
-            batch_index,
-            batch_count,
-        });
+/// Keep `keep_id` and delete `remove_ids`, which must all be duplicates of it.
+#[tauri::command]
+pub fn merge_duplicate_accounts(
+    keep_id: String,
+    remove_ids: Vec<String>,
+    storage: State<Mutex<Storage>>,
+) -> Result<AccountDisplay, String> {
+    if remove_ids.is_empty() {
+        return Err("No accounts selected to merge".to_string());
+    }
+    let mut storage = lock_storage(&storage)?;
+    let keep = storage
+        .get(&keep_id)
+        .ok_or_else(|| "Account not found".to_string())?;
+    for id in &remove_ids {
+        let other = storage
+            .get(id)
+            .ok_or_else(|| "Account not found".to_string())?;
+        if !duplicates::are_duplicates(keep, other) {
+            return Err("Only duplicate accounts can be merged".to_string());
+        }
     }
 
+    storage.merge(&keep_id, &remove_ids)?;
     tracing::info!(
-        event = "export_qr_generated",
-        accounts = total,
-        batches = batch_count,
-        "Export QR migration URIs generated"
+        event = "accounts_merged",
+        kept = %keep_id,
+        removed = remove_ids.len(),
+        "Merged duplicate accounts"
     );
-    Ok(batches)
+    let account = storage
+        .get(&keep_id)
+        .ok_or_else(|| "Account not found".to_string())?;
+    Ok(AccountDisplay::from(account.clone()))
 }
 
-// --- External import commands ---
+// --- Automatic backup commands ---
 
 #[derive(Serialize)]
-pub struct ImportPreview {
-    pub format: String,
-    pub accounts: Vec<AccountDisplay>,
-    pub skipped: usize,
-    pub duplicates: usize,
+pub struct AutoBackupStatusView {
+    pub enabled: bool,
+    pub directory: Option<String>,
+    pub has_password: bool,
+    /// Number of recovery public keys backups are encrypted to; these take
+    /// precedence over the password.
+    pub recipient_count: usize,
+    pub last_success_at: Option<u64>,
+    pub last_file: Option<String>,
+    pub last_attempt_at: Option<u64>,
+    pub last_error: Option<String>,
+    pub changes_since_backup: u64,
 }
 
 #[tauri::command]
-pub fn import_external_preview(
-    data: Vec<u8>,
+pub fn get_auto_backup_status(
     storage: State<Mutex<Storage>>,
-) -> Result<ImportPreview, String> {
-    let result = crate::import::parse_import(&data)?;
+    app_handle: tauri::AppHandle,
+) -> Result<AutoBackupStatusView, String> {
+    use tauri::Manager;
+    let data_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|_| "Failed to resolve data directory".to_string())?;
+    let settings = crate::settings::Settings::load(&data_dir);
+    let config = settings.auto_backup;
+    let status = crate::auto_backup::AutoBackupStatus::load(&data_dir);
     let storage = lock_storage(&storage)?;
-    let existing: Vec<(&str, &str, &str)> = storage
-        .list()
-        .iter()
-        .map(|e| (e.issuer.as_str(), e.label.as_str(), e.secret.as_str()))
-        .collect();
+    Ok(AutoBackupStatusView {
+        enabled: config.enabled,
+        directory: config.directory,
+        has_password: storage.backup_key().is_some(),
+        recipient_count: settings.backup_recipients.len(),
+        last_success_at: status.last_success_at,
+        last_file: status.last_file,
+        last_attempt_at: status.last_attempt_at,
+        last_error: status.last_error,
+        changes_since_backup: storage.changes_since_backup(),
+    })
+}
 
-    let mut new_accounts = Vec::new();
-    let mut duplicates = 0usize;
-    for account in result.accounts {
-        let is_dup = existing
-            .iter()
-            .any(|e| e.0 == account.issuer && e.1 == account.label && e.2 == account.secret);
-        if is_dup {
-            duplicates += 1;
-        } else {
-            new_accounts.push(AccountDisplay::from(account));
-        }
-    }
+/// Set the password automatic backups are encrypted with. Only the derived
+/// key is kept, inside the encrypted vault.
+#[tauri::command]
+pub fn set_auto_backup_password(
+    mut password: String,
+    storage: State<Mutex<Storage>>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    use tauri::Manager;
+    let data_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|_| "Failed to resolve data directory".to_string())?;
+    let settings = crate::settings::Settings::load(&data_dir);
+    let key = crate::password_strength::check_policy(&password, settings.backup_min_password_score)
+        .and_then(|()| crate::backup::BackupKey::derive(&password, settings.backup_kdf_preset));
+    password.zeroize();
+    let key = key?;
+    lock_storage(&storage)?.set_backup_key(Some(key))?;
+    tracing::info!(
+        event = "auto_backup_password_set",
+        "Automatic backup password set"
+    );
+    Ok(())
+}
 
-    Ok(ImportPreview {
-        format: result.format,
-        accounts: new_accounts,
-        skipped: result.skipped,
-        duplicates,
-    })
+/// Write an automatic backup now, regardless of the schedule.
+#[tauri::command]
+pub fn run_auto_backup(
+    storage: State<Mutex<Storage>>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, String> {
+    use tauri::Manager;
+    let data_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|_| "Failed to resolve data directory".to_string())?;
+    let path = crate::auto_backup::run_now(&data_dir, &storage, crate::storage::now_secs())?;
+    Ok(path.display().to_string())
 }
 
+// --- Health commands ---
+
 #[tauri::command]
-pub fn import_external_confirm(
-    data: Vec<u8>,
+pub fn get_vault_health(
     storage: State<Mutex<Storage>>,
-) -> Result<Vec<AccountDisplay>, String> {
-    let result = crate::import::parse_import(&data)?;
-    let mut storage = lock_storage(&storage)?;
-    let added = deduplicate_and_import(result.accounts, &mut storage)?;
+) -> Result<crate::health::HealthReport, String> {
+    let storage = lock_storage(&storage)?;
+    let report = crate::health::audit(
+        storage.list(),
+        storage.last_backup_at(),
+        crate::storage::now_secs(),
+    );
     tracing::info!(
-        event = "external_import",
-        format = %result.format,
-        count = added.len(),
-        "External import completed"
+        event = "vault_health_checked",
+        findings = report.findings.len(),
+        "Vault health report generated"
     );
-    Ok(added)
+    Ok(report)
 }
 
-// --- Account editing commands ---
+// --- Search commands ---
+
+#[derive(Serialize)]
+pub struct SearchResult {
+    pub account: AccountDisplay,
+    pub score: u32,
+    pub highlights: Vec<search::Highlight>,
+}
 
 #[tauri::command]
-pub fn edit_account(
-    id: String,
-    issuer: String,
-    label: String,
+pub fn search_accounts(
+    query: String,
     storage: State<Mutex<Storage>>,
-) -> Result<AccountDisplay, String> {
-    if issuer.len() > 255 {
-        return Err("Issuer name is too long (max 255 characters)".to_string());
-    }
-    if label.len() > 255 {
-        return Err("Label is too long (max 255 characters)".to_string());
+) -> Result<Vec<SearchResult>, String> {
+    if query.chars().count() > 255 {
+        return Err("Search query is too long (max 255 characters)".to_string());
     }
-    let mut storage = lock_storage(&storage)?;
-    storage.update(&id, issuer, label)?;
-    let account = storage
-        .get(&id)
-        .ok_or_else(|| "Account not found".to_string())?;
-    Ok(AccountDisplay::from(account.clone()))
+    let storage = lock_storage(&storage)?;
+    let hits = search::search(storage.list(), &query, |id| storage.usage_count(id));
+    Ok(hits
+        .into_iter()
+        .map(|hit| SearchResult {
+            account: AccountDisplay::from(hit.account.clone()),
+            score: hit.score,
+            highlights: hit.highlights,
+        })
+        .collect())
 }
 
+/// Record that an account's code was used, so search can rank it higher.
+/// Usage counts stay on this device and are never synced or exported.
 #[tauri::command]
-pub fn reorder_accounts(ids: Vec<String>, storage: State<Mutex<Storage>>) -> Result<(), String> {
+pub fn record_account_use(id: String, storage: State<Mutex<Storage>>) -> Result<(), String> {
     let mut storage = lock_storage(&storage)?;
-    storage.reorder(&ids)
+    storage.record_use(&id)
 }
 
 // --- PIN commands ---
 
+/// Ask for the PIN again, when one is set, before secrets leave the app.
+fn reverify_pin(pin_manager: &PinManager, pin: Option<String>) -> Result<(), String> {
+    if !pin_manager.has_pin() {
+        return Ok(());
+    }
+    let mut pin = pin.ok_or_else(|| "Enter your PIN to continue".to_string())?;
+    let valid = pin_manager.verify_pin(&pin);
+    pin.zeroize();
+    if !valid? {
+        tracing::warn!(
+            event = "pin_reverify_failed",
+            "Export attempted with incorrect PIN"
+        );
+        return Err("Incorrect PIN".to_string());
+    }
+    Ok(())
+}
+
 #[tauri::command]
 pub fn has_pin(pin_manager: State<PinManager>) -> bool {
     pin_manager.has_pin()
@@ -659,6 +1643,10 @@ pub struct MergePreview {
     pub to_delete: Vec<AccountDisplay>,
     pub auto_updated: Vec<AccountDisplay>,
     pub unchanged: usize,
+    /// Accounts held back because the peer is too stale to tell whether
+    /// they were deleted here. Restored only with a "restore" decision.
+    pub possibly_resurrected: Vec<AccountDisplay>,
+    pub stale_peer: bool,
 }
 
 #[derive(Serialize, Clone)]
@@ -717,6 +1705,13 @@ fn merge_result_to_preview(result: &crate::sync::MergeResult) -> MergePreview {
             .map(AccountDisplay::from)
             .collect(),
         unchanged: result.unchanged,
+        possibly_resurrected: result
+            .possibly_resurrected
+            .iter()
+            .cloned()
+            .map(AccountDisplay::from)
+            .collect(),
+        stale_peer: result.stale_peer,
     }
 }
 
@@ -748,6 +1743,7 @@ pub fn sync_start(
     let device_id = storage_guard.device_id().to_string();
     let accounts = storage_guard.list().to_vec();
     let tombstones = storage_guard.tombstones().to_vec();
+    let pruned_through = storage_guard.tombstones_pruned_through();
     drop(storage_guard);
 
     let key = *session.key();
@@ -870,13 +1866,14 @@ pub fn sync_start(
         let history = crate::sync::SyncHistory::load(&data_dir);
         let last_sync = history.last_sync_with(&remote_payload.device_id);
 
-        let merge_result = crate::sync::merge(
+        let mut merge_result = crate::sync::merge(
             &accounts,
             &tombstones,
             remote_accounts,
             &remote_payload.tombstones,
             last_sync,
         );
+        crate::sync::flag_stale_peer(&mut merge_result, last_sync, pruned_through);
 
         if let Ok(mut s) = shared.lock() {
             if let Some(ref mut a) = *s {
@@ -975,6 +1972,7 @@ pub fn sync_join(
     let device_id = storage_guard.device_id().to_string();
     let accounts = storage_guard.list().to_vec();
     let tombstones = storage_guard.tombstones().to_vec();
+    let pruned_through = storage_guard.tombstones_pruned_through();
     drop(storage_guard);
 
     // Joiner sends first
@@ -1001,13 +1999,14 @@ pub fn sync_join(
     let history = crate::sync::SyncHistory::load(&data_dir);
     let last_sync = history.last_sync_with(&remote_payload.device_id);
 
-    let merge_result = crate::sync::merge(
+    let mut merge_result = crate::sync::merge(
         &accounts,
         &tombstones,
         remote_accounts,
         &remote_payload.tombstones,
         last_sync,
     );
+    crate::sync::flag_stale_peer(&mut merge_result, last_sync, pruned_through);
 
     let preview = merge_result_to_preview(&merge_result);
 
@@ -1032,6 +2031,85 @@ pub fn sync_join(
     Ok(preview)
 }
 
+/// Apply a merge with the user's decisions on its conflicts, deletions and
+/// held-back accounts.
+fn apply_merge(
+    storage: &mut Storage,
+    merge_result: crate::sync::MergeResult,
+    decisions: &[MergeDecision],
+) -> Result<SyncConfirmResult, String> {
+    let crate::sync::MergeResult {
+        to_add,
+        conflicts,
+        remote_deletions,
+        auto_updated,
+        unchanged: _,
+        possibly_resurrected,
+        stale_peer: _,
+    } = merge_result;
+
+    // Apply the whole merge atomically: a failure leaves the vault untouched
+    storage.transaction(|storage| {
+        let mut added = 0usize;
+        let mut updated = 0usize;
+        let mut deleted = 0usize;
+
+        // Auto-add new accounts from remote
+        for mut account in to_add {
+            crate::icons::apply(&mut account);
+            storage.add_synced(account)?;
+            added += 1;
+        }
+
+        // Auto-update accounts where remote is newer
+        for account in auto_updated {
+            storage.replace_account(account)?;
+            updated += 1;
+        }
+
+        // Apply user decisions for conflicts and deletions
+        let decision_map: std::collections::HashMap<&str, &str> = decisions
+            .iter()
+            .map(|d| (d.account_id.as_str(), d.action.as_str()))
+            .collect();
+
+        for conflict in &conflicts {
+            match decision_map.get(conflict.local.id.as_str()) {
+                Some(&"keep_remote") => {
+                    storage.replace_account(conflict.remote.clone())?;
+                    updated += 1;
+                }
+                Some(&"delete") => {
+                    storage.delete(&conflict.local.id)?;
+                    deleted += 1;
+                }
+                _ => {} // keep_local or unspecified — keep local version
+            }
+        }
+
+        for account in &remote_deletions {
+            if let Some(&"delete") = decision_map.get(account.id.as_str()) {
+                storage.delete(&account.id)?;
+                deleted += 1;
+            }
+        }
+
+        for mut account in possibly_resurrected {
+            if let Some(&"restore") = decision_map.get(account.id.as_str()) {
+                crate::icons::apply(&mut account);
+                storage.add_synced(account)?;
+                added += 1;
+            }
+        }
+
+        Ok(SyncConfirmResult {
+            added,
+            updated,
+            deleted,
+        })
+    })
+}
+
 #[tauri::command]
 pub fn sync_confirm(
     decisions: Vec<MergeDecision>,
@@ -1058,64 +2136,20 @@ pub fn sync_confirm(
         data_dir,
     } = pending;
 
-    let crate::sync::MergeResult {
-        to_add,
-        conflicts,
-        remote_deletions,
-        auto_updated,
-        unchanged: _,
-    } = merge_result;
+    // Record this sync before saving, so tombstones the peer has now seen
+    // can be pruned by the save below
+    let mut history = crate::sync::SyncHistory::load(&data_dir);
+    history.record_sync(&remote_device_id, crate::storage::now_secs());
+    let settings = crate::settings::Settings::load(&data_dir);
 
     let mut storage = lock_storage(&storage)?;
-
-    let mut added = 0usize;
-    let mut updated = 0usize;
-    let mut deleted = 0usize;
-
-    // Auto-add new accounts from remote
-    for account in to_add {
-        storage.add_synced(account)?;
-        added += 1;
-    }
-
-    // Auto-update accounts where remote is newer
-    for account in auto_updated {
-        storage.replace_account(account)?;
-        updated += 1;
-    }
-
-    // Apply user decisions for conflicts and deletions
-    let decision_map: std::collections::HashMap<&str, &str> = decisions
-        .iter()
-        .map(|d| (d.account_id.as_str(), d.action.as_str()))
-        .collect();
-
-    for conflict in &conflicts {
-        match decision_map.get(conflict.local.id.as_str()) {
-            Some(&"keep_remote") => {
-                storage.replace_account(conflict.remote.clone())?;
-                updated += 1;
-            }
-            Some(&"delete") => {
-                storage.delete(&conflict.local.id)?;
-                deleted += 1;
-            }
-            _ => {} // keep_local or unspecified — keep local version
-        }
-    }
-
-    for account in &remote_deletions {
-        if let Some(&"delete") = decision_map.get(account.id.as_str()) {
-            storage.delete(&account.id)?;
-            deleted += 1;
-        }
-    }
+    storage.set_tombstone_retention(
+        history.tombstone_retention(settings.tombstone_max_retention_days),
+    );
+    let result = apply_merge(&mut storage, merge_result, &decisions)?;
 
     drop(storage);
 
-    // Record sync in history
-    let mut history = crate::sync::SyncHistory::load(&data_dir);
-    history.record_sync(&remote_device_id, crate::storage::now_secs());
     if let Err(e) = history.save(&data_dir) {
         tracing::warn!(error = %e, "Failed to save sync history");
     }
@@ -1127,17 +2161,13 @@ pub fn sync_confirm(
 
     tracing::info!(
         event = "sync_confirmed",
-        added = added,
-        updated = updated,
-        deleted = deleted,
+        added = result.added,
+        updated = result.updated,
+        deleted = result.deleted,
         "Sync merge applied"
     );
 
-    Ok(SyncConfirmResult {
-        added,
-        updated,
-        deleted,
-    })
+    Ok(result)
 }
 
 #[tauri::command]
@@ -1170,6 +2200,40 @@ pub fn sync_history(app_handle: tauri::AppHandle) -> Result<Vec<SyncPeerInfo>, S
         .collect())
 }
 
+// --- Settings commands ---
+
+#[tauri::command]
+pub fn get_settings(app_handle: tauri::AppHandle) -> Result<crate::settings::Settings, String> {
+    use tauri::Manager;
+    let data_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|_| "Failed to resolve data directory".to_string())?;
+    Ok(crate::settings::Settings::load(&data_dir))
+}
+
+#[tauri::command]
+pub fn save_settings(
+    settings: crate::settings::Settings,
+    storage: State<Mutex<Storage>>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    use tauri::Manager;
+    settings.validate()?;
+    let data_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|_| "Failed to resolve data directory".to_string())?;
+    settings.save(&data_dir)?;
+
+    let history = crate::sync::SyncHistory::load(&data_dir);
+    let mut storage = lock_storage(&storage)?;
+    storage.set_tombstone_retention(
+        history.tombstone_retention(settings.tombstone_max_retention_days),
+    );
+    Ok(())
+}
+
 #[tauri::command]
 pub fn save_theme(app_handle: tauri::AppHandle, theme: String) -> Result<(), String> {
     use tauri::Manager;
@@ -1185,6 +2249,7 @@ pub fn save_theme(app_handle: tauri::AppHandle, theme: String) -> Result<(), Str
 #[cfg(test)]
 mod tests {
     use crate::storage::{Account, Storage};
+    use crate::storage_backend::MemoryBackend;
     use crate::totp;
 
     fn test_key() -> [u8; 32] {
@@ -1233,6 +2298,18 @@ mod tests {
         assert!(super::validate_account_fields("X", "Y", "", 6, 30).is_err());
     }
 
+    #[test]
+    fn test_normalize_tags() {
+        let tags = vec![" Work ".into(), "".into(), "work".into(), "Personal".into()];
+        assert_eq!(
+            super::normalize_tags(tags).unwrap(),
+            vec!["Work".to_string(), "Personal".to_string()]
+        );
+        assert!(super::normalize_tags(vec!["a".repeat(33)]).is_err());
+        let many: Vec<String> = (0..21).map(|i| format!("tag{i}")).collect();
+        assert!(super::normalize_tags(many).is_err());
+    }
+
     #[test]
     fn test_account_display_strips_secret() {
         let account = Account {
@@ -1245,6 +2322,7 @@ mod tests {
             period: 30,
             icon: None,
             last_modified: 0,
+            tags: Vec::new(),
         };
         let display = super::AccountDisplay::from(account);
         assert_eq!(display.id, "id1");
@@ -1253,8 +2331,8 @@ mod tests {
 
     #[test]
     fn test_full_add_generate_delete_flow() {
-        let dir = tempfile::tempdir().unwrap();
-        let mut storage = Storage::new_with_key(dir.path().to_path_buf(), test_key()).unwrap();
+        let backend = MemoryBackend::new(test_key());
+        let mut storage = Storage::with_backend(Box::new(backend)).unwrap();
 
         let uri = "otpauth://totp/GitHub:user@example.com?secret=JBSWY3DPEHPK3PXP&issuer=GitHub";
         let account = totp::parse_otpauth_uri(uri).unwrap();
@@ -1268,4 +2346,246 @@ mod tests {
         storage.delete(&id).unwrap();
         assert!(storage.get(&id).is_none());
     }
+
+    #[test]
+    fn test_reverify_pin() {
+        let dir = tempfile::tempdir().unwrap();
+        let pin_manager = crate::pin::PinManager::new(dir.path().to_path_buf());
+        assert!(super::reverify_pin(&pin_manager, None).is_ok());
+
+        pin_manager.set_pin("1234").unwrap();
+        assert_eq!(
+            super::reverify_pin(&pin_manager, None).unwrap_err(),
+            "Enter your PIN to continue"
+        );
+        assert_eq!(
+            super::reverify_pin(&pin_manager, Some("9999".into())).unwrap_err(),
+            "Incorrect PIN"
+        );
+        assert!(super::reverify_pin(&pin_manager, Some("1234".into())).is_ok());
+    }
+
+    #[test]
+    fn test_export_batches_share_a_batch_id() {
+        let accounts: Vec<Account> = (0..10)
+            .map(|i| Account {
+                id: format!("a{i}"),
+                issuer: format!("Service {i}"),
+                label: "me".into(),
+                secret: "JBSWY3DPEHPK3PXP".into(),
+                algorithm: "SHA1".into(),
+                digits: 6,
+                period: 30,
+                icon: None,
+                last_modified: 0,
+                tags: vec![],
+            })
+            .collect();
+
+        let batches = super::export_batches(&accounts).unwrap();
+        assert_eq!(batches.len(), 2);
+        let parsed: Vec<_> = batches
+            .iter()
+            .map(|b| crate::import::parse_migration_batch(&b.migration_uri).unwrap())
+            .collect();
+        assert_ne!(parsed[0].batch_id, 0);
+        assert_eq!(parsed[0].batch_id, parsed[1].batch_id);
+        assert_eq!((parsed[1].batch_size, parsed[1].batch_index), (2, 1));
+
+        // A second export gets a different id
+        let again = super::export_batches(&accounts).unwrap();
+        let other = crate::import::parse_migration_batch(&again[0].migration_uri).unwrap();
+        assert_ne!(other.batch_id, parsed[0].batch_id);
+
+        let uris = batches.iter().rev().map(|b| b.migration_uri.as_str());
+        let result = crate::import::parse_migration_uris(uris).unwrap();
+        assert_eq!(result.accounts.len(), 10);
+        assert_eq!(result.accounts[9].issuer, "Service 9");
+    }
+
+    #[test]
+    fn test_import_preview_reports_duplicates_in_source_order() {
+        let vault = vec![totp::parse_otpauth_uri(
+            "otpauth://totp/AWS:b@example.com?secret=GEZDGNBVGY3TQOJQ&issuer=AWS",
+        )
+        .unwrap()];
+        let text = "otpauth://totp/GitHub:a@example.com?secret=JBSWY3DPEHPK3PXP&issuer=GitHub\n\
+            otpauth://hotp/Counter:me?secret=JBSWY3DPEHPK3PXP&counter=1\n\
+            otpauth://totp/AWS:b@example.com?secret=GEZDGNBVGY3TQOJQ&issuer=AWS\n\
+            otpauth://totp/AWS:b@example.com?secret=MFRGGZDFMZTWQ2LK&issuer=AWS&digits=8\n";
+        let result = crate::import::parse_import(text.as_bytes(), None).unwrap();
+
+        let preview = super::import_preview(result, &vault);
+        assert_eq!(preview.accounts.len(), 1);
+        assert_eq!(preview.accounts[0].issuer, "GitHub");
+        assert_eq!((preview.skipped, preview.duplicates), (1, 1));
+        assert_eq!(preview.conflicts.len(), 1);
+        assert_eq!(preview.conflicts[0].entry, 3);
+        assert_eq!(preview.conflicts[0].account_id, vault[0].id);
+        assert_eq!(preview.conflicts[0].changed, ["secret", "digits"]);
+        let report: Vec<_> = preview
+            .skipped_entries
+            .iter()
+            .map(|e| (e.index, e.issuer.as_str(), e.reason))
+            .collect();
+        assert_eq!(
+            report,
+            vec![
+                (1, "Counter", crate::import::SkipReason::UnsupportedType),
+                (2, "AWS", crate::import::SkipReason::Duplicate),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_import_accounts_applies_conflict_decisions() {
+        let backend = MemoryBackend::new(test_key());
+        let mut storage = Storage::with_backend(Box::new(backend)).unwrap();
+        let parse = |uri: &str| totp::parse_otpauth_uri(uri).unwrap();
+        for uri in [
+            "otpauth://totp/GitHub:a?secret=JBSWY3DPEHPK3PXP&issuer=GitHub",
+            "otpauth://totp/AWS:b?secret=GEZDGNBVGY3TQOJQ&issuer=AWS",
+            "otpauth://totp/Slack:c?secret=GEZDGNBVGY3TQOJQ&issuer=Slack",
+        ] {
+            storage.add(parse(uri)).unwrap();
+        }
+        let ids: Vec<String> = storage.list().iter().map(|a| a.id.clone()).collect();
+        storage.set_tags(&ids[1], vec!["work".into()]).unwrap();
+
+        let incoming = vec![
+            parse("otpauth://totp/GitHub:a?secret=JBSWY3DPEHPK3PXP&issuer=GitHub"),
+            parse("otpauth://totp/GitHub:a?secret=MFRGGZDFMZTWQ2LK&issuer=GitHub"),
+            parse("otpauth://totp/AWS:b?secret=MFRGGZDFMZTWQ2LK&issuer=AWS"),
+            parse("otpauth://totp/Slack:c?secret=MFRGGZDFMZTWQ2LK&issuer=Slack"),
+            parse("otpauth://totp/Dropbox:d?secret=JBSWY3DPEHPK3PXP&issuer=Dropbox"),
+            parse("otpauth://totp/AWS:b?secret=MZXW6YTBOI&issuer=AWS"),
+        ];
+        let decision = |entry: usize, action: &str| super::ImportDecision {
+            entry,
+            action: action.to_string(),
+        };
+        let decisions = [
+            decision(1, "keep_existing"),
+            decision(2, "replace"),
+            decision(5, "keep_both"),
+        ];
+
+        // Two entries can't both replace the AWS account; nothing is imported
+        let clashing = [decision(2, "replace"), decision(5, "replace")];
+        let entries = incoming.clone().into_iter().enumerate();
+        let err = super::import_accounts(entries, &clashing, &mut storage).err();
+        assert!(err.unwrap().contains("Entries 3 and 6 both replace AWS"));
+        assert_eq!(storage.list().len(), 3);
+        assert_eq!(storage.list()[1].secret, "GEZDGNBVGY3TQOJQ");
+
+        let entries = incoming.into_iter().enumerate();
+        let imported = super::import_accounts(entries, &decisions, &mut storage).unwrap();
+        let issuers: Vec<&str> = imported.iter().map(|a| a.issuer.as_str()).collect();
+        assert_eq!(issuers, ["AWS", "Slack", "Dropbox", "AWS"]);
+
+        // Kept: GitHub unchanged; replaced: AWS keeps its id and tags;
+        // undecided: Slack is kept twice
+        let accounts = storage.list();
+        assert_eq!(accounts.len(), 6);
+        assert_eq!(accounts[0].secret, "JBSWY3DPEHPK3PXP");
+        assert_eq!(accounts[1].id, ids[1]);
+        assert_eq!(accounts[1].secret, "MFRGGZDFMZTWQ2LK");
+        assert_eq!(accounts[1].tags, ["work"]);
+        let slack = accounts.iter().filter(|a| a.issuer == "Slack").count();
+        assert_eq!(slack, 2);
+        // The other AWS entry is kept alongside
+        assert_eq!(accounts[5].issuer, "AWS");
+        assert_eq!(accounts[5].secret, "MZXW6YTBOI");
+        assert_ne!(accounts[5].id, ids[1]);
+    }
+
+    #[test]
+    fn test_merge_restore_keeps_ids_and_is_idempotent() {
+        let backend = MemoryBackend::new(test_key());
+        let mut storage = Storage::with_backend(Box::new(backend)).unwrap();
+        for uri in [
+            "otpauth://totp/GitHub:a@example.com?secret=JBSWY3DPEHPK3PXP&issuer=GitHub",
+            "otpauth://totp/AWS:b@example.com?secret=GEZDGNBVGY3TQOJQ&issuer=AWS",
+        ] {
+            storage.add(totp::parse_otpauth_uri(uri).unwrap()).unwrap();
+        }
+
+        let mut accounts = storage.list().to_vec();
+        accounts[0].issuer = "GitHub Enterprise".into();
+        accounts[0].last_modified += 10;
+        let mut extra = totp::parse_otpauth_uri(
+            "otpauth://totp/Slack:c@example.com?secret=JBSWY3DPEHPK3PXP&issuer=Slack",
+        )
+        .unwrap();
+        extra.id = "restored-id".into();
+        extra.last_modified = 100;
+        accounts.push(extra);
+        let contents = crate::backup::BackupContents {
+            exported_at: accounts[0].last_modified,
+            accounts,
+            ..Default::default()
+        };
+
+        let merge_result = super::merge_backup(&storage, &contents);
+        assert_eq!(merge_result.to_add.len(), 1);
+        assert_eq!(merge_result.auto_updated.len(), 1);
+        assert_eq!(merge_result.unchanged, 1);
+        let result = super::apply_merge(&mut storage, merge_result, &[]).unwrap();
+        assert_eq!((result.added, result.updated, result.deleted), (1, 1, 0));
+
+        let restored = storage.get("restored-id").unwrap();
+        assert_eq!(restored.last_modified, 100);
+        assert_eq!(storage.list()[0].issuer, "GitHub Enterprise");
+
+        // Restoring the same backup again changes nothing
+        let again = super::merge_backup(&storage, &contents);
+        assert!(again.to_add.is_empty() && again.auto_updated.is_empty());
+        assert_eq!(again.unchanged, 3);
+        assert_eq!(storage.list().len(), 3);
+    }
+
+    #[test]
+    fn test_merge_restore_raises_conflict_for_edits_on_both_sides() {
+        let backend = MemoryBackend::new(test_key());
+        let mut storage = Storage::with_backend(Box::new(backend)).unwrap();
+        let mut account = totp::parse_otpauth_uri(
+            "otpauth://totp/GitHub:a@example.com?secret=JBSWY3DPEHPK3PXP&issuer=GitHub",
+        )
+        .unwrap();
+        account.last_modified = 1_000;
+        storage.add(account.clone()).unwrap();
+
+        // Edited here after the backup was made...
+        let mut local = account.clone();
+        local.label = "local@example.com".into();
+        local.last_modified = 2_000;
+        storage.replace_account(local).unwrap();
+
+        // ...and in the backup's copy, by the device that exported it
+        let mut remote = account.clone();
+        remote.issuer = "GitHub Enterprise".into();
+        remote.last_modified = 3_000;
+        let contents = crate::backup::BackupContents {
+            exported_at: 1_500,
+            accounts: vec![remote],
+            ..Default::default()
+        };
+
+        let merge_result = super::merge_backup(&storage, &contents);
+        let preview = super::merge_result_to_preview(&merge_result);
+        assert_eq!(preview.conflicts.len(), 1);
+        assert_eq!(preview.conflicts[0].account_id, account.id);
+        assert!(preview.auto_updated.is_empty());
+
+        let decisions = [super::MergeDecision {
+            account_id: account.id.clone(),
+            action: "keep_remote".to_string(),
+        }];
+        let result = super::apply_merge(&mut storage, merge_result, &decisions).unwrap();
+        assert_eq!(result.updated, 1);
+        let restored = storage.get(&account.id).unwrap();
+        assert_eq!(restored.issuer, "GitHub Enterprise");
+        assert_eq!(restored.label, "a@example.com");
+        assert_eq!(storage.list().len(), 1);
+    }
 }
@@ -0,0 +1,273 @@
+//! Detection of duplicate and near-duplicate accounts.
+//!
+//! Exact duplicate checks compare raw (issuer, label, secret) strings, which
+//! misses the same secret imported twice with a different label or with
+//! different Base32 formatting. This module groups accounts by decoded secret
+//! bytes and by normalized issuer/label instead.
+
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::storage::Account;
+use crate::totp;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateKind {
+    /// The accounts generate the same codes: their secrets decode to the
+    /// same bytes, even if issuer or label differ.
+    SameSecret,
+    /// Issuer and label match after normalization but the secrets differ.
+    /// Usually one of them is a stale enrollment that no longer works.
+    SameName,
+}
+
+/// How an account being imported relates to the vault.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMatch {
+    /// An account with the same issuer and label already generates the
+    /// same codes.
+    Identical,
+    /// An account with the same issuer and label has a different secret or
+    /// parameters, typically because the service was re-keyed.
+    Conflict,
+    New,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DuplicateGroup {
+    pub kind: DuplicateKind,
+    /// Account IDs in vault order.
+    pub account_ids: Vec<String>,
+}
+
+/// Group likely duplicates. Each returned group has at least two accounts.
+/// Same-secret groups come first; a same-name group is only reported if its
+/// accounts aren't already all in one same-secret group.
+pub fn find_duplicates(accounts: &[Account]) -> Vec<DuplicateGroup> {
+    let mut groups = Vec::new();
+
+    let by_secret = group_by(accounts, |a| secret_key(&a.secret));
+    for ids in &by_secret {
+        groups.push(DuplicateGroup {
+            kind: DuplicateKind::SameSecret,
+            account_ids: ids.clone(),
+        });
+    }
+
+    for ids in group_by(accounts, |a| Some(name_key(&a.issuer, &a.label))) {
+        let covered = by_secret
+            .iter()
+            .any(|group| ids.iter().all(|id| group.contains(id)));
+        if !covered {
+            groups.push(DuplicateGroup {
+                kind: DuplicateKind::SameName,
+                account_ids: ids,
+            });
+        }
+    }
+
+    groups
+}
+
+/// Whether two accounts would be grouped together by `find_duplicates`.
+pub fn are_duplicates(a: &Account, b: &Account) -> bool {
+    let same_secret = match (secret_key(&a.secret), secret_key(&b.secret)) {
+        (Some(x), Some(y)) => x == y,
+        _ => false,
+    };
+    same_secret || name_key(&a.issuer, &a.label) == name_key(&b.issuer, &b.label)
+}
+
+/// Compare an incoming account with the vault by normalized issuer/label,
+/// returning the matching vault account. An identical match wins over a
+/// conflicting one.
+pub fn match_import<'a>(
+    account: &Account,
+    vault: &'a [Account],
+) -> (ImportMatch, Option<&'a Account>) {
+    let name = name_key(&account.issuer, &account.label);
+    let secret = secret_key(&account.secret);
+    let same_name: Vec<&Account> = vault
+        .iter()
+        .filter(|existing| name_key(&existing.issuer, &existing.label) == name)
+        .collect();
+    let identical = same_name.iter().find(|existing| {
+        secret_key(&existing.secret) == secret
+            && existing.algorithm == account.algorithm
+            && existing.digits == account.digits
+            && existing.period == account.period
+    });
+    match (identical, same_name.first()) {
+        (Some(existing), _) => (ImportMatch::Identical, Some(existing)),
+        (None, Some(existing)) => (ImportMatch::Conflict, Some(existing)),
+        (None, None) => (ImportMatch::New, None),
+    }
+}
+
+/// Group account IDs by a key, keeping only groups with more than one account.
+/// Groups are ordered by their first member's position in the vault.
+fn group_by<K, F>(accounts: &[Account], key: F) -> Vec<Vec<String>>
+where
+    K: std::hash::Hash + Eq,
+    F: Fn(&Account) -> Option<K>,
+{
+    let mut index: HashMap<K, usize> = HashMap::new();
+    let mut groups: Vec<Vec<String>> = Vec::new();
+    for account in accounts {
+        let Some(k) = key(account) else { continue };
+        match index.get(&k) {
+            Some(&i) => groups[i].push(account.id.clone()),
+            None => {
+                index.insert(k, groups.len());
+                groups.push(vec![account.id.clone()]);
+            }
+        }
+    }
+    groups.retain(|g| g.len() > 1);
+    groups
+}
+
+/// Decoded secret bytes, or the normalized string if it isn't valid Base32
+/// (so identical malformed secrets are still caught).
+fn secret_key(secret: &str) -> Option<Vec<u8>> {
+    totp::decode_secret(secret).or_else(|| {
+        let normalized = normalize(secret);
+        (!normalized.is_empty()).then(|| normalized.into_bytes())
+    })
+}
+
+/// Normalized (issuer, label) pair. A label of the form "Issuer:user" has
+/// the issuer prefix stripped, matching how otpauth URIs are often labelled.
+fn name_key(issuer: &str, label: &str) -> (String, String) {
+    let issuer = normalize(issuer);
+    let label = match label.split_once(':') {
+        Some((prefix, rest)) if normalize(prefix) == issuer => rest,
+        _ => label,
+    };
+    (issuer, normalize(label))
+}
+
+/// Lowercase and drop whitespace and punctuation that commonly varies
+/// between apps (spaces, dashes, dots in "G Suite" vs "GSuite").
+fn normalize(s: &str) -> String {
+    s.chars()
+        .filter(|c| !c.is_whitespace() && !matches!(c, '-' | '_' | '.' | '='))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_account(id: &str, issuer: &str, label: &str, secret: &str) -> Account {
+        Account {
+            id: id.to_string(),
+            issuer: issuer.to_string(),
+            label: label.to_string(),
+            secret: secret.to_string(),
+            algorithm: "SHA1".to_string(),
+            digits: 6,
+            period: 30,
+            icon: None,
+            last_modified: 0,
+            tags: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_same_secret_with_different_formatting() {
+        let accounts = vec![
+            make_account("a", "GitHub", "alice", "JBSWY3DPEHPK3PXP"),
+            make_account("b", "Other", "x", "GEZDGNBVGY3TQOJQ"),
+            make_account("c", "GitHub", "alice@example.com", "jbsw y3dp ehpk 3pxp"),
+            make_account("d", "github.com", "alice", "JBSWY3DPEHPK3PXP===="),
+        ];
+        let groups = find_duplicates(&accounts);
+        assert_eq!(
+            groups,
+            vec![DuplicateGroup {
+                kind: DuplicateKind::SameSecret,
+                account_ids: vec!["a".into(), "c".into(), "d".into()],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_same_name_with_different_secrets() {
+        let accounts = vec![
+            make_account("a", "GitHub", "alice", "JBSWY3DPEHPK3PXP"),
+            make_account("b", "github", "GitHub:Alice", "GEZDGNBVGY3TQOJQ"),
+            make_account("c", "GitLab", "alice", "MFRGGZDFMZTWQ2LK"),
+        ];
+        let groups = find_duplicates(&accounts);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].kind, DuplicateKind::SameName);
+        assert_eq!(groups[0].account_ids, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_name_group_covered_by_secret_group_not_repeated() {
+        let accounts = vec![
+            make_account("a", "GitHub", "alice", "JBSWY3DPEHPK3PXP"),
+            make_account("b", "GitHub", "alice", "JBSWY3DPEHPK3PXP"),
+        ];
+        let groups = find_duplicates(&accounts);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].kind, DuplicateKind::SameSecret);
+    }
+
+    #[test]
+    fn test_no_duplicates() {
+        let accounts = vec![
+            make_account("a", "GitHub", "alice", "JBSWY3DPEHPK3PXP"),
+            make_account("b", "GitHub", "bob", "GEZDGNBVGY3TQOJQ"),
+        ];
+        assert!(find_duplicates(&accounts).is_empty());
+    }
+
+    #[test]
+    fn test_are_duplicates() {
+        let a = make_account("a", "GitHub", "alice", "JBSWY3DPEHPK3PXP");
+        let b = make_account("b", "Renamed", "someone", "jbswy3dpehpk3pxp");
+        let c = make_account("c", "GitHub", "Alice", "GEZDGNBVGY3TQOJQ");
+        let d = make_account("d", "GitLab", "bob", "MFRGGZDFMZTWQ2LK");
+        assert!(are_duplicates(&a, &b));
+        assert!(are_duplicates(&a, &c));
+        assert!(!are_duplicates(&a, &d));
+    }
+
+    #[test]
+    fn test_match_import() {
+        let vault = vec![
+            make_account("a", "GitHub", "alice", "JBSWY3DPEHPK3PXP"),
+            make_account("b", "AWS", "ops", "GEZDGNBVGY3TQOJQ"),
+        ];
+
+        let same = make_account("x", "github", "GitHub:Alice", "jbsw y3dp ehpk 3pxp");
+        let (kind, existing) = match_import(&same, &vault);
+        assert_eq!(
+            (kind, existing.map(|a| a.id.as_str())),
+            (ImportMatch::Identical, Some("a"))
+        );
+
+        let rekeyed = make_account("x", "AWS", "ops", "MFRGGZDFMZTWQ2LK");
+        let (kind, existing) = match_import(&rekeyed, &vault);
+        assert_eq!(
+            (kind, existing.map(|a| a.id.as_str())),
+            (ImportMatch::Conflict, Some("b"))
+        );
+
+        let mut longer = make_account("x", "GitHub", "alice", "JBSWY3DPEHPK3PXP");
+        longer.digits = 8;
+        assert_eq!(match_import(&longer, &vault).0, ImportMatch::Conflict);
+
+        let other = make_account("x", "GitHub", "bob", "JBSWY3DPEHPK3PXP");
+        assert!(matches!(
+            match_import(&other, &vault),
+            (ImportMatch::New, None)
+        ));
+    }
+}
@@ -0,0 +1,411 @@
+//! Vault security health audit.
+//!
+//! Codes are generated with `TOTP::new_unchecked` so that real-world secrets
+//! shorter than 128 bits keep working. This audit is where weak or unusual
+//! accounts are surfaced instead. Each finding groups every account affected
+//! by one issue.
+
+use serde::Serialize;
+
+use crate::duplicates::{self, DuplicateKind};
+use crate::storage::Account;
+use crate::totp;
+
+/// RFC 4226 requires at least 128 bits of shared secret.
+const MIN_SECRET_BYTES: usize = 16;
+/// Below 80 bits a secret is weak enough to treat as high severity.
+const WEAK_SECRET_BYTES: usize = 10;
+
+const DAY_SECS: u64 = 24 * 60 * 60;
+const BACKUP_STALE_SECS: u64 = 30 * DAY_SECS;
+const BACKUP_VERY_STALE_SECS: u64 = 90 * DAY_SECS;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Finding {
+    /// Stable machine-readable identifier, e.g. "short_secret".
+    pub code: String,
+    pub severity: Severity,
+    pub message: String,
+    /// Affected accounts; empty for vault-wide findings such as backups.
+    pub account_ids: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct HealthReport {
+    pub generated_at: u64,
+    pub accounts_checked: usize,
+    /// Findings ordered from most to least severe.
+    pub findings: Vec<Finding>,
+}
+
+/// Audit the vault. `last_backup_at` is when a backup was last exported.
+pub fn audit(accounts: &[Account], last_backup_at: Option<u64>, now: u64) -> HealthReport {
+    let mut findings = Vec::new();
+
+    check_secrets(accounts, &mut findings);
+    check_shared_secrets(accounts, &mut findings);
+    check_parameters(accounts, &mut findings);
+    check_backup(accounts, last_backup_at, now, &mut findings);
+
+    // Stable sort keeps the check order within a severity
+    findings.sort_by_key(|f| std::cmp::Reverse(f.severity));
+
+    HealthReport {
+        generated_at: now,
+        accounts_checked: accounts.len(),
+        findings,
+    }
+}
+
+/// Add a finding if any accounts match the predicate.
+fn push_matching(
+    findings: &mut Vec<Finding>,
+    accounts: &[Account],
+    code: &str,
+    severity: Severity,
+    message: &str,
+    predicate: impl Fn(&Account) -> bool,
+) {
+    let account_ids: Vec<String> = accounts
+        .iter()
+        .filter(|a| predicate(a))
+        .map(|a| a.id.clone())
+        .collect();
+    if !account_ids.is_empty() {
+        findings.push(Finding {
+            code: code.to_string(),
+            severity,
+            message: message.to_string(),
+            account_ids,
+        });
+    }
+}
+
+fn check_secrets(accounts: &[Account], findings: &mut Vec<Finding>) {
+    let secret_len = |a: &Account| totp::decode_secret(&a.secret).map(|s| s.len());
+
+    push_matching(
+        findings,
+        accounts,
+        "invalid_secret",
+        Severity::Critical,
+        "Secret is not valid Base32, so no codes can be generated",
+        |a| secret_len(a).is_none(),
+    );
+    push_matching(
+        findings,
+        accounts,
+        "weak_secret",
+        Severity::High,
+        "Secret is shorter than 80 bits and could be brute-forced",
+        |a| secret_len(a).is_some_and(|n| n < WEAK_SECRET_BYTES),
+    );
+    push_matching(
+        findings,
+        accounts,
+        "short_secret",
+        Severity::Medium,
+        "Secret is shorter than the 128 bits required by RFC 4226",
+        |a| secret_len(a).is_some_and(|n| (WEAK_SECRET_BYTES..MIN_SECRET_BYTES).contains(&n)),
+    );
+    push_matching(
+        findings,
+        accounts,
+        "sha1",
+        Severity::Low,
+        "Uses SHA-1; switch to SHA-256 if the service supports it",
+        |a| a.algorithm.eq_ignore_ascii_case("SHA1"),
+    );
+}
+
+fn check_shared_secrets(accounts: &[Account], findings: &mut Vec<Finding>) {
+    for group in duplicates::find_duplicates(accounts) {
+        if group.kind != DuplicateKind::SameSecret {
+            continue;
+        }
+        findings.push(Finding {
+            code: "shared_secret".to_string(),
+            severity: Severity::High,
+            message: format!(
+                "{} accounts share the same secret and generate the same codes",
+                group.account_ids.len()
+            ),
+            account_ids: group.account_ids,
+        });
+    }
+}
+
+fn check_parameters(accounts: &[Account], findings: &mut Vec<Finding>) {
+    push_matching(
+        findings,
+        accounts,
+        "missing_issuer",
+        Severity::Low,
+        "Account has no issuer, which makes it easy to confuse with others",
+        |a| a.issuer.trim().is_empty(),
+    );
+    push_matching(
+        findings,
+        accounts,
+        "few_digits",
+        Severity::Medium,
+        "Codes have fewer than 6 digits and are easier to guess",
+        |a| a.digits < 6,
+    );
+    push_matching(
+        findings,
+        accounts,
+        "unusual_digits",
+        Severity::Info,
+        "Code length is not the usual 6 or 8 digits",
+        |a| a.digits > 6 && a.digits != 8,
+    );
+    push_matching(
+        findings,
+        accounts,
+        "long_period",
+        Severity::Low,
+        "Codes stay valid for more than 60 seconds",
+        |a| a.period > 60,
+    );
+    push_matching(
+        findings,
+        accounts,
+        "unusual_period",
+        Severity::Info,
+        "Code period is not the usual 30 seconds",
+        |a| a.period != 30 && a.period <= 60,
+    );
+}
+
+fn check_backup(
+    accounts: &[Account],
+    last_backup_at: Option<u64>,
+    now: u64,
+    findings: &mut Vec<Finding>,
+) {
+    if accounts.is_empty() {
+        return;
+    }
+
+    let Some(last_backup_at) = last_backup_at else {
+        findings.push(Finding {
+            code: "no_backup".to_string(),
+            severity: Severity::High,
+            message: "No backup has been exported from this device".to_string(),
+            account_ids: Vec::new(),
+        });
+        return;
+    };
+
+    let age = now.saturating_sub(last_backup_at);
+    let changed_since: Vec<String> = accounts
+        .iter()
+        .filter(|a| a.last_modified > last_backup_at)
+        .map(|a| a.id.clone())
+        .collect();
+
+    let severity = if age > BACKUP_VERY_STALE_SECS {
+        Severity::High
+    } else if age > BACKUP_STALE_SECS || !changed_since.is_empty() {
+        Severity::Medium
+    } else {
+        return;
+    };
+
+    let message = if changed_since.is_empty() {
+        format!("Last backup is {} days old", age / DAY_SECS)
+    } else {
+        format!(
+            "Last backup is {} days old and is missing changes to {} account(s)",
+            age / DAY_SECS,
+            changed_since.len()
+        )
+    };
+
+    findings.push(Finding {
+        code: "stale_backup".to_string(),
+        severity,
+        message,
+        account_ids: changed_since,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn make_account(id: &str, secret: &str) -> Account {
+        Account {
+            id: id.to_string(),
+            issuer: "Service".to_string(),
+            label: format!("{id}@example.com"),
+            secret: secret.to_string(),
+            algorithm: "SHA256".to_string(),
+            digits: 6,
+            period: 30,
+            icon: None,
+            last_modified: NOW - 365 * DAY_SECS,
+            tags: Vec::new(),
+        }
+    }
+
+    /// A 160-bit secret, unique per id.
+    fn strong_secret(n: u8) -> String {
+        data_encoding::BASE32_NOPAD.encode(&[n; 20])
+    }
+
+    fn find<'a>(report: &'a HealthReport, code: &str) -> Option<&'a Finding> {
+        report.findings.iter().find(|f| f.code == code)
+    }
+
+    #[test]
+    fn test_healthy_vault_has_no_findings() {
+        let accounts = vec![
+            make_account("a", &strong_secret(1)),
+            make_account("b", &strong_secret(2)),
+        ];
+        let report = audit(&accounts, Some(NOW - 60), NOW);
+        assert_eq!(report.accounts_checked, 2);
+        assert!(report.findings.is_empty(), "{:?}", report.findings);
+    }
+
+    #[test]
+    fn test_secret_length_findings() {
+        let accounts = vec![
+            make_account("weak", "JBSWY3DPEHPK3PXP"), // 80 bits is short, not weak
+            make_account("tiny", "JBSWY3DP"),         // 40 bits
+            make_account("bad", "NOT*BASE32"),
+            make_account("ok", &strong_secret(1)),
+        ];
+        let report = audit(&accounts, Some(NOW), NOW);
+        assert_eq!(
+            find(&report, "short_secret").unwrap().account_ids,
+            vec!["weak"]
+        );
+        assert_eq!(
+            find(&report, "weak_secret").unwrap().account_ids,
+            vec!["tiny"]
+        );
+        assert_eq!(
+            find(&report, "invalid_secret").unwrap().account_ids,
+            vec!["bad"]
+        );
+    }
+
+    #[test]
+    fn test_sha1_and_missing_issuer() {
+        let mut sha1 = make_account("sha1", &strong_secret(1));
+        sha1.algorithm = "SHA1".to_string();
+        let mut anon = make_account("anon", &strong_secret(2));
+        anon.issuer = "  ".to_string();
+        let report = audit(&[sha1, anon], Some(NOW), NOW);
+
+        let finding = find(&report, "sha1").unwrap();
+        assert_eq!(finding.severity, Severity::Low);
+        assert_eq!(finding.account_ids, vec!["sha1"]);
+        assert_eq!(
+            find(&report, "missing_issuer").unwrap().account_ids,
+            vec!["anon"]
+        );
+    }
+
+    #[test]
+    fn test_shared_secret_grouped() {
+        let secret = strong_secret(7);
+        let accounts = vec![
+            make_account("a", &secret),
+            make_account("b", &secret.to_lowercase()),
+            make_account("c", &strong_secret(8)),
+        ];
+        let report = audit(&accounts, Some(NOW), NOW);
+        let finding = find(&report, "shared_secret").unwrap();
+        assert_eq!(finding.severity, Severity::High);
+        assert_eq!(finding.account_ids, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_unusual_parameters() {
+        let mut four = make_account("four", &strong_secret(1));
+        four.digits = 4;
+        let mut seven = make_account("seven", &strong_secret(2));
+        seven.digits = 7;
+        let mut slow = make_account("slow", &strong_secret(3));
+        slow.period = 120;
+        let mut sixty = make_account("sixty", &strong_secret(4));
+        sixty.period = 60;
+        let report = audit(&[four, seven, slow, sixty], Some(NOW), NOW);
+
+        assert_eq!(
+            find(&report, "few_digits").unwrap().account_ids,
+            vec!["four"]
+        );
+        assert_eq!(
+            find(&report, "unusual_digits").unwrap().account_ids,
+            vec!["seven"]
+        );
+        assert_eq!(
+            find(&report, "long_period").unwrap().account_ids,
+            vec!["slow"]
+        );
+        assert_eq!(
+            find(&report, "unusual_period").unwrap().account_ids,
+            vec!["sixty"]
+        );
+    }
+
+    #[test]
+    fn test_backup_findings() {
+        let accounts = vec![make_account("a", &strong_secret(1))];
+
+        let report = audit(&accounts, None, NOW);
+        assert_eq!(find(&report, "no_backup").unwrap().severity, Severity::High);
+
+        let report = audit(&accounts, Some(NOW - 45 * DAY_SECS), NOW);
+        let finding = find(&report, "stale_backup").unwrap();
+        assert_eq!(finding.severity, Severity::Medium);
+        assert!(finding.account_ids.is_empty());
+
+        let report = audit(&accounts, Some(NOW - 120 * DAY_SECS), NOW);
+        assert_eq!(
+            find(&report, "stale_backup").unwrap().severity,
+            Severity::High
+        );
+
+        // Recent backup that predates an account change
+        let mut changed = accounts.clone();
+        changed[0].last_modified = NOW - DAY_SECS;
+        let report = audit(&changed, Some(NOW - 2 * DAY_SECS), NOW);
+        let finding = find(&report, "stale_backup").unwrap();
+        assert_eq!(finding.severity, Severity::Medium);
+        assert_eq!(finding.account_ids, vec!["a"]);
+
+        // Empty vault needs no backup
+        assert!(audit(&[], None, NOW).findings.is_empty());
+    }
+
+    #[test]
+    fn test_findings_sorted_by_severity() {
+        let mut sha1 = make_account("sha1", &strong_secret(1));
+        sha1.algorithm = "SHA1".to_string();
+        let bad = make_account("bad", "!!!");
+        let report = audit(&[sha1, bad], None, NOW);
+        let severities: Vec<Severity> = report.findings.iter().map(|f| f.severity).collect();
+        let mut sorted = severities.clone();
+        sorted.sort_by(|a, b| b.cmp(a));
+        assert_eq!(severities, sorted);
+        assert_eq!(severities[0], Severity::Critical);
+    }
+}
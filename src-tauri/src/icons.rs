@@ -0,0 +1,365 @@
+//! Offline issuer-to-icon registry.
+//!
+//! Maps well-known issuers to icons bundled with the app so accounts get a
+//! recognizable icon without any network lookup. Issuers are matched by
+//! normalized name, alias, or domain (in the issuer or in an email label).
+
+use crate::search::edit_distance;
+use crate::storage::Account;
+
+struct IconEntry {
+    slug: &'static str,
+    /// Display name and aliases, matched after normalization.
+    names: &'static [&'static str],
+    /// Domains owned by the issuer; subdomains match too.
+    domains: &'static [&'static str],
+    svg: &'static str,
+}
+
+macro_rules! icon {
+    ($slug:literal, [$($name:literal),* $(,)?], [$($domain:literal),* $(,)?]) => {
+        IconEntry {
+            slug: $slug,
+            names: &[$($name),*],
+            domains: &[$($domain),*],
+            svg: include_str!(concat!("../issuer-icons/", $slug, ".svg")),
+        }
+    };
+}
+
+const REGISTRY: &[IconEntry] = &[
+    icon!(
+        "1password",
+        ["1Password", "One Password"],
+        ["1password.com"]
+    ),
+    icon!(
+        "amazon",
+        ["Amazon", "Amazon.com"],
+        ["amazon.com", "amazon.co.uk", "amazon.de"]
+    ),
+    icon!(
+        "apple",
+        ["Apple", "Apple ID", "iCloud"],
+        ["apple.com", "icloud.com"]
+    ),
+    icon!(
+        "atlassian",
+        ["Atlassian", "Jira", "Confluence"],
+        ["atlassian.com", "atlassian.net"]
+    ),
+    icon!(
+        "aws",
+        [
+            "AWS",
+            "Amazon Web Services",
+            "Amazon AWS",
+            "AWS IAM",
+            "AWS SSO"
+        ],
+        ["aws.amazon.com", "awsapps.com"]
+    ),
+    icon!(
+        "azure",
+        [
+            "Azure",
+            "Microsoft Azure",
+            "Azure AD",
+            "Azure Active Directory",
+            "Entra ID",
+            "Microsoft Entra"
+        ],
+        ["azure.com", "portal.azure.com"]
+    ),
+    icon!("binance", ["Binance"], ["binance.com"]),
+    icon!("bitbucket", ["Bitbucket"], ["bitbucket.org"]),
+    icon!("bitwarden", ["Bitwarden"], ["bitwarden.com"]),
+    icon!("cloudflare", ["Cloudflare"], ["cloudflare.com"]),
+    icon!("coinbase", ["Coinbase"], ["coinbase.com"]),
+    icon!(
+        "digitalocean",
+        ["DigitalOcean", "Digital Ocean"],
+        ["digitalocean.com"]
+    ),
+    icon!("discord", ["Discord"], ["discord.com", "discordapp.com"]),
+    icon!(
+        "docker",
+        ["Docker", "Docker Hub"],
+        ["docker.com", "hub.docker.com"]
+    ),
+    icon!("dropbox", ["Dropbox"], ["dropbox.com"]),
+    icon!("epicgames", ["Epic Games", "Epic"], ["epicgames.com"]),
+    icon!(
+        "facebook",
+        ["Facebook", "Meta"],
+        ["facebook.com", "meta.com"]
+    ),
+    icon!("github", ["GitHub"], ["github.com"]),
+    icon!("gitlab", ["GitLab"], ["gitlab.com"]),
+    icon!(
+        "google",
+        [
+            "Google",
+            "Gmail",
+            "Google Workspace",
+            "G Suite",
+            "Google Cloud"
+        ],
+        ["google.com", "gmail.com"]
+    ),
+    icon!("heroku", ["Heroku"], ["heroku.com"]),
+    icon!("instagram", ["Instagram"], ["instagram.com"]),
+    icon!("linkedin", ["LinkedIn"], ["linkedin.com"]),
+    icon!(
+        "microsoft",
+        [
+            "Microsoft",
+            "Microsoft Account",
+            "Outlook",
+            "Office 365",
+            "Microsoft 365",
+            "Xbox"
+        ],
+        ["microsoft.com", "live.com", "outlook.com", "hotmail.com"]
+    ),
+    icon!("npm", ["npm", "npmjs"], ["npmjs.com"]),
+    icon!("okta", ["Okta"], ["okta.com"]),
+    icon!("paypal", ["PayPal"], ["paypal.com"]),
+    icon!(
+        "proton",
+        ["Proton", "ProtonMail", "Proton Mail", "Proton Pass"],
+        ["proton.me", "protonmail.com"]
+    ),
+    icon!("reddit", ["Reddit"], ["reddit.com"]),
+    icon!("slack", ["Slack"], ["slack.com"]),
+    icon!("stripe", ["Stripe"], ["stripe.com"]),
+    icon!("twitch", ["Twitch"], ["twitch.tv"]),
+    icon!(
+        "x",
+        ["X", "Twitter", "X (Twitter)"],
+        ["twitter.com", "x.com"]
+    ),
+];
+
+/// Aliases shorter than this are only matched exactly, never as a word inside
+/// a longer issuer ("X" must not claim "Space X Corp").
+const MIN_WORD_ALIAS_LEN: usize = 3;
+
+/// Issuers shorter than this are never matched with a typo allowance.
+const MIN_FUZZY_LEN: usize = 5;
+
+/// Resolve the icon slug for an issuer, falling back to the domain of an
+/// email-style label. Returns `None` when nothing in the registry matches.
+pub fn resolve(issuer: &str, label: &str) -> Option<&'static str> {
+    let normalized = normalize(issuer);
+
+    if !normalized.is_empty() {
+        if let Some(entry) = find_by_name(&normalized) {
+            return Some(entry.slug);
+        }
+    }
+
+    if issuer.contains('.') {
+        if let Some(entry) = find_by_domain(issuer) {
+            return Some(entry.slug);
+        }
+    }
+
+    if let Some(entry) = find_by_words(issuer) {
+        return Some(entry.slug);
+    }
+
+    if let Some((_, domain)) = label.rsplit_once('@') {
+        if let Some(entry) = find_by_domain(domain) {
+            return Some(entry.slug);
+        }
+    }
+
+    find_by_typo(&normalized).map(|entry| entry.slug)
+}
+
+/// Fill in the icon of an account that doesn't have one yet.
+pub fn apply(account: &mut Account) {
+    if account.icon.is_none() {
+        account.icon = resolve(&account.issuer, &account.label).map(str::to_string);
+    }
+}
+
+/// SVG markup for a bundled icon.
+pub fn icon_svg(slug: &str) -> Option<&'static str> {
+    REGISTRY.iter().find(|e| e.slug == slug).map(|e| e.svg)
+}
+
+/// Primary domain of a bundled icon's issuer.
+pub fn primary_domain(slug: &str) -> Option<&'static str> {
+    REGISTRY
+        .iter()
+        .find(|e| e.slug == slug)
+        .and_then(|e| e.domains.first().copied())
+}
+
+/// Lowercase and strip everything but letters and digits.
+fn normalize(s: &str) -> String {
+    s.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn find_by_name(normalized: &str) -> Option<&'static IconEntry> {
+    REGISTRY.iter().find(|e| {
+        normalize(e.slug) == normalized || e.names.iter().any(|n| normalize(n) == normalized)
+    })
+}
+
+/// Match a host (or URL) against the registered domains. The most specific
+/// domain wins, so `aws.amazon.com` resolves to AWS rather than Amazon.
+fn find_by_domain(input: &str) -> Option<&'static IconEntry> {
+    let host = host_of(input);
+    if host.is_empty() {
+        return None;
+    }
+
+    REGISTRY
+        .iter()
+        .flat_map(|e| e.domains.iter().map(move |d| (e, *d)))
+        .filter(|(_, d)| host == *d || host.ends_with(&format!(".{d}")))
+        .max_by_key(|(_, d)| d.len())
+        .map(|(e, _)| e)
+}
+
+/// Extract the lowercase host from a bare domain or URL.
+fn host_of(input: &str) -> String {
+    let s = input.trim().to_lowercase();
+    let s = s.split_once("://").map(|(_, rest)| rest).unwrap_or(&s);
+    let s = s.split(['/', '?', '#', ':']).next().unwrap_or("");
+    s.strip_prefix("www.").unwrap_or(s).to_string()
+}
+
+/// Match contiguous runs of words in the issuer against the aliases, trying
+/// the longest runs first ("Amazon Web Services Console" → aws, not amazon).
+fn find_by_words(issuer: &str) -> Option<&'static IconEntry> {
+    let words: Vec<String> = issuer
+        .split(|c: char| !c.is_alphanumeric())
+        .map(normalize)
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    for len in (1..=words.len()).rev() {
+        for window in words.windows(len) {
+            let candidate = window.concat();
+            if candidate.chars().count() < MIN_WORD_ALIAS_LEN {
+                continue;
+            }
+            if let Some(entry) = find_by_name(&candidate) {
+                return Some(entry);
+            }
+        }
+    }
+    None
+}
+
+/// Tolerate a single typo ("Githb", "Dropbx") for reasonably long issuers.
+fn find_by_typo(normalized: &str) -> Option<&'static IconEntry> {
+    if normalized.chars().count() < MIN_FUZZY_LEN {
+        return None;
+    }
+    let target: Vec<char> = normalized.chars().collect();
+    REGISTRY.iter().find(|e| {
+        e.names.iter().any(|n| {
+            let name: Vec<char> = normalize(n).chars().collect();
+            name.len() >= MIN_FUZZY_LEN && edit_distance(&name, &target) <= 1
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_exact_names() {
+        assert_eq!(resolve("GitHub", ""), Some("github"));
+        assert_eq!(resolve("github", ""), Some("github"));
+        assert_eq!(resolve("Google", ""), Some("google"));
+        assert_eq!(resolve("Twitter", ""), Some("x"));
+        assert_eq!(resolve("X", ""), Some("x"));
+    }
+
+    #[test]
+    fn test_resolve_aliases() {
+        assert_eq!(resolve("Amazon Web Services", ""), Some("aws"));
+        assert_eq!(resolve("Microsoft Azure", ""), Some("azure"));
+        assert_eq!(resolve("Docker Hub", ""), Some("docker"));
+        assert_eq!(resolve("Google Workspace", ""), Some("google"));
+    }
+
+    #[test]
+    fn test_resolve_longest_word_run_wins() {
+        assert_eq!(resolve("Amazon Web Services (root)", ""), Some("aws"));
+        assert_eq!(resolve("GitLab - work", ""), Some("gitlab"));
+        assert_eq!(resolve("Amazon Seller Central", ""), Some("amazon"));
+    }
+
+    #[test]
+    fn test_resolve_domains() {
+        assert_eq!(resolve("github.com", ""), Some("github"));
+        assert_eq!(
+            resolve("https://www.dropbox.com/login", ""),
+            Some("dropbox")
+        );
+        assert_eq!(resolve("signin.aws.amazon.com", ""), Some("aws"));
+        assert_eq!(resolve("amazon.com", ""), Some("amazon"));
+    }
+
+    #[test]
+    fn test_resolve_from_label_email_domain() {
+        assert_eq!(resolve("", "alice@gmail.com"), Some("google"));
+        assert_eq!(resolve("Corp SSO", "bob@outlook.com"), Some("microsoft"));
+    }
+
+    #[test]
+    fn test_resolve_tolerates_single_typo() {
+        assert_eq!(resolve("Githb", ""), Some("github"));
+        assert_eq!(resolve("Dropbx", ""), Some("dropbox"));
+        assert_eq!(resolve("Gitbub Enterprise", ""), None);
+    }
+
+    #[test]
+    fn test_resolve_unknown_and_short_aliases() {
+        assert_eq!(resolve("My Homelab", "admin"), None);
+        assert_eq!(resolve("Space X Corp", ""), None);
+        assert_eq!(resolve("", ""), None);
+    }
+
+    #[test]
+    fn test_apply_keeps_existing_icon() {
+        let mut account = Account {
+            id: "a1".into(),
+            issuer: "GitHub".into(),
+            label: "user".into(),
+            secret: "JBSWY3DPEHPK3PXP".into(),
+            algorithm: "SHA1".into(),
+            digits: 6,
+            period: 30,
+            icon: Some("custom".into()),
+            last_modified: 0,
+            tags: Vec::new(),
+        };
+        apply(&mut account);
+        assert_eq!(account.icon.as_deref(), Some("custom"));
+
+        account.icon = None;
+        apply(&mut account);
+        assert_eq!(account.icon.as_deref(), Some("github"));
+    }
+
+    #[test]
+    fn test_every_entry_has_svg() {
+        for entry in REGISTRY {
+            assert!(entry.svg.starts_with("<svg"), "{} has no SVG", entry.slug);
+            assert_eq!(icon_svg(entry.slug), Some(entry.svg));
+        }
+        assert!(icon_svg("nope").is_none());
+    }
+}
@@ -1,27 +1,181 @@
 use crate::google_auth_proto;
 use crate::storage::Account;
+use aes_gcm::{
+    aead::{Aead, KeyInit},
+    Aes256Gcm, Nonce,
+};
 use prost::Message;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use zeroize::Zeroizing;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct ImportResult {
     pub format: String,
     pub accounts: Vec<Account>,
-    pub skipped: usize,
+    /// Source index of each account, in step with `accounts`.
+    pub sources: Vec<usize>,
+    pub skipped: Vec<SkippedEntry>,
+}
+
+/// An entry of the source file that was not imported.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct SkippedEntry {
+    /// Position of the entry in the source, from 0: the item, line, row or
+    /// QR code, depending on the format.
+    pub index: usize,
+    /// As far as the entry could be read; empty when unknown.
+    pub issuer: String,
+    pub label: String,
+    pub reason: SkipReason,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    /// HOTP, Steam and other codes that are not plain TOTP
+    UnsupportedType,
+    /// A password manager item or QR code without a TOTP
+    NoTotp,
+    /// Missing, or not valid Base32
+    InvalidSecret,
+    UnsupportedAlgorithm,
+    InvalidDigits,
+    InvalidPeriod,
+    /// In the other app's trash
+    Trashed,
+    /// Not a readable otpauth:// URI or TOTP field
+    Unreadable,
+    /// Already in the vault
+    Duplicate,
+}
+
+impl ImportResult {
+    pub fn new(format: &str) -> Self {
+        ImportResult {
+            format: format.to_string(),
+            accounts: Vec::new(),
+            sources: Vec::new(),
+            skipped: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, index: usize, account: Account) {
+        self.accounts.push(account);
+        self.sources.push(index);
+    }
+
+    pub fn skip(&mut self, index: usize, issuer: &str, label: &str, reason: SkipReason) {
+        self.skipped.push(SkippedEntry {
+            index,
+            issuer: issuer.trim().to_string(),
+            label: label.trim().to_string(),
+            reason,
+        });
+    }
+
+    /// Record one entry: its account, or why it was skipped. `issuer` and
+    /// `label` name a skipped entry in the report.
+    pub fn record(
+        &mut self,
+        index: usize,
+        issuer: &str,
+        label: &str,
+        parsed: Result<Account, SkipReason>,
+    ) {
+        match parsed {
+            Ok(account) => self.add(index, account),
+            Err(reason) => self.skip(index, issuer, label, reason),
+        }
+    }
+
+    /// Each account with its source index.
+    pub fn into_entries(self) -> impl Iterator<Item = (usize, Account)> {
+        self.sources.into_iter().zip(self.accounts)
+    }
+
+    /// Number of source entries seen so far.
+    fn entry_count(&self) -> usize {
+        let skipped = self.skipped.iter().map(|entry| entry.index);
+        self.sources
+            .iter()
+            .copied()
+            .chain(skipped)
+            .max()
+            .map_or(0, |i| i + 1)
+    }
+
+    /// Add the entries of `other`, numbered after this result's.
+    pub fn append(&mut self, other: ImportResult) {
+        let offset = self.entry_count();
+        for (account, index) in other.accounts.into_iter().zip(other.sources) {
+            self.add(offset + index, account);
+        }
+        self.skipped
+            .extend(other.skipped.into_iter().map(|entry| SkippedEntry {
+                index: offset + entry.index,
+                ..entry
+            }));
+    }
 }
 
 /// Auto-detect the import format and parse accounts from the file data.
-pub fn parse_import(data: &[u8]) -> Result<ImportResult, String> {
-    let text = std::str::from_utf8(data)
-        .map_err(|_| "File is not valid UTF-8 text")?;
+/// `password` opens encrypted exports. Accounts from well-known issuers get
+/// a bundled icon assigned.
+pub fn parse_import(data: &[u8], password: Option<&str>) -> Result<ImportResult, String> {
+    let mut result = detect_and_parse(data, password)?;
+    for account in &mut result.accounts {
+        crate::icons::apply(account);
+    }
+    Ok(result)
+}
+
+fn detect_and_parse(data: &[u8], password: Option<&str>) -> Result<ImportResult, String> {
+    // Encrypted Authenticator Pro backups (.authpro)
+    if data.starts_with(AUTHPRO_HEADER) || data.starts_with(AUTHPRO_LEGACY_HEADER) {
+        return parse_authpro_encrypted(data, password);
+    }
+
+    // Screenshots or photos of QR codes
+    if crate::qr_image::looks_like_image(data) {
+        return crate::qr_image::parse_image(data);
+    }
+
+    // 1Password .1pux archives are ZIP files
+    if data.starts_with(b"PK\x03\x04") {
+        return parse_onepux(data);
+    }
+
+    let text = match std::str::from_utf8(data) {
+        Ok(text) => text,
+        Err(_) if looks_like_encrypted_andotp(data) => {
+            return parse_andotp_encrypted(data, password);
+        }
+        Err(_) if data.starts_with(&crate::keepass::KDBX_SIGNATURE) => {
+            return Err(
+                "KeePass database files are encrypted. In KeePassXC, use Database > \
+                        Export to CSV or XML and import that file instead."
+                    .to_string(),
+            );
+        }
+        Err(_) => return Err("File is not valid UTF-8 text".to_string()),
+    };
     let trimmed = text.trim();
 
     if trimmed.is_empty() {
         return Err("File is empty".to_string());
     }
 
-    // Google Auth migration URI
+    // Google Auth migration URI, or all the QR codes of one export
     if trimmed.starts_with("otpauth-migration://") {
+        let uris: Vec<&str> = trimmed
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .collect();
+        if uris.len() > 1 {
+            return parse_migration_uris(uris);
+        }
         return parse_google_auth_migration(trimmed);
     }
 
@@ -32,25 +186,61 @@ pub fn parse_import(data: &[u8]) -> Result<ImportResult, String> {
 
     // JSON formats
     if trimmed.starts_with('{') || trimmed.starts_with('[') {
-        return parse_json_import(trimmed);
+        return parse_json_import(trimmed, password);
+    }
+
+    // KeePass 2 XML export
+    if trimmed.starts_with('<') {
+        return crate::keepass::parse_xml(trimmed);
+    }
+
+    // KeePassXC CSV export
+    if crate::keepass::looks_like_csv(trimmed) {
+        return crate::keepass::parse_csv(trimmed);
+    }
+
+    // Encrypted andOTP ciphertext that happens to be valid UTF-8
+    if looks_like_encrypted_andotp(data) {
+        return parse_andotp_encrypted(data, password);
     }
 
-    Err("Unrecognized file format. Supported: Aegis, 2FAS, andOTP, Google Authenticator, otpauth:// URI list".to_string())
+    Err(
+        "Unrecognized file format. Supported: Aegis, 2FAS, andOTP, Google Authenticator, \
+         FreeOTP+, Ente Auth, Authenticator Pro, Bitwarden, 1Password, Proton Pass, KeePassXC, \
+         otpauth:// URI list"
+            .to_string(),
+    )
 }
 
-fn parse_json_import(text: &str) -> Result<ImportResult, String> {
+fn parse_json_import(text: &str, password: Option<&str>) -> Result<ImportResult, String> {
     let value: serde_json::Value =
         serde_json::from_str(text).map_err(|e| format!("Invalid JSON: {e}"))?;
 
     if let Some(obj) = value.as_object() {
         if obj.contains_key("db") {
-            return parse_aegis(text);
+            return parse_aegis(text, password);
+        }
+        if obj.contains_key("services") || obj.contains_key("servicesEncrypted") {
+            return parse_twofas(text, password);
+        }
+        if obj.contains_key("tokens") {
+            return parse_freeotp_plus(text);
+        }
+        if obj.contains_key("encryptedData") && obj.contains_key("kdfParams") {
+            return parse_ente_encrypted(text, password);
+        }
+        if obj.contains_key("Authenticators") {
+            return parse_authpro(text);
         }
-        if obj.contains_key("services") {
-            return parse_twofas(text);
+        if obj.contains_key("items") || obj.contains_key("passwordProtected") {
+            return parse_bitwarden(text);
+        }
+        if obj.contains_key("vaults") {
+            return parse_proton_pass(text);
         }
         return Err(
-            "Unrecognized JSON format. Expected Aegis (\"db\" key) or 2FAS (\"services\" key)."
+            "Unrecognized JSON format. Expected Aegis, 2FAS, FreeOTP+, Ente Auth, \
+             Authenticator Pro, Bitwarden or Proton Pass."
                 .to_string(),
         );
     }
@@ -64,12 +254,22 @@ fn parse_json_import(text: &str) -> Result<ImportResult, String> {
 
 // --- Aegis ---
 
+const AEGIS_SLOT_PASSWORD: u32 = 1;
+/// Aegis derives the key of a password slot with scrypt (N=2^15, r=8, p=1,
+/// so 32 MiB). A crafted file picks its own parameters, so the memory scrypt
+/// needs (128·r·N bytes) and the work (N·p) are bounded, as is the number of
+/// slots tried.
+const AEGIS_MAX_SCRYPT_MEMORY: u64 = 256 * 1024 * 1024;
+const AEGIS_MAX_SCRYPT_N_P: u64 = 1 << 20;
+const AEGIS_MAX_PASSWORD_SLOTS: usize = 4;
+
 #[derive(Deserialize)]
 struct AegisExport {
+    // Only read for encrypted vaults; plaintext exports may carry nulls
+    header: Option<serde_json::Value>,
     db: AegisDb,
 }
 
-#[allow(dead_code)]
 #[derive(Deserialize)]
 #[serde(untagged)]
 enum AegisDb {
@@ -77,6 +277,31 @@ enum AegisDb {
     Encrypted(String),
 }
 
+#[derive(Deserialize)]
+struct AegisHeader {
+    slots: Vec<AegisSlot>,
+    params: AegisKeyParams,
+}
+
+#[derive(Deserialize)]
+struct AegisSlot {
+    #[serde(rename = "type")]
+    slot_type: u32,
+    /// The master key, encrypted with the slot's key.
+    key: String,
+    key_params: AegisKeyParams,
+    n: Option<u64>,
+    r: Option<u32>,
+    p: Option<u32>,
+    salt: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AegisKeyParams {
+    nonce: String,
+    tag: String,
+}
+
 #[derive(Deserialize)]
 struct AegisDbPlaintext {
     entries: Vec<AegisEntry>,
@@ -99,69 +324,140 @@ struct AegisInfo {
     period: Option<u32>,
 }
 
-fn parse_aegis(text: &str) -> Result<ImportResult, String> {
+fn parse_aegis(text: &str, password: Option<&str>) -> Result<ImportResult, String> {
     let export: AegisExport =
         serde_json::from_str(text).map_err(|e| format!("Failed to parse Aegis JSON: {e}"))?;
 
     let entries = match export.db {
         AegisDb::Plaintext(db) => db.entries,
-        AegisDb::Encrypted(_) => {
-            return Err(
-                "This Aegis backup is encrypted. Please export an unencrypted backup from Aegis."
-                    .to_string(),
-            );
+        AegisDb::Encrypted(db) => {
+            let password = password.ok_or_else(|| {
+                "This Aegis backup is encrypted. Enter its password to import it.".to_string()
+            })?;
+            decrypt_aegis(export.header, &db, password)?.entries
         }
     };
 
-    let mut accounts = Vec::new();
-    let mut skipped = 0;
+    let mut result = ImportResult::new("Aegis");
 
-    for entry in entries {
+    for (index, entry) in entries.into_iter().enumerate() {
+        let issuer = entry.issuer.unwrap_or_default();
+        let label = entry.name.unwrap_or_default();
         if entry.entry_type.to_lowercase() != "totp" {
-            skipped += 1;
+            result.skip(index, &issuer, &label, SkipReason::UnsupportedType);
             continue;
         }
 
-        let secret = normalize_secret(&entry.info.secret);
-        if secret.is_empty() {
-            skipped += 1;
-            continue;
-        }
+        let info = entry.info;
+        let parsed = totp_account(
+            &info.secret,
+            info.algo.as_deref().unwrap_or("SHA1"),
+            info.digits.unwrap_or(6),
+            info.period.unwrap_or(30),
+        )
+        .map(|account| Account {
+            issuer: issuer.clone(),
+            label: label.clone(),
+            ..account
+        });
+        result.record(index, &issuer, &label, parsed);
+    }
 
-        let algorithm = normalize_algorithm(entry.info.algo.as_deref().unwrap_or("SHA1"));
-        let digits = entry.info.digits.unwrap_or(6);
-        let period = entry.info.period.unwrap_or(30);
+    Ok(result)
+}
 
-        if !is_valid_account(&algorithm, digits, period) {
-            skipped += 1;
-            continue;
+/// Decrypt an Aegis vault: unlock the master key from a password slot, then
+/// decrypt the database with it.
+fn decrypt_aegis(
+    header: Option<serde_json::Value>,
+    db: &str,
+    password: &str,
+) -> Result<AegisDbPlaintext, String> {
+    let header: AegisHeader = header
+        .and_then(|h| serde_json::from_value(h).ok())
+        .ok_or_else(|| "Invalid Aegis backup: missing encryption header".to_string())?;
+
+    let mut password_slots = header
+        .slots
+        .iter()
+        .filter(|slot| slot.slot_type == AEGIS_SLOT_PASSWORD)
+        .peekable();
+    if password_slots.peek().is_none() {
+        return Err("This Aegis backup has no password slot and can't be imported".to_string());
+    }
+    let mut master_key = None;
+    for slot in password_slots.take(AEGIS_MAX_PASSWORD_SLOTS) {
+        let slot_key = aegis_slot_key(slot, password)?;
+        let encrypted_key = decode_hex(&slot.key)?;
+        if let Ok(key) = aegis_open(&slot_key, &slot.key_params, &encrypted_key) {
+            master_key = Some(key);
+            break;
         }
+    }
+    let master_key =
+        master_key.ok_or_else(|| "Wrong password for this Aegis backup".to_string())?;
+
+    let ciphertext = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, db.trim())
+        .map_err(|_| "Invalid Aegis backup: database is not valid base64".to_string())?;
+    let plaintext = aegis_open(&master_key, &header.params, &ciphertext)
+        .map_err(|_| "Aegis backup is damaged and can't be decrypted".to_string())?;
+    serde_json::from_slice(&plaintext).map_err(|e| format!("Failed to parse Aegis vault: {e}"))
+}
 
-        accounts.push(Account {
-            id: uuid::Uuid::new_v4().to_string(),
-            issuer: entry.issuer.unwrap_or_default(),
-            label: entry.name.unwrap_or_default(),
-            secret,
-            algorithm,
-            digits,
-            period,
-            icon: None,
-            last_modified: 0,
-        });
+/// Derive a password slot's key with the slot's scrypt parameters.
+fn aegis_slot_key(slot: &AegisSlot, password: &str) -> Result<Zeroizing<Vec<u8>>, String> {
+    let invalid = || "Invalid Aegis backup: bad password slot".to_string();
+    let n = slot.n.ok_or_else(invalid)?;
+    let r = slot.r.ok_or_else(invalid)?;
+    let p = slot.p.ok_or_else(invalid)?;
+    let salt = decode_hex(slot.salt.as_deref().ok_or_else(invalid)?)?;
+    if !n.is_power_of_two() || n < 2 {
+        return Err(invalid());
+    }
+    let memory = n.saturating_mul(128 * u64::from(r));
+    let work = n.saturating_mul(u64::from(p));
+    if r == 0 || p == 0 || memory > AEGIS_MAX_SCRYPT_MEMORY || work > AEGIS_MAX_SCRYPT_N_P {
+        return Err("Aegis backup uses unsupported key derivation settings".to_string());
     }
+    let log_n = n.trailing_zeros() as u8;
+
+    let params = scrypt::Params::new(log_n, r, p, 32).map_err(|_| invalid())?;
+    let mut key = Zeroizing::new(vec![0u8; 32]);
+    scrypt::scrypt(password.as_bytes(), &salt, &params, &mut key).map_err(|e| {
+        tracing::error!(error = %e, "scrypt key derivation failed");
+        "Failed to derive key from password".to_string()
+    })?;
+    Ok(key)
+}
 
-    Ok(ImportResult {
-        format: "Aegis".to_string(),
-        accounts,
-        skipped,
-    })
+/// AES-256-GCM decryption with the tag stored separately, as Aegis does.
+fn aegis_open(
+    key: &[u8],
+    params: &AegisKeyParams,
+    ciphertext: &[u8],
+) -> Result<Zeroizing<Vec<u8>>, String> {
+    let nonce = decode_hex(&params.nonce)?;
+    let tag = decode_hex(&params.tag)?;
+    if tag.len() != 16 {
+        return Err("Invalid encryption parameters".to_string());
+    }
+    let mut sealed = ciphertext.to_vec();
+    sealed.extend_from_slice(&tag);
+    aes_gcm_open(key, &nonce, &sealed)
 }
 
 // --- 2FAS ---
 
+/// 2FAS derives the key of encrypted backups with PBKDF2-HMAC-SHA256.
+const TWOFAS_PBKDF2_ITERATIONS: u32 = 10_000;
+
 #[derive(Deserialize)]
 struct TwoFASExport {
+    #[serde(default)]
     services: Vec<TwoFASService>,
+    /// `<ciphertext>:<salt>:<iv>`, each base64, when the backup is encrypted.
+    #[serde(rename = "servicesEncrypted")]
+    services_encrypted: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -182,46 +478,32 @@ struct TwoFASOtp {
     token_type: Option<String>,
 }
 
-fn parse_twofas(text: &str) -> Result<ImportResult, String> {
+fn parse_twofas(text: &str, password: Option<&str>) -> Result<ImportResult, String> {
     let export: TwoFASExport =
         serde_json::from_str(text).map_err(|e| format!("Failed to parse 2FAS JSON: {e}"))?;
 
-    let mut accounts = Vec::new();
-    let mut skipped = 0;
+    let services = match export.services_encrypted {
+        Some(encrypted) => {
+            let password = password.ok_or_else(|| {
+                "This 2FAS backup is encrypted. Enter its password to import it.".to_string()
+            })?;
+            decrypt_twofas(&encrypted, password)?
+        }
+        None => export.services,
+    };
+
+    let mut result = ImportResult::new("2FAS");
 
-    for service in export.services {
+    for (index, service) in services.into_iter().enumerate() {
         let otp = match &service.otp {
             Some(otp) => otp,
             None => {
-                skipped += 1;
+                let name = service.name.as_deref().unwrap_or_default();
+                result.skip(index, name, "", SkipReason::NoTotp);
                 continue;
             }
         };
 
-        // Skip non-TOTP
-        if let Some(ref token_type) = otp.token_type {
-            if token_type.to_uppercase() != "TOTP" {
-                skipped += 1;
-                continue;
-            }
-        }
-
-        let raw_secret = service.secret.as_deref().unwrap_or("");
-        let secret = normalize_secret(raw_secret);
-        if secret.is_empty() {
-            skipped += 1;
-            continue;
-        }
-
-        let algorithm = normalize_algorithm(otp.algorithm.as_deref().unwrap_or("SHA1"));
-        let digits = otp.digits.unwrap_or(6);
-        let period = otp.period.unwrap_or(30);
-
-        if !is_valid_account(&algorithm, digits, period) {
-            skipped += 1;
-            continue;
-        }
-
         let issuer = otp
             .issuer
             .clone()
@@ -229,24 +511,54 @@ fn parse_twofas(text: &str) -> Result<ImportResult, String> {
             .unwrap_or_default();
         let label = otp.account.clone().unwrap_or_default();
 
-        accounts.push(Account {
-            id: uuid::Uuid::new_v4().to_string(),
-            issuer,
-            label,
-            secret,
-            algorithm,
-            digits,
-            period,
-            icon: None,
-            last_modified: 0,
+        // Skip non-TOTP
+        if let Some(ref token_type) = otp.token_type {
+            if token_type.to_uppercase() != "TOTP" {
+                result.skip(index, &issuer, &label, SkipReason::UnsupportedType);
+                continue;
+            }
+        }
+
+        let parsed = totp_account(
+            service.secret.as_deref().unwrap_or(""),
+            otp.algorithm.as_deref().unwrap_or("SHA1"),
+            otp.digits.unwrap_or(6),
+            otp.period.unwrap_or(30),
+        )
+        .map(|account| Account {
+            issuer: issuer.clone(),
+            label: label.clone(),
+            ..account
         });
+        result.record(index, &issuer, &label, parsed);
     }
 
-    Ok(ImportResult {
-        format: "2FAS".to_string(),
-        accounts,
-        skipped,
-    })
+    Ok(result)
+}
+
+fn decrypt_twofas(encrypted: &str, password: &str) -> Result<Vec<TwoFASService>, String> {
+    let invalid = || "Invalid 2FAS backup: damaged encrypted data".to_string();
+    let parts: Vec<Vec<u8>> = encrypted
+        .split(':')
+        .map(|part| {
+            base64::Engine::decode(&base64::engine::general_purpose::STANDARD, part.trim())
+                .map_err(|_| invalid())
+        })
+        .collect::<Result<_, _>>()?;
+    let [sealed, salt, iv] = &parts[..] else {
+        return Err(invalid());
+    };
+
+    let mut key = Zeroizing::new([0u8; 32]);
+    pbkdf2::pbkdf2_hmac::<sha2::Sha256>(
+        password.as_bytes(),
+        salt,
+        TWOFAS_PBKDF2_ITERATIONS,
+        &mut *key,
+    );
+    let plaintext = aes_gcm_open(&*key, iv, sealed)
+        .map_err(|_| "Wrong password for this 2FAS backup".to_string())?;
+    serde_json::from_slice(&plaintext).map_err(|e| format!("Failed to parse 2FAS services: {e}"))
 }
 
 // --- andOTP ---
@@ -267,63 +579,585 @@ fn parse_andotp(text: &str) -> Result<ImportResult, String> {
     let entries: Vec<AndOTPEntry> =
         serde_json::from_str(text).map_err(|e| format!("Failed to parse andOTP JSON: {e}"))?;
 
-    let mut accounts = Vec::new();
-    let mut skipped = 0;
+    let mut result = ImportResult::new("andOTP");
+
+    for (index, entry) in entries.into_iter().enumerate() {
+        // andOTP uses "label" which may contain "issuer:label" format
+        let (issuer, label) = if let Some(ref issuer) = entry.issuer {
+            (issuer.clone(), entry.label.unwrap_or_default())
+        } else if let Some(ref raw_label) = entry.label {
+            split_issuer_label(raw_label)
+        } else {
+            (String::new(), String::new())
+        };
 
-    for entry in entries {
         let entry_type = entry.entry_type.as_deref().unwrap_or("TOTP");
         if entry_type.to_uppercase() != "TOTP" {
-            skipped += 1;
+            result.skip(index, &issuer, &label, SkipReason::UnsupportedType);
             continue;
         }
 
-        let secret = normalize_secret(&entry.secret);
-        if secret.is_empty() {
-            skipped += 1;
+        let parsed = totp_account(
+            &entry.secret,
+            entry.algorithm.as_deref().unwrap_or("SHA1"),
+            entry.digits.unwrap_or(6),
+            entry.period.unwrap_or(30),
+        )
+        .map(|account| Account {
+            issuer: issuer.clone(),
+            label: label.clone(),
+            ..account
+        });
+        result.record(index, &issuer, &label, parsed);
+    }
+
+    Ok(result)
+}
+
+/// Encrypted andOTP backups (`.json.aes`) are binary:
+/// iterations (u32 BE) + salt (12) + IV (12) + AES-GCM ciphertext and tag,
+/// keyed with PBKDF2-HMAC-SHA1.
+const ANDOTP_SALT_LEN: usize = 12;
+const ANDOTP_IV_LEN: usize = 12;
+const ANDOTP_HEADER_LEN: usize = 4 + ANDOTP_SALT_LEN + ANDOTP_IV_LEN;
+/// andOTP picks 140,000–160,000; anything far above is not an andOTP file
+/// or would take too long to derive.
+const ANDOTP_MAX_ITERATIONS: u32 = 1_000_000;
+
+fn andotp_iterations(data: &[u8]) -> Option<u32> {
+    let bytes: [u8; 4] = data.get(..4)?.try_into().ok()?;
+    Some(u32::from_be_bytes(bytes))
+}
+
+fn looks_like_encrypted_andotp(data: &[u8]) -> bool {
+    data.len() >= ANDOTP_HEADER_LEN + 16
+        && andotp_iterations(data).is_some_and(|n| (1..=ANDOTP_MAX_ITERATIONS).contains(&n))
+}
+
+fn parse_andotp_encrypted(data: &[u8], password: Option<&str>) -> Result<ImportResult, String> {
+    let password = password.ok_or_else(|| {
+        "This looks like an encrypted andOTP backup. Enter its password to import it.".to_string()
+    })?;
+    let iterations = andotp_iterations(data).unwrap_or_default();
+    let salt = &data[4..4 + ANDOTP_SALT_LEN];
+    let iv = &data[4 + ANDOTP_SALT_LEN..ANDOTP_HEADER_LEN];
+
+    let mut key = Zeroizing::new([0u8; 32]);
+    pbkdf2::pbkdf2_hmac::<sha1::Sha1>(password.as_bytes(), salt, iterations, &mut *key);
+    let plaintext = aes_gcm_open(&*key, iv, &data[ANDOTP_HEADER_LEN..])
+        .map_err(|_| "Wrong password for this andOTP backup".to_string())?;
+    let text = std::str::from_utf8(&plaintext)
+        .map_err(|_| "Decrypted andOTP backup is not valid text".to_string())?;
+    parse_andotp(text)
+}
+
+// --- FreeOTP+ ---
+
+#[derive(Deserialize)]
+struct FreeOtpPlusExport {
+    tokens: Vec<FreeOtpPlusToken>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FreeOtpPlusToken {
+    #[serde(rename = "type")]
+    token_type: Option<String>,
+    /// Raw secret bytes, serialized as Java's signed bytes
+    secret: Vec<i8>,
+    issuer_ext: Option<String>,
+    issuer_int: Option<String>,
+    label: Option<String>,
+    algo: Option<String>,
+    digits: Option<u32>,
+    period: Option<u32>,
+}
+
+fn parse_freeotp_plus(text: &str) -> Result<ImportResult, String> {
+    let export: FreeOtpPlusExport =
+        serde_json::from_str(text).map_err(|e| format!("Failed to parse FreeOTP+ JSON: {e}"))?;
+
+    let mut result = ImportResult::new("FreeOTP+");
+
+    for (index, token) in export.tokens.into_iter().enumerate() {
+        // issuerExt is the name shown in the app; issuerInt the one from the URI
+        let issuer = token
+            .issuer_ext
+            .filter(|issuer| !issuer.is_empty())
+            .or(token.issuer_int)
+            .unwrap_or_default();
+        let label = token.label.unwrap_or_default();
+
+        let token_type = token.token_type.as_deref().unwrap_or("TOTP");
+        if token_type.to_uppercase() != "TOTP" {
+            result.skip(index, &issuer, &label, SkipReason::UnsupportedType);
             continue;
         }
 
-        let algorithm = normalize_algorithm(entry.algorithm.as_deref().unwrap_or("SHA1"));
-        let digits = entry.digits.unwrap_or(6);
-        let period = entry.period.unwrap_or(30);
+        let bytes = Zeroizing::new(token.secret.iter().map(|&b| b as u8).collect::<Vec<u8>>());
+        let parsed = totp_account(
+            &data_encoding::BASE32_NOPAD.encode(&bytes),
+            token.algo.as_deref().unwrap_or("SHA1"),
+            token.digits.unwrap_or(6),
+            token.period.unwrap_or(30),
+        )
+        .map(|account| Account {
+            issuer: issuer.clone(),
+            label: label.clone(),
+            ..account
+        });
+        result.record(index, &issuer, &label, parsed);
+    }
+
+    Ok(result)
+}
+
+// --- Ente Auth ---
+
+/// Caps on the Argon2id cost of encrypted Ente exports; libsodium's
+/// "sensitive" preset (1 GiB, 4 passes) is the most Ente uses.
+const ENTE_MAX_MEM_LIMIT: u64 = 1 << 30;
+const ENTE_MAX_OPS_LIMIT: u32 = 16;
+const SECRETSTREAM_HEADER_LEN: usize = 24;
+const SECRETSTREAM_TAG_FINAL: u8 = 3;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EnteEncryptedExport {
+    kdf_params: EnteKdfParams,
+    encrypted_data: String,
+    /// The secretstream header
+    encryption_nonce: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EnteKdfParams {
+    mem_limit: u64,
+    ops_limit: u32,
+    salt: String,
+}
+
+/// The `codeDisplay` JSON Ente appends to each otpauth URI it exports.
+#[derive(Deserialize, Default)]
+struct EnteCodeDisplay {
+    #[serde(default)]
+    trashed: bool,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// Decrypt an encrypted Ente Auth export, which wraps its plain text export
+/// in libsodium's secretstream, keyed with Argon2id.
+fn parse_ente_encrypted(text: &str, password: Option<&str>) -> Result<ImportResult, String> {
+    let export: EnteEncryptedExport =
+        serde_json::from_str(text).map_err(|e| format!("Failed to parse Ente Auth export: {e}"))?;
+    let password = password.ok_or_else(|| {
+        "This Ente Auth export is encrypted. Enter its password to import it.".to_string()
+    })?;
+
+    let invalid = || "Invalid Ente Auth export: damaged encrypted data".to_string();
+    let decode = |value: &str| {
+        base64::Engine::decode(&base64::engine::general_purpose::STANDARD, value.trim())
+            .map_err(|_| invalid())
+    };
+    let salt = decode(&export.kdf_params.salt)?;
+    let header = decode(&export.encryption_nonce)?;
+    let sealed = decode(&export.encrypted_data)?;
+
+    let params = &export.kdf_params;
+    if params.mem_limit > ENTE_MAX_MEM_LIMIT || params.ops_limit > ENTE_MAX_OPS_LIMIT {
+        return Err("Ente Auth export uses unsupported key derivation settings".to_string());
+    }
+    // libsodium's memory limit is in bytes, Argon2's in KiB
+    let argon2_params = argon2::Params::new(
+        (params.mem_limit / 1024) as u32,
+        params.ops_limit,
+        1,
+        Some(32),
+    )
+    .map_err(|_| invalid())?;
+    let mut key = Zeroizing::new([0u8; 32]);
+    argon2::Argon2::new(
+        argon2::Algorithm::Argon2id,
+        argon2::Version::V0x13,
+        argon2_params,
+    )
+    .hash_password_into(password.as_bytes(), &salt, &mut *key)
+    .map_err(|e| {
+        tracing::error!(error = %e, "Argon2 key derivation failed");
+        "Failed to derive key from password".to_string()
+    })?;
+
+    let plaintext = secretstream_open(&key, &header, &sealed)
+        .map_err(|_| "Wrong password for this Ente Auth export".to_string())?;
+    let text = std::str::from_utf8(&plaintext)
+        .map_err(|_| "Decrypted Ente Auth export is not valid text".to_string())?;
+    let mut result = parse_otpauth_uri_list(text)?;
+    result.format = "Ente Auth".to_string();
+    Ok(result)
+}
+
+/// Open a single-message libsodium `crypto_secretstream_xchacha20poly1305`
+/// stream: `sealed` is the encrypted tag byte, the ciphertext and the MAC.
+fn secretstream_open(
+    key: &[u8; 32],
+    header: &[u8],
+    sealed: &[u8],
+) -> Result<Zeroizing<Vec<u8>>, String> {
+    use chacha20::cipher::{consts::U10, KeyIvInit, StreamCipher};
+    use poly1305::universal_hash::KeyInit;
+
+    let invalid = || "Decryption failed".to_string();
+    if header.len() != SECRETSTREAM_HEADER_LEN || sealed.len() < 17 {
+        return Err(invalid());
+    }
+    let (body, mac) = sealed.split_at(sealed.len() - 16);
+    let (sealed_tag, ciphertext) = body.split_at(1);
+
+    let subkey: Zeroizing<[u8; 32]> =
+        Zeroizing::new(chacha20::hchacha::<U10>(key.into(), header[..16].into()).into());
+    // Counter 1, then the header's remaining 8 bytes
+    let mut nonce = [0u8; 12];
+    nonce[0] = 1;
+    nonce[4..].copy_from_slice(&header[16..]);
+    let mut cipher = chacha20::ChaCha20::new(subkey.as_ref().into(), &nonce.into());
+
+    // Block 0 keys Poly1305, block 1 encrypts the tag, the message starts at block 2
+    let mut poly_key = Zeroizing::new([0u8; 64]);
+    cipher.apply_keystream(&mut *poly_key);
+    let mut block = [0u8; 64];
+    block[0] = sealed_tag[0];
+    cipher.apply_keystream(&mut block);
+    let tag = block[0];
+    block[0] = sealed_tag[0];
+
+    // libsodium pads the ciphertext with `len % 16` zeros, not up to a block
+    // boundary, so the MAC input isn't block aligned
+    let mut authenticated = block.to_vec();
+    authenticated.extend_from_slice(ciphertext);
+    authenticated.resize(authenticated.len() + ciphertext.len() % 16, 0);
+    authenticated.extend_from_slice(&0u64.to_le_bytes());
+    authenticated.extend_from_slice(&((block.len() + ciphertext.len()) as u64).to_le_bytes());
+    let expected = poly1305::Poly1305::new(poly1305::Key::from_slice(&poly_key[..32]))
+        .compute_unpadded(&authenticated);
+    let diff = expected
+        .iter()
+        .zip(mac)
+        .fold(0, |acc, (a, b)| acc | (a ^ b));
+    if diff != 0 {
+        return Err(invalid());
+    }
+    if tag != SECRETSTREAM_TAG_FINAL {
+        return Err(invalid());
+    }
+
+    let mut plaintext = Zeroizing::new(ciphertext.to_vec());
+    cipher.apply_keystream(&mut plaintext);
+    Ok(plaintext)
+}
+
+/// Tidy an otpauth URI from Ente for parsing: take out its `codeDisplay`
+/// parameter and upper-case the algorithm, which Ente writes as "sha1".
+fn split_code_display(uri: &str) -> (String, Option<EnteCodeDisplay>) {
+    let Some((base, query)) = uri.split_once('?') else {
+        return (uri.to_string(), None);
+    };
+    let mut display = None;
+    let mut params = Vec::new();
+    for pair in query.split('&') {
+        match pair.split_once('=') {
+            Some(("codeDisplay", value)) => {
+                display = Some(
+                    percent_decode(value)
+                        .ok()
+                        .and_then(|json| serde_json::from_str(&json).ok())
+                        .unwrap_or_default(),
+                );
+            }
+            Some(("algorithm", value)) => {
+                params.push(format!("algorithm={}", value.to_uppercase()));
+            }
+            _ => params.push(pair.to_string()),
+        }
+    }
+    (format!("{base}?{}", params.join("&")), display)
+}
+
+// --- Authenticator Pro ---
+
+/// Encrypted `.authpro` backups: header + salt (16) + IV (12) + AES-GCM
+/// ciphertext and tag, keyed with Argon2id.
+const AUTHPRO_HEADER: &[u8] = b"AUTHENTICATORPRO";
+/// Backups from before Authenticator Pro switched to Argon2id and AES-GCM
+const AUTHPRO_LEGACY_HEADER: &[u8] = b"AuthenticatorPro";
+const AUTHPRO_SALT_LEN: usize = 16;
+const AUTHPRO_IV_LEN: usize = 12;
+const AUTHPRO_ARGON2_MEMORY_KIB: u32 = 65_536;
+const AUTHPRO_ARGON2_ITERATIONS: u32 = 3;
+const AUTHPRO_ARGON2_PARALLELISM: u32 = 4;
+const AUTHPRO_TYPE_TOTP: u32 = 2;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AuthProBackup {
+    #[serde(default)]
+    authenticators: Vec<AuthProAuthenticator>,
+    #[serde(default)]
+    categories: Vec<AuthProCategory>,
+    #[serde(default)]
+    authenticator_categories: Vec<AuthProAuthenticatorCategory>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AuthProAuthenticator {
+    /// 1 HOTP, 2 TOTP, 3 mOTP, 4 Steam, 5 Yandex
+    #[serde(rename = "Type")]
+    auth_type: u32,
+    issuer: Option<String>,
+    username: Option<String>,
+    secret: String,
+    /// 0 SHA-1, 1 SHA-256, 2 SHA-512
+    algorithm: Option<u32>,
+    digits: Option<u32>,
+    period: Option<u32>,
+}
 
-        if !is_valid_account(&algorithm, digits, period) {
-            skipped += 1;
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AuthProCategory {
+    id: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AuthProAuthenticatorCategory {
+    category_id: String,
+    authenticator_secret: String,
+}
+
+fn parse_authpro(text: &str) -> Result<ImportResult, String> {
+    let backup: AuthProBackup = serde_json::from_str(text)
+        .map_err(|e| format!("Failed to parse Authenticator Pro backup: {e}"))?;
+
+    let mut result = ImportResult::new("Authenticator Pro");
+
+    for (index, auth) in backup.authenticators.iter().enumerate() {
+        let issuer = auth.issuer.clone().unwrap_or_default();
+        let label = auth.username.clone().unwrap_or_default();
+        if auth.auth_type != AUTHPRO_TYPE_TOTP {
+            result.skip(index, &issuer, &label, SkipReason::UnsupportedType);
             continue;
         }
 
-        // andOTP uses "label" which may contain "issuer:label" format
-        let (issuer, label) = if let Some(ref issuer) = entry.issuer {
-            (issuer.clone(), entry.label.unwrap_or_default())
-        } else if let Some(ref raw_label) = entry.label {
-            split_issuer_label(raw_label)
-        } else {
-            (String::new(), String::new())
+        let algorithm = match auth.algorithm.unwrap_or(0) {
+            0 => "SHA1",
+            1 => "SHA256",
+            2 => "SHA512",
+            _ => "",
         };
-
-        accounts.push(Account {
-            id: uuid::Uuid::new_v4().to_string(),
-            issuer,
-            label,
-            secret,
+        let account = match totp_account(
+            &auth.secret,
             algorithm,
-            digits,
-            period,
-            icon: None,
-            last_modified: 0,
-        });
+            auth.digits.unwrap_or(6),
+            auth.period.unwrap_or(30),
+        ) {
+            Ok(account) => account,
+            Err(reason) => {
+                result.skip(index, &issuer, &label, reason);
+                continue;
+            }
+        };
+
+        // Categories become tags; entries refer to authenticators by secret
+        let tags = backup
+            .authenticator_categories
+            .iter()
+            .filter(|link| link.authenticator_secret == auth.secret)
+            .filter_map(|link| backup.categories.iter().find(|c| c.id == link.category_id))
+            .map(|category| category.name.clone())
+            .collect();
+
+        result.add(
+            index,
+            Account {
+                issuer,
+                label,
+                tags,
+                ..account
+            },
+        );
     }
 
-    Ok(ImportResult {
-        format: "andOTP".to_string(),
-        accounts,
-        skipped,
-    })
+    Ok(result)
+}
+
+fn parse_authpro_encrypted(data: &[u8], password: Option<&str>) -> Result<ImportResult, String> {
+    if data.starts_with(AUTHPRO_LEGACY_HEADER) {
+        return Err(
+            "This Authenticator Pro backup uses an old encryption format. Back up again \
+                    with a current version of Authenticator Pro and import that file."
+                .to_string(),
+        );
+    }
+    let password = password.ok_or_else(|| {
+        "This Authenticator Pro backup is encrypted. Enter its password to import it.".to_string()
+    })?;
+    let body = &data[AUTHPRO_HEADER.len()..];
+    if body.len() < AUTHPRO_SALT_LEN + AUTHPRO_IV_LEN + 16 {
+        return Err("Invalid Authenticator Pro backup: file is truncated".to_string());
+    }
+    let (salt, rest) = body.split_at(AUTHPRO_SALT_LEN);
+    let (iv, sealed) = rest.split_at(AUTHPRO_IV_LEN);
+
+    let params = argon2::Params::new(
+        AUTHPRO_ARGON2_MEMORY_KIB,
+        AUTHPRO_ARGON2_ITERATIONS,
+        AUTHPRO_ARGON2_PARALLELISM,
+        Some(32),
+    )
+    .map_err(|e| {
+        tracing::error!(error = %e, "Argon2 parameter construction failed");
+        "Failed to derive key from password".to_string()
+    })?;
+    let mut key = Zeroizing::new([0u8; 32]);
+    argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params)
+        .hash_password_into(password.as_bytes(), salt, &mut *key)
+        .map_err(|e| {
+            tracing::error!(error = %e, "Argon2 key derivation failed");
+            "Failed to derive key from password".to_string()
+        })?;
+    let plaintext = aes_gcm_open(&*key, iv, sealed)
+        .map_err(|_| "Wrong password for this Authenticator Pro backup".to_string())?;
+    let text = std::str::from_utf8(&plaintext)
+        .map_err(|_| "Decrypted Authenticator Pro backup is not valid text".to_string())?;
+    parse_authpro(text)
 }
 
 // --- Google Authenticator migration ---
 
+/// Google Authenticator splits exports into about ten codes at most; larger
+/// batch sizes in untrusted data are rejected before anything is sized by them.
+const MIGRATION_MAX_BATCH_SIZE: usize = 100;
+
+/// One QR code of a Google Authenticator export. Large exports are split
+/// into `batch_size` codes that share a `batch_id`.
+pub struct MigrationBatch {
+    pub batch_id: i32,
+    pub batch_size: usize,
+    pub batch_index: usize,
+    pub result: ImportResult,
+}
+
+/// Where a multi-code Google Authenticator import stands. Batch indices
+/// start at 0, as in the migration payload.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct MigrationProgress {
+    pub batch_size: usize,
+    pub received: Vec<usize>,
+    pub missing: Vec<usize>,
+    pub account_count: usize,
+    pub complete: bool,
+}
+
+/// Collects the QR codes of one Google Authenticator export, in any order,
+/// until every batch is present.
+#[derive(Default)]
+pub struct MigrationSession {
+    batch_id: Option<i32>,
+    batch_size: usize,
+    batches: BTreeMap<usize, ImportResult>,
+}
+
+impl MigrationSession {
+    /// Add a scanned migration URI. Scanning a code twice is harmless.
+    pub fn add(&mut self, uri: &str) -> Result<MigrationProgress, String> {
+        let batch = parse_migration_batch(uri)?;
+        match self.batch_id {
+            Some(id) if id != batch.batch_id || self.batch_size != batch.batch_size => {
+                return Err(
+                    "This QR code belongs to a different Google Authenticator export".to_string(),
+                );
+            }
+            Some(_) => {}
+            None => {
+                self.batch_id = Some(batch.batch_id);
+                self.batch_size = batch.batch_size;
+            }
+        }
+        self.batches.insert(batch.batch_index, batch.result);
+        Ok(self.progress())
+    }
+
+    pub fn progress(&self) -> MigrationProgress {
+        let missing = self.missing();
+        MigrationProgress {
+            batch_size: self.batch_size,
+            received: self.batches.keys().copied().collect(),
+            complete: self.batch_id.is_some() && missing.is_empty(),
+            missing,
+            account_count: self.batches.values().map(|b| b.accounts.len()).sum(),
+        }
+    }
+
+    fn missing(&self) -> Vec<usize> {
+        (0..self.batch_size)
+            .filter(|i| !self.batches.contains_key(i))
+            .collect()
+    }
+
+    /// All accounts of the export, once every batch has been added.
+    pub fn result(&self) -> Result<ImportResult, String> {
+        if self.batch_id.is_none() {
+            return Err("No Google Authenticator QR codes scanned yet".to_string());
+        }
+        let missing = self.missing();
+        if !missing.is_empty() {
+            let numbers: Vec<String> = missing.iter().map(|i| (i + 1).to_string()).collect();
+            return Err(format!(
+                "Scan the remaining QR codes first: {} of {}",
+                numbers.join(", "),
+                self.batch_size
+            ));
+        }
+
+        let mut result = ImportResult::new("Google Authenticator");
+        for batch in self.batches.values() {
+            result.append(batch.clone());
+        }
+        Ok(result)
+    }
+
+    /// Like `result`, and starts a new session. On error the scanned batches
+    /// are kept.
+    pub fn finish(&mut self) -> Result<ImportResult, String> {
+        let result = self.result()?;
+        *self = Self::default();
+        Ok(result)
+    }
+}
+
+/// Import a complete multi-code Google Authenticator export.
+pub fn parse_migration_uris<'a>(
+    uris: impl IntoIterator<Item = &'a str>,
+) -> Result<ImportResult, String> {
+    let mut session = MigrationSession::default();
+    for uri in uris {
+        session.add(uri)?;
+    }
+    session.finish()
+}
+
 fn parse_google_auth_migration(uri: &str) -> Result<ImportResult, String> {
+    parse_migration_batch(uri).map(|batch| batch.result)
+}
+
+pub fn parse_migration_batch(uri: &str) -> Result<MigrationBatch, String> {
+    if !uri.trim().starts_with("otpauth-migration://") {
+        return Err("Not a Google Authenticator export QR code".to_string());
+    }
     // Extract the data parameter from the URI
     let data_start = uri
         .find("data=")
@@ -345,20 +1179,33 @@ fn parse_google_auth_migration(uri: &str) -> Result<ImportResult, String> {
     let payload = google_auth_proto::MigrationPayload::decode(bytes.as_slice())
         .map_err(|e| format!("Failed to decode protobuf migration data: {e}"))?;
 
-    let mut accounts = Vec::new();
-    let mut skipped = 0;
+    let mut result = ImportResult::new("Google Authenticator");
+
+    for (index, param) in payload.otp_parameters.into_iter().enumerate() {
+        // Parse issuer from name if needed (format: "issuer:label")
+        let (issuer, label) = if !param.issuer.is_empty() {
+            // If the name starts with "issuer:", strip that prefix for the label
+            let label = param
+                .name
+                .strip_prefix(&format!("{}:", param.issuer))
+                .unwrap_or(&param.name)
+                .trim()
+                .to_string();
+            (param.issuer, label)
+        } else {
+            split_issuer_label(&param.name)
+        };
 
-    for param in payload.otp_parameters {
         // Only import TOTP (type == 2)
         if param.otp_type != google_auth_proto::OtpType::Totp as i32 {
-            skipped += 1;
+            result.skip(index, &issuer, &label, SkipReason::UnsupportedType);
             continue;
         }
 
         // Encode raw secret bytes to Base32 no-pad
         let secret = data_encoding::BASE32_NOPAD.encode(&param.secret);
         if secret.is_empty() {
-            skipped += 1;
+            result.skip(index, &issuer, &label, SkipReason::InvalidSecret);
             continue;
         }
 
@@ -368,7 +1215,7 @@ fn parse_google_auth_migration(uri: &str) -> Result<ImportResult, String> {
             x if x == google_auth_proto::Algorithm::Sha512 as i32 => "SHA512",
             0 => "SHA1", // Unspecified defaults to SHA1
             _ => {
-                skipped += 1;
+                result.skip(index, &issuer, &label, SkipReason::UnsupportedAlgorithm);
                 continue;
             }
         }
@@ -379,84 +1226,529 @@ fn parse_google_auth_migration(uri: &str) -> Result<ImportResult, String> {
             x if x == google_auth_proto::DigitCount::Eight as i32 => 8,
             0 => 6, // Unspecified defaults to 6
             _ => {
-                skipped += 1;
+                result.skip(index, &issuer, &label, SkipReason::InvalidDigits);
                 continue;
             }
         };
 
-        // Parse issuer from name if needed (format: "issuer:label")
-        let (issuer, label) = if !param.issuer.is_empty() {
-            // If the name starts with "issuer:", strip that prefix for the label
-            let label = param
-                .name
-                .strip_prefix(&format!("{}:", param.issuer))
-                .unwrap_or(&param.name)
-                .trim()
-                .to_string();
-            (param.issuer, label)
-        } else {
-            split_issuer_label(&param.name)
-        };
+        result.add(
+            index,
+            Account {
+                id: uuid::Uuid::new_v4().to_string(),
+                issuer,
+                label,
+                secret,
+                algorithm,
+                digits,
+                period: 30,
+                icon: None,
+                last_modified: 0,
+                tags: Vec::new(),
+            },
+        );
+    }
 
-        accounts.push(Account {
-            id: uuid::Uuid::new_v4().to_string(),
-            issuer,
-            label,
-            secret,
-            algorithm,
-            digits,
-            period: 30,
-            icon: None,
-            last_modified: 0,
+    // Single-code exports leave the batch fields unset
+    let batch_size = usize::try_from(payload.batch_size).unwrap_or(0).max(1);
+    if batch_size > MIGRATION_MAX_BATCH_SIZE {
+        return Err("Invalid batch count in migration data".to_string());
+    }
+    let batch_index = usize::try_from(payload.batch_index)
+        .ok()
+        .filter(|&i| i < batch_size)
+        .ok_or_else(|| "Invalid batch number in migration data".to_string())?;
+
+    Ok(MigrationBatch {
+        batch_id: payload.batch_id,
+        batch_size,
+        batch_index,
+        result,
+    })
+}
+
+// --- otpauth:// URI list ---
+
+/// Entries are numbered by line.
+fn parse_otpauth_uri_list(text: &str) -> Result<ImportResult, String> {
+    let mut result = ImportResult::new("otpauth:// URI list");
+    let mut from_ente = false;
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || !line.starts_with("otpauth://") {
+            continue;
+        }
+
+        // Ente Auth's plain text export adds display settings to each URI
+        let (uri, display) = split_code_display(line);
+        from_ente |= display.is_some();
+        let display = display.unwrap_or_default();
+        if display.trashed {
+            let (issuer, label) = uri_identity(&uri);
+            result.skip(index, &issuer, &label, SkipReason::Trashed);
+            continue;
+        }
+
+        let parsed = parse_totp_uri(&uri).map(|account| Account {
+            tags: display.tags,
+            ..account
         });
+        let (issuer, label) = uri_identity(&uri);
+        result.record(index, &issuer, &label, parsed);
     }
 
-    Ok(ImportResult {
-        format: "Google Authenticator".to_string(),
-        accounts,
-        skipped,
-    })
+    if result.accounts.is_empty() && result.skipped.is_empty() {
+        return Err("No otpauth:// URIs found in file".to_string());
+    }
+
+    if from_ente {
+        result.format = "Ente Auth".to_string();
+    }
+    Ok(result)
+}
+
+// --- Password managers ---
+
+/// Upper bound on the unpacked size of a 1Password `export.data`.
+const ONEPUX_MAX_DATA_LEN: u64 = 64 * 1024 * 1024;
+
+#[derive(Deserialize)]
+struct BitwardenExport {
+    #[serde(default)]
+    encrypted: bool,
+    #[serde(default)]
+    items: Vec<BitwardenItem>,
+}
+
+#[derive(Deserialize)]
+struct BitwardenItem {
+    #[serde(default)]
+    name: String,
+    login: Option<BitwardenLogin>,
+}
+
+#[derive(Deserialize)]
+struct BitwardenLogin {
+    username: Option<String>,
+    totp: Option<String>,
+    uris: Option<Vec<BitwardenUri>>,
+}
+
+#[derive(Deserialize)]
+struct BitwardenUri {
+    uri: Option<String>,
+}
+
+fn parse_bitwarden(text: &str) -> Result<ImportResult, String> {
+    let export: BitwardenExport =
+        serde_json::from_str(text).map_err(|e| format!("Failed to parse Bitwarden JSON: {e}"))?;
+    if export.encrypted {
+        return Err(
+            "This Bitwarden export is encrypted. Export the vault as JSON (not encrypted) \
+             and import that file."
+                .to_string(),
+        );
+    }
+
+    let mut result = ImportResult::new("Bitwarden");
+
+    for (index, item) in export.items.into_iter().enumerate() {
+        let Some(login) = item.login else {
+            result.skip(index, &item.name, "", SkipReason::NoTotp);
+            continue;
+        };
+        let username = login.username.unwrap_or_default();
+        let Some(totp) = login.totp.filter(|t| !t.trim().is_empty()) else {
+            result.skip(index, &item.name, &username, SkipReason::NoTotp);
+            continue;
+        };
+        let url = login
+            .uris
+            .unwrap_or_default()
+            .into_iter()
+            .find_map(|u| u.uri.filter(|u| !u.trim().is_empty()));
+
+        let parsed = account_from_totp_field(&totp, &item.name, url.as_deref(), &username);
+        result.record(index, &item.name, &username, parsed);
+    }
+
+    Ok(result)
+}
+
+#[derive(Deserialize)]
+struct OnePuxExport {
+    #[serde(default)]
+    accounts: Vec<OnePuxAccount>,
+}
+
+#[derive(Deserialize)]
+struct OnePuxAccount {
+    #[serde(default)]
+    vaults: Vec<OnePuxVault>,
+}
+
+#[derive(Deserialize)]
+struct OnePuxVault {
+    #[serde(default)]
+    items: Vec<OnePuxItem>,
+}
+
+#[derive(Deserialize)]
+struct OnePuxItem {
+    #[serde(default)]
+    overview: OnePuxOverview,
+    #[serde(default)]
+    details: OnePuxDetails,
+}
+
+#[derive(Deserialize, Default)]
+struct OnePuxOverview {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    subtitle: String,
+    url: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct OnePuxDetails {
+    #[serde(default, rename = "loginFields")]
+    login_fields: Vec<OnePuxLoginField>,
+    #[serde(default)]
+    sections: Vec<OnePuxSection>,
+}
+
+#[derive(Deserialize)]
+struct OnePuxLoginField {
+    designation: Option<String>,
+    #[serde(default)]
+    value: String,
+}
+
+#[derive(Deserialize)]
+struct OnePuxSection {
+    #[serde(default)]
+    fields: Vec<OnePuxField>,
+}
+
+#[derive(Deserialize)]
+struct OnePuxField {
+    /// A single-key object naming the field type, e.g. `{"totp": "otpauth://..."}`
+    #[serde(default)]
+    value: serde_json::Value,
+}
+
+fn parse_onepux(data: &[u8]) -> Result<ImportResult, String> {
+    use std::io::Read;
+
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(data))
+        .map_err(|e| format!("Invalid ZIP archive: {e}"))?;
+    let file = archive
+        .by_name("export.data")
+        .map_err(|_| "Unrecognized ZIP archive. Expected a 1Password .1pux export.".to_string())?;
+    let mut text = String::new();
+    file.take(ONEPUX_MAX_DATA_LEN + 1)
+        .read_to_string(&mut text)
+        .map_err(|e| format!("Failed to read 1Password export: {e}"))?;
+    if text.len() as u64 > ONEPUX_MAX_DATA_LEN {
+        return Err("1Password export is too large".to_string());
+    }
+
+    let export: OnePuxExport = serde_json::from_str(&text)
+        .map_err(|e| format!("Failed to parse 1Password export: {e}"))?;
+
+    let mut result = ImportResult::new("1Password");
+
+    let items = export
+        .accounts
+        .into_iter()
+        .flat_map(|a| a.vaults)
+        .flat_map(|v| v.items);
+    for (index, item) in items.enumerate() {
+        let username = item
+            .details
+            .login_fields
+            .iter()
+            .find(|f| f.designation.as_deref() == Some("username"))
+            .map(|f| f.value.as_str())
+            .unwrap_or(&item.overview.subtitle);
+        let totps: Vec<&str> = item
+            .details
+            .sections
+            .iter()
+            .flat_map(|s| &s.fields)
+            .filter_map(|f| f.value.get("totp").and_then(|v| v.as_str()))
+            .filter(|t| !t.trim().is_empty())
+            .collect();
+        let title = &item.overview.title;
+        if totps.is_empty() {
+            result.skip(index, title, username, SkipReason::NoTotp);
+            continue;
+        }
+
+        for totp in totps {
+            let parsed =
+                account_from_totp_field(totp, title, item.overview.url.as_deref(), username);
+            result.record(index, title, username, parsed);
+        }
+    }
+
+    Ok(result)
+}
+
+#[derive(Deserialize)]
+struct ProtonPassExport {
+    #[serde(default)]
+    encrypted: bool,
+    // Keyed by share id; sorted so imports come out in a stable order
+    #[serde(default)]
+    vaults: std::collections::BTreeMap<String, ProtonPassVault>,
+}
+
+#[derive(Deserialize)]
+struct ProtonPassVault {
+    #[serde(default)]
+    items: Vec<ProtonPassItem>,
+}
+
+#[derive(Deserialize)]
+struct ProtonPassItem {
+    data: ProtonPassItemData,
+}
+
+#[derive(Deserialize)]
+struct ProtonPassItemData {
+    #[serde(default)]
+    metadata: ProtonPassMetadata,
+    #[serde(default)]
+    content: ProtonPassContent,
+    #[serde(default, rename = "extraFields")]
+    extra_fields: Vec<ProtonPassExtraField>,
+}
+
+#[derive(Deserialize, Default)]
+struct ProtonPassMetadata {
+    #[serde(default)]
+    name: String,
+}
+
+#[derive(Deserialize, Default)]
+struct ProtonPassContent {
+    #[serde(rename = "totpUri")]
+    totp_uri: Option<String>,
+    #[serde(rename = "itemUsername")]
+    item_username: Option<String>,
+    #[serde(rename = "itemEmail")]
+    item_email: Option<String>,
+    /// Older exports only have `username`
+    username: Option<String>,
+    #[serde(default)]
+    urls: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct ProtonPassExtraField {
+    #[serde(rename = "type")]
+    field_type: String,
+    #[serde(default)]
+    data: serde_json::Value,
 }
 
-// --- otpauth:// URI list ---
+fn parse_proton_pass(text: &str) -> Result<ImportResult, String> {
+    let export: ProtonPassExport =
+        serde_json::from_str(text).map_err(|e| format!("Failed to parse Proton Pass JSON: {e}"))?;
+    if export.encrypted {
+        return Err(
+            "This Proton Pass export is PGP-encrypted. Export it again without encryption \
+             and import that file."
+                .to_string(),
+        );
+    }
 
-fn parse_otpauth_uri_list(text: &str) -> Result<ImportResult, String> {
-    let mut accounts = Vec::new();
-    let mut skipped = 0;
+    let mut result = ImportResult::new("Proton Pass");
 
-    for line in text.lines() {
-        let line = line.trim();
-        if line.is_empty() || line.starts_with('#') {
-            continue;
-        }
-        if !line.starts_with("otpauth://totp/") {
-            if line.starts_with("otpauth://") {
-                // Non-TOTP otpauth URI (e.g., hotp)
-                skipped += 1;
-            }
+    let items = export.vaults.into_values().flat_map(|v| v.items);
+    for (index, item) in items.enumerate() {
+        let data = item.data;
+        let content = data.content;
+        let username = [content.item_username, content.item_email, content.username]
+            .into_iter()
+            .flatten()
+            .find(|u| !u.trim().is_empty())
+            .unwrap_or_default();
+        let url = content.urls.into_iter().find(|u| !u.trim().is_empty());
+
+        let extra_totps = data
+            .extra_fields
+            .into_iter()
+            .filter(|f| f.field_type == "totp")
+            .filter_map(|f| f.data.get("totpUri")?.as_str().map(str::to_string));
+        let totps: Vec<String> = content
+            .totp_uri
+            .into_iter()
+            .chain(extra_totps)
+            .filter(|t| !t.trim().is_empty())
+            .collect();
+        let name = &data.metadata.name;
+        if totps.is_empty() {
+            result.skip(index, name, &username, SkipReason::NoTotp);
             continue;
         }
 
-        match crate::totp::parse_otpauth_uri(line) {
-            Ok(account) => accounts.push(account),
-            Err(e) => {
-                tracing::warn!(error = %e, line = %line, "Skipping invalid otpauth URI");
-                skipped += 1;
-            }
+        for totp in totps {
+            let parsed = account_from_totp_field(&totp, name, url.as_deref(), &username);
+            result.record(index, name, &username, parsed);
         }
     }
 
-    if accounts.is_empty() && skipped == 0 {
-        return Err("No otpauth:// URIs found in file".to_string());
+    Ok(result)
+}
+
+/// Build an account from a password manager's TOTP field, which holds either
+/// an otpauth:// URI or a bare Base32 secret.
+fn account_from_totp_field(
+    totp: &str,
+    item_name: &str,
+    url: Option<&str>,
+    username: &str,
+) -> Result<Account, SkipReason> {
+    let mut account = parse_totp_field(totp)?;
+    fill_item_identity(&mut account, item_name, url, username);
+    Ok(account)
+}
+
+/// Parse a TOTP field holding an otpauth:// URI or a bare Base32 secret.
+/// Anything else (HOTP URIs, Steam's steam:// codes) is not imported.
+pub(crate) fn parse_totp_field(totp: &str) -> Result<Account, SkipReason> {
+    let totp = totp.trim();
+    if totp.starts_with("otpauth://") {
+        return parse_totp_uri(totp);
+    }
+    if totp.contains("://") {
+        return Err(SkipReason::UnsupportedType);
+    }
+    totp_account(totp, "SHA1", 6, 30)
+}
+
+/// Parse an otpauth:// URI from an export, saying why it can't be imported.
+pub(crate) fn parse_totp_uri(uri: &str) -> Result<Account, SkipReason> {
+    if !uri.starts_with("otpauth://totp/") {
+        // Non-TOTP otpauth URI (e.g., hotp)
+        return Err(SkipReason::UnsupportedType);
+    }
+    let account = crate::totp::parse_otpauth_uri(uri).map_err(|e| {
+        tracing::warn!(error = %e, "Skipping invalid otpauth URI");
+        uri_skip_reason(uri)
+    })?;
+    check_params(&account.algorithm, account.digits, account.period)?;
+    Ok(account)
+}
+
+/// Why an otpauth URI was rejected, going by its parameters.
+fn uri_skip_reason(uri: &str) -> SkipReason {
+    let secret = uri_param(uri, "secret").unwrap_or_default();
+    if crate::totp::decode_secret(&secret).is_none() {
+        return SkipReason::InvalidSecret;
+    }
+    let algorithm = uri_param(uri, "algorithm");
+    if algorithm.is_some_and(|a| !matches!(a.as_str(), "SHA1" | "SHA256" | "SHA512")) {
+        return SkipReason::UnsupportedAlgorithm;
+    }
+    if uri_param(uri, "digits").is_some_and(|d| d.parse::<u32>().is_err()) {
+        return SkipReason::InvalidDigits;
+    }
+    if uri_param(uri, "period").is_some_and(|p| p.parse::<u32>().is_err()) {
+        return SkipReason::InvalidPeriod;
+    }
+    SkipReason::Unreadable
+}
+
+/// The percent-decoded value of a query parameter of `uri`.
+fn uri_param(uri: &str, name: &str) -> Option<String> {
+    let (_, query) = uri.split_once('?')?;
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .and_then(|(_, value)| percent_decode(value).ok())
+}
+
+/// Issuer and label of an otpauth URI, read leniently for the import report.
+pub(crate) fn uri_identity(uri: &str) -> (String, String) {
+    let path = uri.split_once("://").map_or(uri, |(_, rest)| rest);
+    let path = path.split('?').next().unwrap_or_default();
+    let name = path.split_once('/').map_or("", |(_, name)| name);
+    let name = percent_decode(name).unwrap_or_else(|_| name.to_string());
+    let (issuer, label) = match name.split_once(':') {
+        Some(_) => split_issuer_label(&name),
+        None => (String::new(), name.trim().to_string()),
+    };
+    match uri_param(uri, "issuer").filter(|issuer| !issuer.is_empty()) {
+        Some(param) => (param, label),
+        None => (issuer, label),
     }
+}
 
-    Ok(ImportResult {
-        format: "otpauth:// URI list".to_string(),
-        accounts,
-        skipped,
+/// An account with no issuer or label yet, if the parameters are valid.
+pub(crate) fn totp_account(
+    secret: &str,
+    algorithm: &str,
+    digits: u32,
+    period: u32,
+) -> Result<Account, SkipReason> {
+    let secret = normalize_secret(secret);
+    let algorithm = normalize_algorithm(algorithm);
+    if crate::totp::decode_secret(&secret).is_none() {
+        return Err(SkipReason::InvalidSecret);
+    }
+    check_params(&algorithm, digits, period)?;
+    Ok(Account {
+        id: uuid::Uuid::new_v4().to_string(),
+        issuer: String::new(),
+        label: String::new(),
+        secret,
+        algorithm,
+        digits,
+        period,
+        icon: None,
+        last_modified: 0,
+        tags: Vec::new(),
     })
 }
 
+/// Name an account after the password manager item holding it: the item
+/// name (or its website) fills in a missing issuer, and the item's username
+/// becomes the label.
+pub(crate) fn fill_item_identity(
+    account: &mut Account,
+    item_name: &str,
+    url: Option<&str>,
+    username: &str,
+) {
+    if account.issuer.is_empty() {
+        let name = item_name.trim();
+        account.issuer = if name.is_empty() {
+            url.and_then(url_host).unwrap_or_default()
+        } else {
+            name.to_string()
+        };
+    }
+    let username = username.trim();
+    if !username.is_empty() {
+        account.label = username.to_string();
+    }
+}
+
+/// Host part of a website URL, without a leading "www.".
+fn url_host(url: &str) -> Option<String> {
+    let url = url.trim();
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?.split(':').next()?;
+    let host = host.strip_prefix("www.").unwrap_or(host);
+    (!host.is_empty()).then(|| host.to_lowercase())
+}
+
 // --- Helpers ---
 
 /// Normalize a Base32 secret: remove spaces, uppercase, strip padding.
@@ -479,10 +1771,17 @@ fn normalize_algorithm(algo: &str) -> String {
 }
 
 /// Validate that account fields are within acceptable ranges.
-fn is_valid_account(algorithm: &str, digits: u32, period: u32) -> bool {
-    matches!(algorithm, "SHA1" | "SHA256" | "SHA512")
-        && (digits == 6 || digits == 8)
-        && (15..=120).contains(&period)
+fn check_params(algorithm: &str, digits: u32, period: u32) -> Result<(), SkipReason> {
+    if !matches!(algorithm, "SHA1" | "SHA256" | "SHA512") {
+        return Err(SkipReason::UnsupportedAlgorithm);
+    }
+    if digits != 6 && digits != 8 {
+        return Err(SkipReason::InvalidDigits);
+    }
+    if !(15..=120).contains(&period) {
+        return Err(SkipReason::InvalidPeriod);
+    }
+    Ok(())
 }
 
 /// Split "issuer:label" format into (issuer, label).
@@ -494,8 +1793,26 @@ fn split_issuer_label(combined: &str) -> (String, String) {
     }
 }
 
+/// AES-256-GCM decryption of `sealed` (ciphertext followed by the tag).
+fn aes_gcm_open(key: &[u8], nonce: &[u8], sealed: &[u8]) -> Result<Zeroizing<Vec<u8>>, String> {
+    if nonce.len() != 12 {
+        return Err("Invalid encryption parameters".to_string());
+    }
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| "Invalid key".to_string())?;
+    cipher
+        .decrypt(Nonce::from_slice(nonce), sealed)
+        .map(Zeroizing::new)
+        .map_err(|_| "Decryption failed".to_string())
+}
+
+fn decode_hex(input: &str) -> Result<Vec<u8>, String> {
+    data_encoding::HEXLOWER_PERMISSIVE
+        .decode(input.trim().as_bytes())
+        .map_err(|_| "Invalid hex data in backup".to_string())
+}
+
 /// Simple percent-decoding for URL query parameters.
-fn percent_decode(input: &str) -> Result<String, String> {
+pub(crate) fn percent_decode(input: &str) -> Result<String, String> {
     let mut result = Vec::new();
     let bytes = input.as_bytes();
     let mut i = 0;
@@ -555,12 +1872,65 @@ mod tests {
     }
 
     #[test]
-    fn test_is_valid_account() {
-        assert!(is_valid_account("SHA1", 6, 30));
-        assert!(is_valid_account("SHA256", 8, 60));
-        assert!(!is_valid_account("MD5", 6, 30));
-        assert!(!is_valid_account("SHA1", 7, 30));
-        assert!(!is_valid_account("SHA1", 6, 10));
+    fn test_check_params() {
+        assert_eq!(check_params("SHA1", 6, 30), Ok(()));
+        assert_eq!(check_params("SHA256", 8, 60), Ok(()));
+        assert_eq!(
+            check_params("MD5", 6, 30),
+            Err(SkipReason::UnsupportedAlgorithm)
+        );
+        assert_eq!(check_params("SHA1", 7, 30), Err(SkipReason::InvalidDigits));
+        assert_eq!(check_params("SHA1", 6, 10), Err(SkipReason::InvalidPeriod));
+    }
+
+    #[test]
+    fn test_uri_list_skip_reasons() {
+        let text = "# exported accounts\n\
+            otpauth://totp/GitHub:octocat?secret=JBSWY3DPEHPK3PXP&issuer=GitHub\n\
+            otpauth://hotp/Counter:me?secret=JBSWY3DPEHPK3PXP&counter=1\n\
+            \n\
+            otpauth://totp/Bad%20Secret:me?secret=not-base32!\n\
+            otpauth://totp/Slow:me?secret=JBSWY3DPEHPK3PXP&period=5\n\
+            otpauth://totp/Long:me?secret=JBSWY3DPEHPK3PXP&digits=7\n\
+            otpauth://totp/Weird:me?secret=JBSWY3DPEHPK3PXP&algorithm=MD5\n";
+        let result = parse_otpauth_uri_list(text).unwrap();
+        assert_eq!(result.sources, vec![1]);
+        let report: Vec<(usize, &str, &str, SkipReason)> = result
+            .skipped
+            .iter()
+            .map(|s| (s.index, s.issuer.as_str(), s.label.as_str(), s.reason))
+            .collect();
+        assert_eq!(
+            report,
+            vec![
+                (2, "Counter", "me", SkipReason::UnsupportedType),
+                (4, "Bad Secret", "me", SkipReason::InvalidSecret),
+                (5, "Slow", "me", SkipReason::InvalidPeriod),
+                (6, "Long", "me", SkipReason::InvalidDigits),
+                (7, "Weird", "me", SkipReason::UnsupportedAlgorithm),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_totp_field_skip_reasons() {
+        assert!(parse_totp_field("JBSW Y3DP EHPK 3PXP").is_ok());
+        assert_eq!(
+            parse_totp_field("steam://ABCDEFGH").unwrap_err(),
+            SkipReason::UnsupportedType
+        );
+        assert_eq!(
+            parse_totp_field("hunter2!").unwrap_err(),
+            SkipReason::InvalidSecret
+        );
+        assert_eq!(
+            uri_identity("otpauth://totp/ACME%20Co:jane?secret=X&issuer=ACME"),
+            ("ACME".to_string(), "jane".to_string())
+        );
+        assert_eq!(
+            uri_identity("otpauth://totp/jane@example.com?secret=X"),
+            (String::new(), "jane@example.com".to_string())
+        );
     }
 
     #[test]
@@ -608,10 +1978,18 @@ mod tests {
             }
         }"#;
 
-        let result = parse_aegis(json).unwrap();
+        let result = parse_aegis(json, None).unwrap();
         assert_eq!(result.format, "Aegis");
         assert_eq!(result.accounts.len(), 2);
-        assert_eq!(result.skipped, 1);
+        assert_eq!(
+            result.skipped,
+            vec![SkippedEntry {
+                index: 2,
+                issuer: "Other".to_string(),
+                label: "counter-based".to_string(),
+                reason: SkipReason::UnsupportedType,
+            }]
+        );
         assert_eq!(result.accounts[0].issuer, "GitHub");
         assert_eq!(result.accounts[0].label, "user@example.com");
         assert_eq!(result.accounts[0].algorithm, "SHA1");
@@ -622,13 +2000,196 @@ mod tests {
     }
 
     #[test]
-    fn test_parse_aegis_encrypted_rejected() {
+    fn test_parse_aegis_encrypted_needs_password() {
         let json = r#"{"version":1,"header":{"slots":[],"params":{}},"db":"base64ciphertext"}"#;
-        let result = parse_aegis(json);
+        let result = parse_aegis(json, None);
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("encrypted"));
     }
 
+    /// Build an encrypted Aegis vault the way Aegis does: a random master key
+    /// wrapped by an scrypt-derived password slot key (AES-256-GCM, detached
+    /// tag, hex-encoded), and the database encrypted with the master key and
+    /// base64-encoded. The scrypt cost is lowered to keep the test fast.
+    fn encrypted_aegis(db: &str, password: &str) -> String {
+        use aes_gcm::aead::OsRng;
+        use aes_gcm::AeadCore;
+
+        let seal = |key: &[u8], plaintext: &[u8]| {
+            let cipher = Aes256Gcm::new_from_slice(key).unwrap();
+            let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+            let mut sealed = cipher.encrypt(&nonce, plaintext).unwrap();
+            let tag = sealed.split_off(sealed.len() - 16);
+            let params = serde_json::json!({
+                "nonce": data_encoding::HEXLOWER.encode(&nonce),
+                "tag": data_encoding::HEXLOWER.encode(&tag),
+            });
+            (sealed, params)
+        };
+
+        let master_key = Aes256Gcm::generate_key(&mut OsRng);
+        let salt = [7u8; 32];
+        let mut slot_key = [0u8; 32];
+        scrypt::scrypt(
+            password.as_bytes(),
+            &salt,
+            &scrypt::Params::new(10, 8, 1, 32).unwrap(),
+            &mut slot_key,
+        )
+        .unwrap();
+        let (wrapped_key, key_params) = seal(&slot_key, &master_key);
+        let (db_ciphertext, db_params) = seal(&master_key, db.as_bytes());
+
+        serde_json::json!({
+            "version": 1,
+            "header": {
+                "slots": [
+                    {
+                        "type": 2,
+                        "uuid": "a8325752-c1be-458a-9b3e-5e0a8154d9ec",
+                        "key": "00",
+                        "key_params": {"nonce": "00", "tag": "00"}
+                    },
+                    {
+                        "type": 1,
+                        "uuid": "01ee8a56-5e6b-4ae4-b2e6-3f2f5a1f0b36",
+                        "key": data_encoding::HEXLOWER.encode(&wrapped_key),
+                        "key_params": key_params,
+                        "n": 1024,
+                        "r": 8,
+                        "p": 1,
+                        "salt": data_encoding::HEXLOWER.encode(&salt),
+                        "repaired": true,
+                        "is_backup": false
+                    }
+                ],
+                "params": db_params
+            },
+            "db": base64::Engine::encode(&base64::engine::general_purpose::STANDARD, db_ciphertext)
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_parse_aegis_encrypted() {
+        let db = r#"{
+            "version": 2,
+            "entries": [
+                {
+                    "type": "totp",
+                    "uuid": "3ae6f1ad-2e65-4ed2-a953-1ec0dff2386d",
+                    "name": "user@example.com",
+                    "issuer": "GitHub",
+                    "icon": null,
+                    "info": {"secret": "JBSWY3DPEHPK3PXP", "algo": "SHA1", "digits": 6, "period": 30}
+                },
+                {
+                    "type": "steam",
+                    "name": "gamer",
+                    "issuer": "Steam",
+                    "info": {"secret": "JBSWY3DPEHPK3PXP", "algo": "SHA1", "digits": 5, "period": 30}
+                }
+            ],
+            "groups": []
+        }"#;
+        let json = encrypted_aegis(db, "test");
+
+        let result = parse_import(json.as_bytes(), Some("test")).unwrap();
+        assert_eq!(result.format, "Aegis");
+        assert_eq!(result.accounts.len(), 1);
+        assert_eq!(result.skipped.len(), 1);
+        assert_eq!(result.accounts[0].issuer, "GitHub");
+        assert_eq!(result.accounts[0].secret, "JBSWY3DPEHPK3PXP");
+
+        let err = parse_import(json.as_bytes(), Some("wrong")).unwrap_err();
+        assert_eq!(err, "Wrong password for this Aegis backup");
+        let err = parse_import(json.as_bytes(), None).unwrap_err();
+        assert!(err.contains("Enter its password"));
+    }
+
+    #[test]
+    fn test_parse_aegis_encrypted_rejects_costly_scrypt() {
+        let vault = encrypted_aegis(r#"{"version":2,"entries":[]}"#, "test");
+        // 2^20 · 16 · 128 bytes is 2 GiB; 2^17 · 16 is too much work
+        for (from, to) in [
+            (r#""n":1024"#, r#""n":4194304"#),
+            (r#""n":1024,"p":1,"r":8"#, r#""n":1048576,"p":1,"r":16"#),
+            (r#""n":1024,"p":1"#, r#""n":131072,"p":16"#),
+        ] {
+            assert!(vault.contains(from), "{from}");
+            let json = vault.replace(from, to);
+            let err = parse_import(json.as_bytes(), Some("test")).unwrap_err();
+            assert!(err.contains("unsupported key derivation"), "{to}: {err}");
+        }
+    }
+
+    #[test]
+    fn test_parse_aegis_encrypted_tries_few_password_slots() {
+        let vault: serde_json::Value =
+            serde_json::from_str(&encrypted_aegis(r#"{"version":2,"entries":[]}"#, "test"))
+                .unwrap();
+        let with_decoys = |count: usize| {
+            let mut vault = vault.clone();
+            let slots = vault["header"]["slots"].as_array_mut().unwrap();
+            let mut decoy = slots[1].clone();
+            decoy["salt"] = serde_json::json!("00".repeat(32));
+            for _ in 0..count {
+                slots.insert(0, decoy.clone());
+            }
+            vault.to_string()
+        };
+
+        let json = with_decoys(AEGIS_MAX_PASSWORD_SLOTS - 1);
+        assert!(parse_import(json.as_bytes(), Some("test")).is_ok());
+        let json = with_decoys(AEGIS_MAX_PASSWORD_SLOTS);
+        let err = parse_import(json.as_bytes(), Some("test")).unwrap_err();
+        assert_eq!(err, "Wrong password for this Aegis backup");
+    }
+
+    /// An encrypted vault in Aegis's documented format with Aegis's own scrypt
+    /// parameters and a biometric slot, produced independently of this parser.
+    #[test]
+    fn test_parse_aegis_encrypted_fixture() {
+        let json = include_str!("../tests/fixtures/aegis_encrypted.json");
+
+        let result = parse_import(json.as_bytes(), Some("test")).unwrap();
+        assert_eq!(result.format, "Aegis");
+        let summary: Vec<(&str, &str, &str, u32, u32)> = result
+            .accounts
+            .iter()
+            .map(|a| {
+                (
+                    a.issuer.as_str(),
+                    a.label.as_str(),
+                    a.algorithm.as_str(),
+                    a.digits,
+                    a.period,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("Deno", "Mason", "SHA1", 6, 30),
+                ("Airbnb", "Elijah", "SHA512", 8, 50),
+            ]
+        );
+        assert_eq!(result.accounts[0].secret, "4SJHB4GSD43FZBAI7C2HLRJGPQ");
+        let skipped: Vec<(usize, SkipReason)> =
+            result.skipped.iter().map(|s| (s.index, s.reason)).collect();
+        assert_eq!(
+            skipped,
+            vec![
+                (1, SkipReason::InvalidDigits),
+                (3, SkipReason::UnsupportedType),
+                (4, SkipReason::UnsupportedType),
+            ]
+        );
+
+        let err = parse_import(json.as_bytes(), Some("wrong")).unwrap_err();
+        assert_eq!(err, "Wrong password for this Aegis backup");
+    }
+
     #[test]
     fn test_parse_twofas() {
         let json = r#"{
@@ -656,14 +2217,99 @@ mod tests {
             ]
         }"#;
 
-        let result = parse_twofas(json).unwrap();
+        let result = parse_twofas(json, None).unwrap();
         assert_eq!(result.format, "2FAS");
         assert_eq!(result.accounts.len(), 1);
-        assert_eq!(result.skipped, 1);
+        assert_eq!(result.skipped.len(), 1);
         assert_eq!(result.accounts[0].issuer, "GitLab");
         assert_eq!(result.accounts[0].label, "user@gitlab.com");
     }
 
+    fn seal_for_test(key: &[u8], nonce: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        Aes256Gcm::new_from_slice(key)
+            .unwrap()
+            .encrypt(Nonce::from_slice(nonce), plaintext)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_twofas_encrypted() {
+        let services = r#"[{
+            "name": "GitLab",
+            "secret": "JBSWY3DPEHPK3PXP",
+            "otp": {
+                "account": "user@gitlab.com",
+                "issuer": "GitLab",
+                "tokenType": "TOTP",
+                "digits": 6
+            }
+        }]"#;
+        let (salt, iv) = ([3u8; 256], [4u8; 12]);
+        let mut key = [0u8; 32];
+        pbkdf2::pbkdf2_hmac::<sha2::Sha256>(b"hunter22", &salt, TWOFAS_PBKDF2_ITERATIONS, &mut key);
+        let b64 = |bytes: &[u8]| {
+            base64::Engine::encode(&base64::engine::general_purpose::STANDARD, bytes)
+        };
+        let encrypted = format!(
+            "{}:{}:{}",
+            b64(&seal_for_test(&key, &iv, services.as_bytes())),
+            b64(&salt),
+            b64(&iv)
+        );
+        let json = serde_json::json!({
+            "services": [],
+            "groups": [],
+            "updatedAt": 1700000000000u64,
+            "schemaVersion": 4,
+            "appVersionCode": 5000029,
+            "appVersionName": "5.3.3",
+            "servicesEncrypted": encrypted,
+        })
+        .to_string();
+
+        let result = parse_import(json.as_bytes(), Some("hunter22")).unwrap();
+        assert_eq!(result.format, "2FAS");
+        assert_eq!(result.accounts.len(), 1);
+        assert_eq!(result.accounts[0].issuer, "GitLab");
+        assert_eq!(result.accounts[0].secret, "JBSWY3DPEHPK3PXP");
+
+        let err = parse_import(json.as_bytes(), Some("wrong")).unwrap_err();
+        assert_eq!(err, "Wrong password for this 2FAS backup");
+        let err = parse_import(json.as_bytes(), None).unwrap_err();
+        assert!(err.contains("2FAS backup is encrypted"));
+    }
+
+    #[test]
+    fn test_parse_andotp_encrypted() {
+        let entries = r#"[{
+            "secret": "JBSWY3DPEHPK3PXP",
+            "issuer": "TestService",
+            "label": "testuser",
+            "type": "TOTP"
+        }]"#;
+        let (iterations, salt, iv) = (1000u32, [5u8; ANDOTP_SALT_LEN], [6u8; ANDOTP_IV_LEN]);
+        let mut key = [0u8; 32];
+        pbkdf2::pbkdf2_hmac::<sha1::Sha1>(b"hunter22", &salt, iterations, &mut key);
+        let mut data = iterations.to_be_bytes().to_vec();
+        data.extend_from_slice(&salt);
+        data.extend_from_slice(&iv);
+        data.extend(seal_for_test(&key, &iv, entries.as_bytes()));
+
+        let result = parse_import(&data, Some("hunter22")).unwrap();
+        assert_eq!(result.format, "andOTP");
+        assert_eq!(result.accounts.len(), 1);
+        assert_eq!(result.accounts[0].issuer, "TestService");
+
+        let err = parse_import(&data, Some("wrong")).unwrap_err();
+        assert_eq!(err, "Wrong password for this andOTP backup");
+        let err = parse_import(&data, None).unwrap_err();
+        assert!(err.contains("encrypted andOTP backup"));
+
+        // Binary data without a plausible header is still rejected
+        let err = parse_import(&[0xFF; 64], Some("hunter22")).unwrap_err();
+        assert_eq!(err, "File is not valid UTF-8 text");
+    }
+
     #[test]
     fn test_parse_andotp() {
         let json = r#"[
@@ -696,7 +2342,7 @@ mod tests {
         let result = parse_andotp(json).unwrap();
         assert_eq!(result.format, "andOTP");
         assert_eq!(result.accounts.len(), 2);
-        assert_eq!(result.skipped, 1);
+        assert_eq!(result.skipped.len(), 1);
         assert_eq!(result.accounts[0].issuer, "TestService");
         assert_eq!(result.accounts[0].label, "testuser");
         // Second entry: issuer parsed from "label" field
@@ -715,11 +2361,191 @@ mod tests {
         let result = parse_otpauth_uri_list(text).unwrap();
         assert_eq!(result.format, "otpauth:// URI list");
         assert_eq!(result.accounts.len(), 2);
-        assert_eq!(result.skipped, 1);
+        assert_eq!(result.skipped.len(), 1);
         assert_eq!(result.accounts[0].issuer, "GitHub");
         assert_eq!(result.accounts[1].issuer, "Google");
     }
 
+    #[test]
+    fn test_parse_freeotp_plus() {
+        let json = r#"{
+            "tokenOrder": ["GitHub:octocat", "Bank:me"],
+            "tokens": [
+                {
+                    "algo": "SHA256",
+                    "counter": 0,
+                    "digits": 8,
+                    "issuerExt": "GitHub",
+                    "issuerInt": "github.com",
+                    "label": "octocat",
+                    "period": 60,
+                    "secret": [-1, 0, 127, -128, 1, 42, -42, 99, 100, -7],
+                    "type": "TOTP"
+                },
+                {
+                    "algo": "SHA1",
+                    "counter": 3,
+                    "digits": 6,
+                    "issuerExt": "Bank",
+                    "label": "me",
+                    "period": 30,
+                    "secret": [1, 2, 3, 4, 5],
+                    "type": "HOTP"
+                }
+            ]
+        }"#;
+
+        let result = parse_import(json.as_bytes(), None).unwrap();
+        assert_eq!(result.format, "FreeOTP+");
+        assert_eq!(result.accounts.len(), 1);
+        assert_eq!(result.skipped.len(), 1);
+        let account = &result.accounts[0];
+        assert_eq!(account.issuer, "GitHub");
+        assert_eq!(account.label, "octocat");
+        let bytes = [255u8, 0, 127, 128, 1, 42, 214, 99, 100, 249];
+        assert_eq!(account.secret, data_encoding::BASE32_NOPAD.encode(&bytes));
+        assert_eq!(
+            (account.algorithm.as_str(), account.digits, account.period),
+            ("SHA256", 8, 60)
+        );
+    }
+
+    #[test]
+    fn test_parse_ente_plain_text() {
+        let text = "otpauth://totp/GitHub:octocat?secret=JBSWY3DPEHPK3PXP&issuer=GitHub&algorithm=sha1&digits=6&period=30&codeDisplay=%7B%22pinned%22%3Atrue%2C%22trashed%22%3Afalse%2C%22lastUsedAt%22%3A0%2C%22tapCount%22%3A3%2C%22tags%22%3A%5B%22work%22%2C%22dev%22%5D%2C%22note%22%3A%22%22%2C%22position%22%3A0%7D\n\
+                    otpauth://totp/Old:me?secret=GEZDGNBVGY3TQOJQ&issuer=Old&algorithm=sha256&digits=6&period=30&codeDisplay=%7B%22trashed%22%3Atrue%7D\n\
+                    otpauth://steam/Steam:gamer?secret=GEZDGNBVGY3TQOJQ&issuer=Steam&algorithm=sha1&digits=5&period=30&codeDisplay=%7B%7D\n\
+                    otpauth://totp/Bank:me?secret=GEZDGNBVGY3TQOJQ&issuer=Bank&algorithm=sha512&digits=8&period=30&codeDisplay=%7B%7D\n";
+
+        let result = parse_import(text.as_bytes(), None).unwrap();
+        assert_eq!(result.format, "Ente Auth");
+        assert_eq!(result.accounts.len(), 2);
+        // The trashed entry and the Steam entry
+        assert_eq!(result.skipped.len(), 2);
+        assert_eq!(result.accounts[0].issuer, "GitHub");
+        assert_eq!(result.accounts[0].tags, vec!["work", "dev"]);
+        assert_eq!(result.accounts[1].issuer, "Bank");
+        assert_eq!(result.accounts[1].algorithm, "SHA512");
+        assert!(result.accounts[1].tags.is_empty());
+    }
+
+    /// Produced by libsodium: crypto_pwhash (Argon2id, 64 KiB, 2 passes) and a
+    /// single crypto_secretstream_xchacha20poly1305 message tagged FINAL.
+    const ENTE_ENCRYPTED_EXPORT: &str = r#"{"version": 1, "kdfParams": {"memLimit": 65536, "opsLimit": 2, "salt": "AAECAwQFBgcICQoLDA0ODw=="}, "encryptedData": "TsJoHBlcvBobHa0PICWF09fAQk0rIMOSR4p9BbrRVXb6fc4+8MYTSaC4hZLTtTLg2J9naApR24jKpiU3UG7t4hJYIzM4Fn/VKJf9oIvZOGaCYiLv+S/eKKyeyggbuWCmyl0m/dWYwfw0IcqcLeZ9fDIedIdnNIe84Wz14OMghkRpda5v+boXISywA1/nIv10aVF9q1GznuMkydPNEr+Pp8Q53nMGqTVMqxX+0zJTvxM+enNgK+MdSWj4gE/yX/WktKgZECtuM6ErzaBgV3BN40JEkNm+GQ==", "encryptionNonce": "qJelX76XvzOq0QJSYFDzCXB3RuW+7Glg"}"#;
+
+    #[test]
+    fn test_parse_ente_encrypted() {
+        let data = ENTE_ENCRYPTED_EXPORT.as_bytes();
+        let err = parse_import(data, None).unwrap_err();
+        assert!(err.contains("Ente Auth export is encrypted"), "{err}");
+        let err = parse_import(data, Some("wrong password")).unwrap_err();
+        assert_eq!(err, "Wrong password for this Ente Auth export");
+
+        let result = parse_import(data, Some("correct horse battery")).unwrap();
+        assert_eq!(result.format, "Ente Auth");
+        assert_eq!(result.accounts.len(), 1);
+        assert_eq!(result.accounts[0].issuer, "GitHub");
+        assert_eq!(result.accounts[0].label, "octocat");
+        assert_eq!(result.accounts[0].secret, "JBSWY3DPEHPK3PXP");
+        assert_eq!(result.accounts[0].tags, vec!["work"]);
+    }
+
+    const AUTHPRO_BACKUP: &str = r#"{
+        "Authenticators": [
+            {
+                "Type": 2,
+                "Icon": "github",
+                "Issuer": "GitHub",
+                "Username": "octocat",
+                "Secret": "JBSWY3DPEHPK3PXP",
+                "Pin": null,
+                "Algorithm": 1,
+                "Digits": 8,
+                "Period": 30,
+                "Counter": 0,
+                "Ranking": 0
+            },
+            {
+                "Type": 4,
+                "Issuer": "Steam",
+                "Username": "gamer",
+                "Secret": "GEZDGNBVGY3TQOJQ",
+                "Algorithm": 0,
+                "Digits": 5,
+                "Period": 30
+            },
+            {
+                "Type": 1,
+                "Issuer": "Bank",
+                "Username": "me",
+                "Secret": "GEZDGNBVGY3TQOJQ",
+                "Algorithm": 0,
+                "Digits": 6,
+                "Period": 30,
+                "Counter": 4
+            }
+        ],
+        "Categories": [
+            {"Id": "c1", "Name": "Work", "Ranking": 0},
+            {"Id": "c2", "Name": "Dev", "Ranking": 1}
+        ],
+        "AuthenticatorCategories": [
+            {"CategoryId": "c1", "AuthenticatorSecret": "JBSWY3DPEHPK3PXP", "Ranking": 0},
+            {"CategoryId": "c2", "AuthenticatorSecret": "JBSWY3DPEHPK3PXP", "Ranking": 0}
+        ],
+        "CustomIcons": []
+    }"#;
+
+    #[test]
+    fn test_parse_authpro() {
+        let result = parse_import(AUTHPRO_BACKUP.as_bytes(), None).unwrap();
+        assert_eq!(result.format, "Authenticator Pro");
+        assert_eq!(result.accounts.len(), 1);
+        assert_eq!(result.skipped.len(), 2);
+        let account = &result.accounts[0];
+        assert_eq!(account.issuer, "GitHub");
+        assert_eq!(account.label, "octocat");
+        assert_eq!((account.algorithm.as_str(), account.digits), ("SHA256", 8));
+        assert_eq!(account.tags, vec!["Work", "Dev"]);
+    }
+
+    #[test]
+    fn test_parse_authpro_encrypted() {
+        let salt = [7u8; AUTHPRO_SALT_LEN];
+        let iv = [9u8; AUTHPRO_IV_LEN];
+        let params = argon2::Params::new(
+            AUTHPRO_ARGON2_MEMORY_KIB,
+            AUTHPRO_ARGON2_ITERATIONS,
+            AUTHPRO_ARGON2_PARALLELISM,
+            Some(32),
+        )
+        .unwrap();
+        let mut key = [0u8; 32];
+        argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params)
+            .hash_password_into(b"backup pass", &salt, &mut key)
+            .unwrap();
+        let mut data = AUTHPRO_HEADER.to_vec();
+        data.extend_from_slice(&salt);
+        data.extend_from_slice(&iv);
+        data.extend(seal_for_test(&key, &iv, AUTHPRO_BACKUP.as_bytes()));
+
+        let err = parse_import(&data, None).unwrap_err();
+        assert!(
+            err.contains("Authenticator Pro backup is encrypted"),
+            "{err}"
+        );
+        let err = parse_import(&data, Some("nope")).unwrap_err();
+        assert_eq!(err, "Wrong password for this Authenticator Pro backup");
+        let result = parse_import(&data, Some("backup pass")).unwrap();
+        assert_eq!(result.format, "Authenticator Pro");
+        assert_eq!(result.accounts[0].issuer, "GitHub");
+
+        let mut legacy = AUTHPRO_LEGACY_HEADER.to_vec();
+        legacy.extend_from_slice(&[0u8; 64]);
+        let err = parse_import(&legacy, Some("backup pass")).unwrap_err();
+        assert!(err.contains("old encryption format"), "{err}");
+    }
+
     #[test]
     fn test_parse_google_auth_migration() {
         // Build a real protobuf payload for testing
@@ -759,7 +2585,7 @@ mod tests {
         let result = parse_google_auth_migration(&uri).unwrap();
         assert_eq!(result.format, "Google Authenticator");
         assert_eq!(result.accounts.len(), 1);
-        assert_eq!(result.skipped, 1);
+        assert_eq!(result.skipped.len(), 1);
         assert_eq!(result.accounts[0].issuer, "GitHub");
         assert_eq!(result.accounts[0].label, "user@example.com");
         assert_eq!(result.accounts[0].algorithm, "SHA1");
@@ -767,37 +2593,372 @@ mod tests {
         assert_eq!(result.accounts[0].period, 30);
     }
 
+    #[test]
+    fn test_parse_bitwarden() {
+        let json = r#"{
+            "encrypted": false,
+            "folders": [],
+            "items": [
+                {
+                    "type": 1,
+                    "name": "GitHub",
+                    "login": {
+                        "username": "octocat",
+                        "totp": "otpauth://totp/GitHub:old@example.com?secret=JBSWY3DPEHPK3PXP&issuer=GitHub",
+                        "uris": [{"match": null, "uri": "https://github.com/login"}]
+                    }
+                },
+                {
+                    "type": 1,
+                    "name": "",
+                    "login": {
+                        "username": "alice",
+                        "totp": "gezd gnbv gy3t qojq",
+                        "uris": [{"match": null, "uri": "https://www.example.org/signin"}]
+                    }
+                },
+                {
+                    "type": 1,
+                    "name": "Forum",
+                    "login": {"username": "bob", "totp": null, "uris": null}
+                },
+                {"type": 2, "name": "Note", "secureNote": {"type": 0}},
+                {
+                    "type": 1,
+                    "name": "Steam",
+                    "login": {"username": "gamer", "totp": "steam://ABCDEF"}
+                }
+            ]
+        }"#;
+
+        let result = parse_import(json.as_bytes(), None).unwrap();
+        assert_eq!(result.format, "Bitwarden");
+        assert_eq!(result.accounts.len(), 2);
+        assert_eq!(result.skipped.len(), 3);
+        assert_eq!(result.accounts[0].issuer, "GitHub");
+        assert_eq!(result.accounts[0].label, "octocat");
+        assert_eq!(result.accounts[0].secret, "JBSWY3DPEHPK3PXP");
+        // Bare secret: issuer from the website, defaults for the rest
+        assert_eq!(result.accounts[1].issuer, "example.org");
+        assert_eq!(result.accounts[1].label, "alice");
+        assert_eq!(result.accounts[1].secret, "GEZDGNBVGY3TQOJQ");
+        assert_eq!(result.accounts[1].digits, 6);
+
+        let encrypted = r#"{"encrypted": true, "passwordProtected": true, "data": "2.abc"}"#;
+        let err = parse_import(encrypted.as_bytes(), None).unwrap_err();
+        assert!(err.contains("Bitwarden export is encrypted"), "{err}");
+    }
+
+    #[test]
+    fn test_parse_onepux() {
+        use std::io::Write;
+
+        let export = r#"{
+            "accounts": [{
+                "attrs": {"name": "Personal"},
+                "vaults": [{
+                    "attrs": {"name": "Private"},
+                    "items": [
+                        {
+                            "overview": {
+                                "title": "Dropbox",
+                                "subtitle": "me@example.com",
+                                "url": "https://www.dropbox.com/"
+                            },
+                            "details": {
+                                "loginFields": [
+                                    {"value": "dropbox-user", "designation": "username"},
+                                    {"value": "hunter2", "designation": "password"}
+                                ],
+                                "sections": [{
+                                    "fields": [
+                                        {"title": "one-time password", "value": {
+                                            "totp": "otpauth://totp/Dropbox?secret=JBSWY3DPEHPK3PXP&digits=8"
+                                        }},
+                                        {"title": "pin", "value": {"concealed": "1234"}}
+                                    ]
+                                }]
+                            }
+                        },
+                        {
+                            "overview": {"title": "Wi-Fi", "subtitle": ""},
+                            "details": {"sections": []}
+                        }
+                    ]
+                }]
+            }]
+        }"#;
+
+        let mut buf = std::io::Cursor::new(Vec::new());
+        {
+            let mut zip = zip::ZipWriter::new(&mut buf);
+            let options = zip::write::SimpleFileOptions::default();
+            zip.start_file("export.attributes", options).unwrap();
+            zip.write_all(br#"{"version": 3}"#).unwrap();
+            zip.start_file("export.data", options).unwrap();
+            zip.write_all(export.as_bytes()).unwrap();
+            zip.finish().unwrap();
+        }
+
+        let result = parse_import(&buf.into_inner(), None).unwrap();
+        assert_eq!(result.format, "1Password");
+        assert_eq!(result.accounts.len(), 1);
+        assert_eq!(result.skipped.len(), 1);
+        assert_eq!(result.accounts[0].issuer, "Dropbox");
+        assert_eq!(result.accounts[0].label, "dropbox-user");
+        assert_eq!(result.accounts[0].digits, 8);
+    }
+
+    #[test]
+    fn test_parse_onepux_without_export_data() {
+        let mut buf = std::io::Cursor::new(Vec::new());
+        {
+            let mut zip = zip::ZipWriter::new(&mut buf);
+            zip.start_file("other.txt", zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.finish().unwrap();
+        }
+        let err = parse_import(&buf.into_inner(), None).unwrap_err();
+        assert!(err.contains("1Password"), "{err}");
+    }
+
+    #[test]
+    fn test_parse_proton_pass() {
+        let json = r#"{
+            "version": "1.21.2",
+            "userId": "u1",
+            "encrypted": false,
+            "vaults": {
+                "share-a": {
+                    "name": "Personal",
+                    "items": [
+                        {
+                            "itemId": "i1",
+                            "data": {
+                                "metadata": {"name": "Proton", "note": ""},
+                                "extraFields": [{
+                                    "fieldName": "Backup 2FA",
+                                    "type": "totp",
+                                    "data": {"totpUri": "GEZDGNBVGY3TQOJQ"}
+                                }],
+                                "type": "login",
+                                "content": {
+                                    "itemEmail": "me@proton.me",
+                                    "itemUsername": "",
+                                    "password": "",
+                                    "urls": ["https://account.proton.me"],
+                                    "totpUri": "otpauth://totp/me%40proton.me?secret=JBSWY3DPEHPK3PXP&issuer=Proton%20Mail"
+                                }
+                            }
+                        },
+                        {
+                            "itemId": "i2",
+                            "data": {
+                                "metadata": {"name": "Shopping list"},
+                                "type": "note",
+                                "content": {}
+                            }
+                        },
+                        {
+                            "itemId": "i3",
+                            "data": {
+                                "metadata": {"name": "Bank"},
+                                "type": "login",
+                                "content": {"username": "jdoe", "totpUri": "", "urls": []}
+                            }
+                        }
+                    ]
+                }
+            }
+        }"#;
+
+        let result = parse_import(json.as_bytes(), None).unwrap();
+        assert_eq!(result.format, "Proton Pass");
+        assert_eq!(result.accounts.len(), 2);
+        assert_eq!(result.skipped.len(), 2);
+        assert_eq!(result.accounts[0].issuer, "Proton Mail");
+        assert_eq!(result.accounts[0].label, "me@proton.me");
+        // Extra TOTP field with a bare secret takes the item name
+        assert_eq!(result.accounts[1].issuer, "Proton");
+        assert_eq!(result.accounts[1].secret, "GEZDGNBVGY3TQOJQ");
+
+        let encrypted = r#"{"version": "1.21.2", "encrypted": true, "vaults": {}}"#;
+        let err = parse_import(encrypted.as_bytes(), None).unwrap_err();
+        assert!(err.contains("PGP-encrypted"), "{err}");
+    }
+
+    #[test]
+    fn test_url_host() {
+        assert_eq!(
+            url_host("https://www.github.com/login").as_deref(),
+            Some("github.com")
+        );
+        assert_eq!(
+            url_host("user@Example.org:8443/x").as_deref(),
+            Some("example.org")
+        );
+        assert_eq!(url_host("https://"), None);
+    }
+
+    fn migration_uri(batch_id: i32, batch_size: i32, batch_index: i32, issuer: &str) -> String {
+        let payload = google_auth_proto::MigrationPayload {
+            otp_parameters: vec![google_auth_proto::OtpParameters {
+                secret: b"Hello!".to_vec(),
+                name: format!("{issuer}:me"),
+                issuer: issuer.to_string(),
+                algorithm: google_auth_proto::Algorithm::Sha1 as i32,
+                digits: google_auth_proto::DigitCount::Six as i32,
+                otp_type: google_auth_proto::OtpType::Totp as i32,
+                counter: 0,
+            }],
+            version: 1,
+            batch_size,
+            batch_index,
+            batch_id,
+        };
+        let b64 = base64::Engine::encode(
+            &base64::engine::general_purpose::STANDARD,
+            payload.encode_to_vec(),
+        );
+        format!(
+            "otpauth-migration://offline?data={}",
+            b64.replace('+', "%2B")
+        )
+    }
+
+    #[test]
+    fn test_migration_session_assembles_batches() {
+        let mut session = MigrationSession::default();
+        let progress = session.add(&migration_uri(77, 3, 2, "Third")).unwrap();
+        assert_eq!(progress.batch_size, 3);
+        assert_eq!(progress.missing, vec![0, 1]);
+        assert!(!progress.complete);
+
+        // Rescanning a code changes nothing
+        session.add(&migration_uri(77, 3, 2, "Third")).unwrap();
+        let progress = session.add(&migration_uri(77, 3, 0, "First")).unwrap();
+        assert_eq!(progress.received, vec![0, 2]);
+        assert_eq!(progress.account_count, 2);
+
+        let err = session.finish().unwrap_err();
+        assert_eq!(err, "Scan the remaining QR codes first: 2 of 3");
+        // The scanned codes survive a premature finish
+        let progress = session.add(&migration_uri(77, 3, 1, "Second")).unwrap();
+        assert!(progress.complete);
+
+        // Previewing leaves the session to be confirmed
+        assert_eq!(session.result().unwrap().accounts.len(), 3);
+        assert!(session.progress().complete);
+
+        let result = session.finish().unwrap();
+        let issuers: Vec<&str> = result.accounts.iter().map(|a| a.issuer.as_str()).collect();
+        assert_eq!(issuers, ["First", "Second", "Third"]);
+        // Entries are numbered across the whole export
+        assert_eq!(result.sources, vec![0, 1, 2]);
+        assert_eq!(session.progress().received, Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_migration_session_rejects_other_export() {
+        let mut session = MigrationSession::default();
+        session.add(&migration_uri(77, 2, 0, "First")).unwrap();
+        let err = session.add(&migration_uri(78, 2, 1, "Other")).unwrap_err();
+        assert!(err.contains("different Google Authenticator export"));
+        assert!(session.add(&migration_uri(77, 2, 5, "Bad")).is_err());
+        assert!(session
+            .add("otpauth://totp/x?secret=JBSWY3DPEHPK3PXP")
+            .is_err());
+    }
+
+    #[test]
+    fn test_migration_rejects_huge_batch_size() {
+        let uri = migration_uri(77, i32::MAX, 0, "Crafted");
+        let err = parse_migration_batch(&uri).err();
+        assert_eq!(
+            err.as_deref(),
+            Some("Invalid batch count in migration data")
+        );
+
+        let mut session = MigrationSession::default();
+        assert!(session.add(&uri).is_err());
+        assert!(parse_import(uri.as_bytes(), None).is_err());
+        assert!(parse_migration_batch(&migration_uri(77, 100, 99, "Large")).is_ok());
+    }
+
+    #[test]
+    fn test_parse_import_multiple_migration_uris() {
+        let text = format!(
+            "{}\n{}\n",
+            migration_uri(5, 2, 1, "Second"),
+            migration_uri(5, 2, 0, "First")
+        );
+        let result = parse_import(text.as_bytes(), None).unwrap();
+        assert_eq!(result.format, "Google Authenticator");
+        assert_eq!(result.accounts[0].issuer, "First");
+        assert_eq!(result.accounts.len(), 2);
+
+        let partial = migration_uri(5, 2, 0, "First") + "\n" + &migration_uri(5, 2, 0, "First");
+        let err = parse_import(partial.as_bytes(), None).unwrap_err();
+        assert!(err.contains("2 of 2"), "{err}");
+    }
+
     #[test]
     fn test_auto_detect_aegis() {
         let json = r#"{"db":{"entries":[]}}"#;
-        let result = parse_import(json.as_bytes()).unwrap();
+        let result = parse_import(json.as_bytes(), None).unwrap();
         assert_eq!(result.format, "Aegis");
     }
 
     #[test]
     fn test_auto_detect_twofas() {
         let json = r#"{"services":[]}"#;
-        let result = parse_import(json.as_bytes()).unwrap();
+        let result = parse_import(json.as_bytes(), None).unwrap();
         assert_eq!(result.format, "2FAS");
     }
 
     #[test]
     fn test_auto_detect_andotp() {
         let json = r#"[]"#;
-        let result = parse_import(json.as_bytes()).unwrap();
+        let result = parse_import(json.as_bytes(), None).unwrap();
         assert_eq!(result.format, "andOTP");
     }
 
+    #[test]
+    fn test_auto_detect_keepass() {
+        let xml = "<?xml version=\"1.0\"?><KeePassFile><Root></Root></KeePassFile>";
+        let result = parse_import(xml.as_bytes(), None).unwrap();
+        assert_eq!(result.format, "KeePass XML");
+
+        let csv = "\"Group\",\"Title\",\"Username\",\"TOTP\"\n";
+        let result = parse_import(csv.as_bytes(), None).unwrap();
+        assert_eq!(result.format, "KeePassXC CSV");
+
+        let kdbx = [0x03, 0xd9, 0xa2, 0x9a, 0x67, 0xfb, 0x4b, 0xb5, 0x00, 0xff];
+        let err = parse_import(&kdbx, None).unwrap_err();
+        assert!(err.contains("Export to CSV or XML"), "{err}");
+    }
+
+    #[test]
+    fn test_parse_import_assigns_icons() {
+        let json = r#"[
+            {"secret": "JBSWY3DPEHPK3PXP", "issuer": "Amazon Web Services", "label": "root"},
+            {"secret": "GEZDGNBVGY3TQOJQ", "issuer": "Homelab", "label": "admin"}
+        ]"#;
+        let result = parse_import(json.as_bytes(), None).unwrap();
+        assert_eq!(result.accounts[0].icon.as_deref(), Some("aws"));
+        assert_eq!(result.accounts[1].icon, None);
+    }
+
     #[test]
     fn test_empty_file_error() {
-        let result = parse_import(b"");
+        let result = parse_import(b"", None);
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("empty"));
     }
 
     #[test]
     fn test_unrecognized_format_error() {
-        let result = parse_import(b"this is not a valid format");
+        let result = parse_import(b"this is not a valid format", None);
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("Unrecognized"));
     }
@@ -0,0 +1,440 @@
+//! KeePass interop: reading KeePassXC CSV and KeePass 2 XML exports, and
+//! writing a CSV that KeePassXC imports.
+//!
+//! KeePassXC keeps an entry's TOTP in an `otp` attribute (an otpauth URI or
+//! KeeOTP's `key=...&step=...` form). Databases from before KeePassXC 2.6
+//! use separate `TOTP Seed` and `TOTP Settings` attributes instead.
+
+use std::collections::HashMap;
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+use crate::import::{self, ImportResult, SkipReason};
+use crate::storage::Account;
+
+/// Column layout of KeePassXC's own CSV export.
+const CSV_HEADER: [&str; 10] = [
+    "Group",
+    "Title",
+    "Username",
+    "Password",
+    "URL",
+    "Notes",
+    "TOTP",
+    "Icon",
+    "Last Modified",
+    "Created",
+];
+const EXPORT_GROUP: &str = "Ghost Auth";
+
+/// KDBX database files start with this signature; they are always encrypted.
+pub const KDBX_SIGNATURE: [u8; 4] = [0x03, 0xd9, 0xa2, 0x9a];
+
+/// Whether `text` starts with a CSV header naming both a title and a TOTP
+/// column, as KeePassXC's export does.
+pub fn looks_like_csv(text: &str) -> bool {
+    let header = text.lines().next().unwrap_or_default();
+    let mut reader = csv::ReaderBuilder::new().from_reader(header.as_bytes());
+    reader.headers().is_ok_and(|headers| {
+        find_column(headers, &["Title"]).is_some()
+            && find_column(headers, &["TOTP", "OTP"]).is_some()
+    })
+}
+
+pub fn parse_csv(text: &str) -> Result<ImportResult, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(text.as_bytes());
+    let headers = reader
+        .headers()
+        .map_err(|e| format!("Invalid CSV header: {e}"))?
+        .clone();
+    let totp_col = find_column(&headers, &["TOTP", "OTP"])
+        .ok_or_else(|| "CSV file has no TOTP column".to_string())?;
+    let title_col = find_column(&headers, &["Title"]);
+    let username_col = find_column(&headers, &["Username", "User Name", "Login"]);
+    let url_col = find_column(&headers, &["URL"]);
+
+    let mut result = ImportResult::new("KeePassXC CSV");
+
+    for (i, record) in reader.records().enumerate() {
+        // Row 1 is the header
+        let record = record.map_err(|e| format!("Invalid CSV on row {}: {e}", i + 2))?;
+        let field = |col: Option<usize>| col.and_then(|c| record.get(c)).unwrap_or_default();
+
+        let (title, username) = (field(title_col), field(username_col));
+        let otp = field(Some(totp_col));
+        if otp.trim().is_empty() {
+            result.skip(i, title, username, SkipReason::NoTotp);
+            continue;
+        }
+        let parsed = entry_account(title, username, field(url_col), Some(otp), None, None);
+        result.record(i, title, username, parsed);
+    }
+
+    Ok(result)
+}
+
+fn find_column(headers: &csv::StringRecord, names: &[&str]) -> Option<usize> {
+    headers
+        .iter()
+        .position(|h| names.iter().any(|name| h.trim().eq_ignore_ascii_case(name)))
+}
+
+/// Parse an unencrypted KeePass 2 XML export. Entry history is ignored.
+pub fn parse_xml(text: &str) -> Result<ImportResult, String> {
+    let entries = read_xml_entries(text)?;
+
+    let mut result = ImportResult::new("KeePass XML");
+
+    for (i, fields) in entries.iter().enumerate() {
+        let get = |key: &str| fields.get(key).map(String::as_str).unwrap_or_default();
+        let (title, username) = (get("Title"), get("UserName"));
+        let otp = fields.get("otp").map(String::as_str);
+        let seed = fields.get("TOTP Seed").map(String::as_str);
+        let settings = fields.get("TOTP Settings").map(String::as_str);
+        if otp.unwrap_or_default().trim().is_empty() && seed.unwrap_or_default().trim().is_empty() {
+            result.skip(i, title, username, SkipReason::NoTotp);
+            continue;
+        }
+        let parsed = entry_account(title, username, get("URL"), otp, seed, settings);
+        result.record(i, title, username, parsed);
+    }
+
+    Ok(result)
+}
+
+#[derive(Clone, Copy)]
+enum XmlText {
+    Key,
+    Value,
+}
+
+/// Collect each current entry's string fields as a key/value map.
+fn read_xml_entries(text: &str) -> Result<Vec<HashMap<String, String>>, String> {
+    let invalid = |e: quick_xml::Error| format!("Invalid KeePass XML: {e}");
+    let mut reader = Reader::from_str(text);
+    reader.config_mut().trim_text(true);
+
+    let mut entries = Vec::new();
+    let mut saw_root = false;
+    let mut history_depth = 0usize;
+    let mut entry: Option<HashMap<String, String>> = None;
+    let mut target: Option<XmlText> = None;
+    let mut key = String::new();
+    let mut value = String::new();
+
+    loop {
+        match reader.read_event().map_err(invalid)? {
+            Event::Start(e) => match e.name().as_ref() {
+                b"KeePassFile" => saw_root = true,
+                b"History" => history_depth += 1,
+                b"Entry" if history_depth == 0 => entry = Some(HashMap::new()),
+                b"String" => {
+                    key.clear();
+                    value.clear();
+                }
+                b"Key" => target = Some(XmlText::Key),
+                b"Value" => target = Some(XmlText::Value),
+                _ => {}
+            },
+            Event::Text(t) => {
+                let t = t.unescape().map_err(invalid)?;
+                match target {
+                    Some(XmlText::Key) => key.push_str(&t),
+                    Some(XmlText::Value) => value.push_str(&t),
+                    None => {}
+                }
+            }
+            Event::CData(t) => {
+                if let Some(XmlText::Value) = target {
+                    value.push_str(&String::from_utf8_lossy(&t));
+                }
+            }
+            Event::End(e) => match e.name().as_ref() {
+                b"History" => history_depth = history_depth.saturating_sub(1),
+                b"Key" | b"Value" => target = None,
+                b"String" if history_depth == 0 => {
+                    if let Some(fields) = entry.as_mut() {
+                        fields.insert(std::mem::take(&mut key), std::mem::take(&mut value));
+                    }
+                }
+                b"Entry" if history_depth == 0 => entries.extend(entry.take()),
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if !saw_root {
+        return Err("Unrecognized XML format. Expected a KeePass XML export.".to_string());
+    }
+    Ok(entries)
+}
+
+/// Build an account from a KeePass entry's TOTP attributes, preferring the
+/// current `otp` attribute over the legacy seed and settings pair.
+fn entry_account(
+    title: &str,
+    username: &str,
+    url: &str,
+    otp: Option<&str>,
+    seed: Option<&str>,
+    settings: Option<&str>,
+) -> Result<Account, SkipReason> {
+    let mut account = match otp.map(str::trim).filter(|otp| !otp.is_empty()) {
+        Some(otp) if !otp.starts_with("otpauth://") && otp.contains("key=") => parse_keeotp(otp)?,
+        Some(otp) => import::parse_totp_field(otp)?,
+        None => parse_legacy(seed.ok_or(SkipReason::NoTotp)?, settings)?,
+    };
+    let url = Some(url).filter(|url| !url.trim().is_empty());
+    import::fill_item_identity(&mut account, title, url, username);
+    Ok(account)
+}
+
+/// KeeOTP's `key=BASE32&step=30&size=6&otpHashMode=sha1` form.
+fn parse_keeotp(otp: &str) -> Result<Account, SkipReason> {
+    let mut secret = None;
+    let mut period = 30;
+    let mut digits = 6;
+    let mut algorithm = "SHA1".to_string();
+
+    for pair in otp.split('&') {
+        let (name, value) = pair.split_once('=').ok_or(SkipReason::Unreadable)?;
+        let value = import::percent_decode(value).map_err(|_| SkipReason::Unreadable)?;
+        match name {
+            "key" => secret = Some(value),
+            "step" => period = value.parse().map_err(|_| SkipReason::InvalidPeriod)?,
+            "size" => digits = value.parse().map_err(|_| SkipReason::InvalidDigits)?,
+            "otpHashMode" => algorithm = value,
+            "type" if !value.eq_ignore_ascii_case("totp") => {
+                return Err(SkipReason::UnsupportedType)
+            }
+            "encoding" if !value.eq_ignore_ascii_case("base32") => {
+                return Err(SkipReason::InvalidSecret)
+            }
+            _ => {}
+        }
+    }
+    let secret = secret.ok_or(SkipReason::InvalidSecret)?;
+    import::totp_account(&secret, &algorithm, digits, period)
+}
+
+/// Pre-2.6 KeePassXC: a Base32 `TOTP Seed` plus `TOTP Settings` of the form
+/// `period;digits`, where the digits are `S` for Steam codes.
+fn parse_legacy(seed: &str, settings: Option<&str>) -> Result<Account, SkipReason> {
+    let (period, digits) = match settings.map(str::trim).filter(|s| !s.is_empty()) {
+        Some(settings) => {
+            let mut parts = settings.split(';');
+            let period = parts.next().unwrap_or_default().trim();
+            let period = period.parse().map_err(|_| SkipReason::InvalidPeriod)?;
+            let digits = match parts.next().unwrap_or("6").trim() {
+                "S" => return Err(SkipReason::UnsupportedType),
+                digits => digits.parse().map_err(|_| SkipReason::InvalidDigits)?,
+            };
+            (period, digits)
+        }
+        None => (30, 6),
+    };
+    import::totp_account(seed, "SHA1", digits, period)
+}
+
+/// Write `accounts` as a CSV file in KeePassXC's export layout, with each
+/// account's otpauth URI in the TOTP column.
+pub fn export_csv(accounts: &[Account]) -> Result<String, String> {
+    let mut writer = csv::WriterBuilder::new()
+        .quote_style(csv::QuoteStyle::Always)
+        .from_writer(Vec::new());
+    let write_err = |e: csv::Error| format!("Failed to write CSV: {e}");
+    writer.write_record(CSV_HEADER).map_err(write_err)?;
+
+    for account in accounts {
+        let uri = crate::totp::otpauth_uri(account)?;
+        let title = if account.issuer.is_empty() {
+            &account.label
+        } else {
+            &account.issuer
+        };
+        let modified = format_timestamp(account.last_modified);
+        writer
+            .write_record([
+                EXPORT_GROUP,
+                title,
+                &account.label,
+                "",
+                "",
+                "",
+                &uri,
+                "0",
+                &modified,
+                &modified,
+            ])
+            .map_err(write_err)?;
+    }
+
+    let data = writer
+        .into_inner()
+        .map_err(|e| format!("Failed to write CSV: {e}"))?;
+    String::from_utf8(data).map_err(|e| format!("Failed to write CSV: {e}"))
+}
+
+/// ISO 8601 in UTC, as KeePassXC writes dates; empty when unknown.
+fn format_timestamp(secs: u64) -> String {
+    if secs == 0 {
+        return String::new();
+    }
+    let (year, month, day) = crate::auto_backup::civil_date(secs / 86_400);
+    let rem = secs % 86_400;
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        rem / 3600,
+        rem / 60 % 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(issuer: &str, label: &str, secret: &str) -> Account {
+        Account {
+            id: issuer.to_lowercase(),
+            issuer: issuer.into(),
+            label: label.into(),
+            secret: secret.into(),
+            algorithm: "SHA1".into(),
+            digits: 6,
+            period: 30,
+            icon: None,
+            last_modified: 1_700_000_000,
+            tags: vec![],
+        }
+    }
+
+    #[test]
+    fn test_parse_csv() {
+        let csv = "\"Group\",\"Title\",\"Username\",\"Password\",\"URL\",\"Notes\",\"TOTP\"\n\
+            \"Root\",\"GitHub\",\"octocat\",\"pw\",\"https://github.com\",\"\",\
+            \"otpauth://totp/GitHub:octocat?secret=JBSWY3DPEHPK3PXP&period=30&digits=6&issuer=GitHub\"\n\
+            \"Root\",\"Router\",\"admin\",\"pw\",\"\",\"multi\nline\",\"\"\n\
+            \"Root\",\"Legacy\",\"me\",\"\",\"\",\"\",\"key=GEZDGNBVGY3TQOJQ&step=60&size=8&otpHashMode=sha256\"\n";
+
+        let result = parse_csv(csv).unwrap();
+        assert_eq!(result.format, "KeePassXC CSV");
+        assert_eq!(result.accounts.len(), 2);
+        assert_eq!(result.skipped.len(), 1);
+        assert_eq!(result.skipped[0].index, 1);
+        assert_eq!(result.skipped[0].issuer, "Router");
+        assert_eq!(result.skipped[0].reason, SkipReason::NoTotp);
+        assert_eq!(result.sources, vec![0, 2]);
+        assert_eq!(result.accounts[0].issuer, "GitHub");
+        assert_eq!(result.accounts[0].label, "octocat");
+        let keeotp = &result.accounts[1];
+        assert_eq!(keeotp.issuer, "Legacy");
+        assert_eq!(keeotp.label, "me");
+        assert_eq!(keeotp.secret, "GEZDGNBVGY3TQOJQ");
+        assert_eq!(
+            (keeotp.algorithm.as_str(), keeotp.digits, keeotp.period),
+            ("SHA256", 8, 60)
+        );
+    }
+
+    #[test]
+    fn test_parse_xml() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8" standalone="yes"?>
+<KeePassFile>
+    <Meta><Generator>KeePassXC</Generator></Meta>
+    <Root>
+        <Group>
+            <Name>Root</Name>
+            <Entry>
+                <String><Key>Title</Key><Value>Dropbox</Value></String>
+                <String><Key>UserName</Key><Value>me@example.com</Value></String>
+                <String><Key>Password</Key><Value ProtectInMemory="True">hunter2</Value></String>
+                <String><Key>otp</Key><Value>otpauth://totp/Dropbox:old?secret=JBSWY3DPEHPK3PXP&amp;issuer=Dropbox</Value></String>
+                <History>
+                    <Entry>
+                        <String><Key>Title</Key><Value>Dropbox (old)</Value></String>
+                        <String><Key>otp</Key><Value>otpauth://totp/x?secret=GEZDGNBVGY3TQOJQ</Value></String>
+                    </Entry>
+                </History>
+            </Entry>
+            <Group>
+                <Name>Work</Name>
+                <Entry>
+                    <String><Key>Title</Key><Value>VPN</Value></String>
+                    <String><Key>UserName</Key><Value>jdoe</Value></String>
+                    <String><Key>TOTP Seed</Key><Value>GEZD GNBV GY3T QOJQ</Value></String>
+                    <String><Key>TOTP Settings</Key><Value>60;8</Value></String>
+                </Entry>
+                <Entry>
+                    <String><Key>Title</Key><Value>Steam</Value></String>
+                    <String><Key>TOTP Seed</Key><Value>JBSWY3DPEHPK3PXP</Value></String>
+                    <String><Key>TOTP Settings</Key><Value>30;S</Value></String>
+                </Entry>
+                <Entry>
+                    <String><Key>Title</Key><Value>Wi-Fi</Value></String>
+                    <String><Key>Notes</Key><Value/></String>
+                </Entry>
+            </Group>
+        </Group>
+    </Root>
+</KeePassFile>"#;
+
+        let result = parse_xml(xml).unwrap();
+        assert_eq!(result.format, "KeePass XML");
+        assert_eq!(result.accounts.len(), 2);
+        let reasons: Vec<_> = result.skipped.iter().map(|s| (s.index, s.reason)).collect();
+        assert_eq!(
+            reasons,
+            vec![(2, SkipReason::UnsupportedType), (3, SkipReason::NoTotp)]
+        );
+        assert_eq!(result.accounts[0].issuer, "Dropbox");
+        assert_eq!(result.accounts[0].label, "me@example.com");
+        assert_eq!(result.accounts[0].secret, "JBSWY3DPEHPK3PXP");
+        assert_eq!(result.accounts[1].issuer, "VPN");
+        assert_eq!(result.accounts[1].label, "jdoe");
+        assert_eq!(result.accounts[1].secret, "GEZDGNBVGY3TQOJQ");
+        assert_eq!(
+            (result.accounts[1].digits, result.accounts[1].period),
+            (8, 60)
+        );
+
+        assert!(parse_xml("<html></html>").unwrap_err().contains("KeePass"));
+    }
+
+    #[test]
+    fn test_export_csv_roundtrip() {
+        let mut sha256 = account("Example, Inc.", "a \"quoted\" user", "GEZDGNBVGY3TQOJQ");
+        sha256.algorithm = "SHA256".into();
+        sha256.digits = 8;
+        sha256.period = 60;
+        let accounts = vec![account("GitHub", "octocat", "JBSWY3DPEHPK3PXP"), sha256];
+
+        let csv = export_csv(&accounts).unwrap();
+        assert!(csv.starts_with("\"Group\",\"Title\",\"Username\""));
+        assert!(csv.contains("\"2023-11-14T22:13:20Z\""));
+        assert!(looks_like_csv(&csv));
+
+        let result = parse_csv(&csv).unwrap();
+        assert!(result.skipped.is_empty());
+        for (imported, original) in result.accounts.iter().zip(&accounts) {
+            assert_eq!(imported.issuer, original.issuer);
+            assert_eq!(imported.label, original.label);
+            assert_eq!(imported.secret, original.secret);
+            assert_eq!(imported.algorithm, original.algorithm);
+            assert_eq!(imported.digits, original.digits);
+            assert_eq!(imported.period, original.period);
+        }
+    }
+
+    #[test]
+    fn test_looks_like_csv() {
+        assert!(looks_like_csv("Title,Username,TOTP\nGitHub,me,"));
+        assert!(!looks_like_csv("Title,Username,Password\n"));
+        assert!(!looks_like_csv("otpauth://totp/x?secret=JBSWY3DPEHPK3PXP"));
+    }
+}
@@ -1,10 +1,22 @@
+mod auto_backup;
 mod backup;
 mod commands;
+mod duplicates;
 mod google_auth_proto;
+mod health;
+mod icons;
 mod import;
+mod keepass;
 mod keystore;
+mod paper_backup;
+mod password_strength;
 mod pin;
+mod qr_image;
+mod recovery_kit;
+mod search;
+mod settings;
 mod storage;
+mod storage_backend;
 mod sync;
 mod sync_transport;
 mod sync_ws;
@@ -112,7 +124,7 @@ pub fn run() {
             std::thread::spawn(move || {
                 let _ = tx.send(storage::Storage::new(data_dir_clone));
             });
-            let store = rx
+            let mut store = rx
                 .recv_timeout(std::time::Duration::from_secs(5))
                 .map_err(|_| {
                     tracing::error!("Storage initialization timed out after 5s");
@@ -122,9 +134,18 @@ pub fn run() {
                     tracing::error!(error = %e, "Failed to initialize encrypted storage");
                     e
                 })?;
+
+            // Keep tombstones until every known sync peer has seen them
+            let history = sync::SyncHistory::load(&data_dir);
+            let settings = settings::Settings::load(&data_dir);
+            store.set_tombstone_retention(
+                history.tombstone_retention(settings.tombstone_max_retention_days),
+            );
             app.manage(Mutex::new(store));
+            auto_backup::spawn_scheduler(app.handle().clone(), data_dir.clone());
             app.manage(pin::PinManager::new(data_dir));
             app.manage(commands::SyncManager::new());
+            app.manage(Mutex::new(import::MigrationSession::default()));
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -132,23 +153,52 @@ pub fn run() {
             commands::add_account,
             commands::add_account_manual,
             commands::edit_account,
+            commands::edit_account_tags,
             commands::delete_account,
             commands::reorder_accounts,
+            commands::search_accounts,
+            commands::find_duplicate_accounts,
+            commands::merge_duplicate_accounts,
+            commands::get_vault_health,
+            commands::record_account_use,
             commands::generate_code,
             commands::generate_all_codes,
+            commands::get_issuer_icon,
             commands::has_pin,
             commands::set_pin,
             commands::verify_pin,
             commands::remove_pin,
+            commands::check_password_strength,
             commands::export_backup,
+            commands::export_backup_to_recipients,
+            commands::generate_recovery_keypair,
+            commands::create_recovery_kit,
+            commands::get_recovery_kit_info,
+            commands::generate_paper_backup,
+            commands::assemble_paper_backup,
+            commands::verify_paper_backup_checksum,
+            commands::save_paper_backup_file,
+            commands::export_keepass_csv,
             commands::import_backup,
             commands::import_backup_confirm,
+            commands::verify_backup,
+            commands::restore_backup_preview,
+            commands::restore_backup_confirm,
             commands::save_backup_file,
+            commands::get_auto_backup_status,
+            commands::set_auto_backup_password,
+            commands::run_auto_backup,
             commands::verify_recovery_code,
             commands::has_recovery_codes,
             commands::get_export_accounts,
             commands::import_external_preview,
             commands::import_external_confirm,
+            commands::migration_import_add,
+            commands::migration_import_preview,
+            commands::migration_import_confirm,
+            commands::migration_import_cancel,
+            commands::scan_qr_image_preview,
+            commands::scan_qr_image_confirm,
             commands::sync_start,
             commands::sync_poll,
             commands::sync_join,
@@ -156,6 +206,8 @@ pub fn run() {
             commands::sync_cancel,
             commands::sync_history,
             commands::save_theme,
+            commands::get_settings,
+            commands::save_settings,
         ])
         .build(tauri::generate_context!())
         .expect("Fatal: failed to start Ghost Auth — check system logs for details")
@@ -0,0 +1,409 @@
+//! Printable paper backups: an HTML sheet (printed or saved as PDF from the
+//! system print dialog) with an otpauth QR code and the Base32 secret for each
+//! account, plus optionally the whole vault as a password-encrypted backup
+//! split over a set of dense QR codes.
+//!
+//! Each encrypted part is an alphanumeric-mode QR payload:
+//! `GHOSTQR1-<part>-<count>-<backup checksum>-<base32 chunk>`. Parts can be
+//! scanned in any order; the checksum ties them to one backup.
+
+use std::collections::BTreeMap;
+
+use qrcode::render::svg;
+use qrcode::{EcLevel, QrCode};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::auto_backup::civil_date;
+use crate::storage::Account;
+use crate::totp;
+
+const PART_PREFIX: &str = "GHOSTQR1";
+/// Backup bytes per QR part; about 1300 alphanumeric characters, which
+/// still scans reliably from a printed page.
+const PART_BYTES: usize = 800;
+const DAY_SECS: u64 = 86_400;
+
+#[derive(Serialize, Clone, Debug)]
+pub struct PaperBackup {
+    pub html: String,
+    /// Checksum of the accounts on the sheet, to confirm a restore later.
+    pub checksum: String,
+    pub generated_at: u64,
+    pub account_count: usize,
+    /// Number of QR codes holding the encrypted backup, 0 if none.
+    pub backup_parts: usize,
+}
+
+/// Render a paper backup sheet for `accounts`. `encrypted_backup` is a GHST
+/// backup to include as a QR set.
+pub fn generate(
+    accounts: &[Account],
+    encrypted_backup: Option<&[u8]>,
+    generated_at: u64,
+) -> Result<PaperBackup, String> {
+    let checksum = accounts_checksum(accounts);
+    let parts = encrypted_backup.map(split_backup).unwrap_or_default();
+
+    let mut html = String::new();
+    html.push_str(concat!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n",
+        "<title>Ghost Auth paper backup</title>\n<style>\n",
+        "body { font-family: -apple-system, 'Segoe UI', Roboto, sans-serif; color: #000; margin: 24px; }\n",
+        "h1 { font-size: 20px; margin: 0 0 4px; }\n",
+        "h2 { font-size: 15px; margin: 0 0 2px; }\n",
+        ".meta, .note { font-size: 12px; margin: 2px 0; }\n",
+        ".warning { font-size: 12px; font-weight: bold; margin: 8px 0 16px; }\n",
+        ".account { display: flex; gap: 16px; align-items: center; padding: 10px 0; border-top: 1px solid #999; break-inside: avoid; }\n",
+        ".account svg { width: 120px; height: 120px; flex: none; }\n",
+        ".account p { margin: 2px 0; font-size: 12px; }\n",
+        ".secret { font-family: 'Courier New', monospace; font-size: 14px; letter-spacing: 1px; }\n",
+        ".encrypted { break-before: page; }\n",
+        ".parts { display: flex; flex-wrap: wrap; gap: 16px; }\n",
+        ".parts figure { margin: 0; text-align: center; break-inside: avoid; }\n",
+        ".parts svg { width: 300px; height: 300px; }\n",
+        "code { font-family: 'Courier New', monospace; }\n",
+        "</style>\n</head>\n<body>\n",
+    ));
+
+    html.push_str("<header>\n<h1>Ghost Auth paper backup</h1>\n");
+    html.push_str(&format!(
+        "<p class=\"meta\">Generated {} &middot; {} account{} &middot; Checksum <code>{checksum}</code></p>\n",
+        format_timestamp(generated_at),
+        accounts.len(),
+        if accounts.len() == 1 { "" } else { "s" },
+    ));
+    html.push_str(
+        "<p class=\"warning\">Anyone with this sheet can generate your sign-in codes. \
+         Store it somewhere safe and destroy old copies.</p>\n</header>\n",
+    );
+
+    html.push_str("<section class=\"accounts\">\n");
+    for account in accounts {
+        let uri = totp::otpauth_uri(account)?;
+        html.push_str("<div class=\"account\">\n");
+        html.push_str(&qr_svg(&uri, EcLevel::M)?);
+        html.push_str("\n<div>\n");
+        let title = if account.issuer.is_empty() {
+            &account.label
+        } else {
+            &account.issuer
+        };
+        html.push_str(&format!("<h2>{}</h2>\n", escape_html(title)));
+        if !account.issuer.is_empty() && !account.label.is_empty() {
+            html.push_str(&format!("<p>{}</p>\n", escape_html(&account.label)));
+        }
+        html.push_str(&format!(
+            "<p class=\"secret\">{}</p>\n",
+            escape_html(&group_secret(&account.secret))
+        ));
+        html.push_str(&format!(
+            "<p>{} &middot; {} digits &middot; {} seconds</p>\n",
+            escape_html(&account.algorithm.to_uppercase()),
+            account.digits,
+            account.period
+        ));
+        html.push_str("</div>\n</div>\n");
+    }
+    html.push_str("</section>\n");
+
+    if !parts.is_empty() {
+        html.push_str("<section class=\"encrypted\">\n<h2>Encrypted backup</h2>\n");
+        html.push_str(
+            "<p class=\"note\">To restore everything at once, scan all of these codes in \
+             Ghost Auth in any order, then enter the backup password.</p>\n",
+        );
+        html.push_str("<div class=\"parts\">\n");
+        for (i, part) in parts.iter().enumerate() {
+            html.push_str("<figure>\n");
+            html.push_str(&qr_svg(part, EcLevel::M)?);
+            html.push_str(&format!(
+                "\n<figcaption class=\"note\">Part {} of {}</figcaption>\n</figure>\n",
+                i + 1,
+                parts.len()
+            ));
+        }
+        html.push_str("</div>\n</section>\n");
+    }
+
+    html.push_str(&format!(
+        "<footer><p class=\"meta\">Checksum <code>{checksum}</code></p></footer>\n</body>\n</html>\n"
+    ));
+
+    Ok(PaperBackup {
+        html,
+        checksum,
+        generated_at,
+        account_count: accounts.len(),
+        backup_parts: parts.len(),
+    })
+}
+
+/// An order-independent checksum of the accounts' issuer, label, secret and
+/// code parameters, shown as four groups of four hex digits.
+pub fn accounts_checksum(accounts: &[Account]) -> String {
+    let mut entries: Vec<String> = accounts
+        .iter()
+        .map(|account| {
+            let secret = totp::decode_secret(&account.secret)
+                .map(|bytes| data_encoding::BASE32_NOPAD.encode(&bytes))
+                .unwrap_or_else(|| account.secret.to_uppercase());
+            format!(
+                "{}\n{}\n{}\n{}\n{}\n{}",
+                account.issuer,
+                account.label,
+                secret,
+                account.algorithm.to_uppercase(),
+                account.digits,
+                account.period
+            )
+        })
+        .collect();
+    entries.sort();
+
+    let mut hasher = Sha256::new();
+    for entry in &entries {
+        hasher.update(entry.as_bytes());
+        hasher.update([0x1e]);
+    }
+    let hex = data_encoding::HEXUPPER.encode(&hasher.finalize()[..8]);
+    hex.as_bytes()
+        .chunks(4)
+        .map(|group| std::str::from_utf8(group).unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Split an encrypted backup into QR part payloads.
+pub fn split_backup(data: &[u8]) -> Vec<String> {
+    let checksum = blob_checksum(data);
+    let count = data.len().div_ceil(PART_BYTES).max(1);
+    (0..count)
+        .map(|i| {
+            let chunk = &data[i * PART_BYTES..((i + 1) * PART_BYTES).min(data.len())];
+            format!(
+                "{PART_PREFIX}-{}-{count}-{checksum}-{}",
+                i + 1,
+                data_encoding::BASE32_NOPAD.encode(chunk)
+            )
+        })
+        .collect()
+}
+
+/// Reassemble an encrypted backup from scanned QR parts, in any order.
+/// Scanning a part twice is harmless.
+pub fn assemble_backup(parts: &[String]) -> Result<Vec<u8>, String> {
+    let mut set: Option<(usize, String)> = None;
+    let mut chunks = BTreeMap::new();
+    for part in parts {
+        let part = part.trim().to_uppercase();
+        let fields: Vec<&str> = part.splitn(5, '-').collect();
+        let [PART_PREFIX, index, count, checksum, data] = fields[..] else {
+            return Err("Not a Ghost Auth paper backup code".to_string());
+        };
+        let index: usize = index
+            .parse()
+            .map_err(|_| "Invalid paper backup code".to_string())?;
+        let count: usize = count
+            .parse()
+            .map_err(|_| "Invalid paper backup code".to_string())?;
+        if index == 0 || index > count {
+            return Err("Invalid paper backup code".to_string());
+        }
+        match &set {
+            None => set = Some((count, checksum.to_string())),
+            Some((c, sum)) if *c != count || sum != checksum => {
+                return Err("These codes come from different paper backups".to_string());
+            }
+            Some(_) => {}
+        }
+        let chunk = data_encoding::BASE32_NOPAD
+            .decode(data.as_bytes())
+            .map_err(|_| format!("Part {index} is damaged, scan it again"))?;
+        chunks.insert(index, chunk);
+    }
+
+    let Some((count, checksum)) = set else {
+        return Err("Scan the encrypted backup codes".to_string());
+    };
+    let missing: Vec<String> = (1..=count)
+        .filter(|i| !chunks.contains_key(i))
+        .map(|i| i.to_string())
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!(
+            "Missing part{} {} of {count}",
+            if missing.len() == 1 { "" } else { "s" },
+            missing.join(", ")
+        ));
+    }
+
+    let data: Vec<u8> = chunks.into_values().flatten().collect();
+    if blob_checksum(&data) != checksum {
+        return Err("The scanned codes don't match their checksum, scan them again".to_string());
+    }
+    Ok(data)
+}
+
+fn blob_checksum(data: &[u8]) -> String {
+    data_encoding::HEXUPPER.encode(&Sha256::digest(data)[..4])
+}
+
+fn qr_svg(data: &str, level: EcLevel) -> Result<String, String> {
+    let code = QrCode::with_error_correction_level(data.as_bytes(), level).map_err(|e| {
+        tracing::error!(error = %e, "Failed to encode QR code");
+        "Failed to generate QR code".to_string()
+    })?;
+    let image = code.render::<svg::Color>().quiet_zone(true).build();
+    // Drop the XML declaration so the SVG can be inlined in HTML
+    Ok(match image.find("<svg") {
+        Some(start) => image[start..].to_string(),
+        None => image,
+    })
+}
+
+/// Base32 in groups of four, easier to read back and type in by hand.
+fn group_secret(secret: &str) -> String {
+    let normalized = totp::decode_secret(secret)
+        .map(|bytes| data_encoding::BASE32_NOPAD.encode(&bytes))
+        .unwrap_or_else(|| secret.to_string());
+    normalized
+        .as_bytes()
+        .chunks(4)
+        .map(|group| String::from_utf8_lossy(group).into_owned())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn format_timestamp(secs: u64) -> String {
+    let (year, month, day) = civil_date(secs / DAY_SECS);
+    let minutes = secs % DAY_SECS / 60;
+    format!(
+        "{year}-{month:02}-{day:02} {:02}:{:02} UTC",
+        minutes / 60,
+        minutes % 60
+    )
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backup::{self, BackupContents, KdfPreset};
+
+    fn account(id: &str, issuer: &str, secret: &str) -> Account {
+        Account {
+            id: id.into(),
+            issuer: issuer.into(),
+            label: format!("{id}@example.com"),
+            secret: secret.into(),
+            algorithm: "SHA1".into(),
+            digits: 6,
+            period: 30,
+            icon: None,
+            last_modified: 1700000000,
+            tags: vec![],
+        }
+    }
+
+    fn sample_accounts() -> Vec<Account> {
+        vec![
+            account("alice", "GitHub", "JBSWY3DPEHPK3PXP"),
+            account("bob", "<Evil> & Co", "GEZDGNBVGY3TQOJQ"),
+        ]
+    }
+
+    #[test]
+    fn test_sheet_contents() {
+        let sheet = generate(&sample_accounts(), None, 1_709_208_000).unwrap();
+        assert_eq!(sheet.account_count, 2);
+        assert_eq!(sheet.backup_parts, 0);
+        assert!(sheet.html.contains("Generated 2024-02-29 12:00 UTC"));
+        assert!(sheet.html.contains(&sheet.checksum));
+        assert!(sheet.html.contains("JBSW Y3DP EHPK 3PXP"));
+        assert_eq!(sheet.html.matches("<svg").count(), 2);
+        assert!(!sheet.html.contains("<?xml"));
+        assert!(!sheet.html.contains("Encrypted backup"));
+        // Issuer and label are escaped
+        assert!(sheet.html.contains("&lt;Evil&gt; &amp; Co"));
+        assert!(!sheet.html.contains("<Evil>"));
+    }
+
+    #[test]
+    fn test_checksum_ignores_order_and_formatting() {
+        let accounts = sample_accounts();
+        let checksum = accounts_checksum(&accounts);
+        assert_eq!(checksum.len(), 19);
+        assert_eq!(checksum.matches('-').count(), 3);
+
+        let mut restored: Vec<Account> = accounts.iter().rev().cloned().collect();
+        restored[0].id = "new-id".into();
+        restored[1].secret = "jbsw y3dp ehpk 3pxp".into();
+        assert_eq!(accounts_checksum(&restored), checksum);
+
+        restored[1].digits = 8;
+        assert_ne!(accounts_checksum(&restored), checksum);
+        assert_ne!(accounts_checksum(&accounts[..1]), checksum);
+    }
+
+    #[test]
+    fn test_encrypted_backup_parts_roundtrip() {
+        let contents = BackupContents {
+            accounts: (0..40)
+                .map(|i| account(&format!("user{i}"), "Service", "JBSWY3DPEHPK3PXP"))
+                .collect(),
+            ..Default::default()
+        };
+        let data = backup::export_backup(&contents, "password1234", KdfPreset::Standard).unwrap();
+        let parts = split_backup(&data);
+        assert!(parts.len() > 1);
+        for part in &parts {
+            assert!(part
+                .chars()
+                .all(|c| c.is_ascii_digit() || c.is_ascii_uppercase() || c == '-'));
+        }
+
+        let mut scanned: Vec<String> = parts.iter().rev().cloned().collect();
+        scanned.push(parts[0].clone());
+        let assembled = assemble_backup(&scanned).unwrap();
+        assert_eq!(assembled, data);
+        let restored = backup::import_backup(&assembled, "password1234").unwrap();
+        assert_eq!(restored.accounts.len(), 40);
+
+        let sheet = generate(&contents.accounts[..1], Some(&data), 0).unwrap();
+        assert_eq!(sheet.backup_parts, parts.len());
+        assert_eq!(sheet.html.matches("<svg").count(), 1 + parts.len());
+        assert!(sheet.html.contains(&format!("Part 1 of {}", parts.len())));
+    }
+
+    #[test]
+    fn test_assemble_reports_missing_and_mixed_parts() {
+        let data: Vec<u8> = (0..2000).map(|i| i as u8).collect();
+        let parts = split_backup(&data);
+        assert_eq!(parts.len(), 3);
+
+        let err = assemble_backup(&parts[1..2]).unwrap_err();
+        assert_eq!(err, "Missing parts 1, 3 of 3");
+
+        let other = split_backup(&[1, 2, 3]);
+        let err = assemble_backup(&[parts[0].clone(), other[0].clone()]).unwrap_err();
+        assert!(err.contains("different paper backups"));
+
+        assert!(assemble_backup(&["GHOST-SHARE-1-AB".into()]).is_err());
+        assert!(assemble_backup(&[]).is_err());
+    }
+}
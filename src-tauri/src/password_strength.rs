@@ -0,0 +1,673 @@
+//! Password strength estimation for backup passwords, in the spirit of
+//! zxcvbn: the password is split into the cheapest sequence of guessable
+//! patterns (common passwords and words, sequences, repeats, keyboard rows,
+//! years), and the estimated number of guesses is mapped to a 0–4 score.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+/// Minimum password length for anything that encrypts the vault.
+pub const MIN_PASSWORD_LEN: usize = 8;
+pub const MAX_SCORE: u8 = 4;
+/// Default minimum score for backup passwords: "safely unguessable".
+pub const DEFAULT_MIN_SCORE: u8 = 3;
+
+/// Only this many characters are matched against patterns; the rest count
+/// as random characters.
+const MAX_ANALYZED_LEN: usize = 100;
+/// Any pattern costs at least this many guesses (log10).
+const MIN_PATTERN_GUESSES_LOG10: f64 = 1.0;
+
+/// Most common passwords, in order.
+const COMMON_PASSWORDS: &[&str] = &[
+    "password",
+    "123456",
+    "123456789",
+    "12345678",
+    "12345",
+    "qwerty",
+    "abc123",
+    "football",
+    "1234567",
+    "monkey",
+    "111111",
+    "letmein",
+    "1234",
+    "1234567890",
+    "dragon",
+    "baseball",
+    "sunshine",
+    "iloveyou",
+    "trustno1",
+    "princess",
+    "admin",
+    "welcome",
+    "666666",
+    "master",
+    "michael",
+    "shadow",
+    "superman",
+    "qazwsx",
+    "123123",
+    "654321",
+    "ashley",
+    "bailey",
+    "mustang",
+    "access",
+    "hello",
+    "charlie",
+    "donald",
+    "freedom",
+    "whatever",
+    "starwars",
+    "batman",
+    "jordan",
+    "hunter",
+    "killer",
+    "soccer",
+    "hockey",
+    "ranger",
+    "harley",
+    "thomas",
+    "robert",
+    "jennifer",
+    "daniel",
+    "jessica",
+    "pepper",
+    "zxcvbnm",
+    "asdfgh",
+    "computer",
+    "matrix",
+    "secret",
+    "summer",
+    "winter",
+    "flower",
+    "cheese",
+    "orange",
+    "buster",
+    "ginger",
+    "cookie",
+    "lovely",
+    "loveme",
+    "google",
+    "apple",
+    "samsung",
+    "maggie",
+    "tigger",
+    "pokemon",
+    "naruto",
+    "liverpool",
+    "chelsea",
+    "arsenal",
+    "chocolate",
+    "butterfly",
+    "qwertyuiop",
+    "1q2w3e4r",
+    "1qaz2wsx",
+    "zaq12wsx",
+    "internet",
+    "changeme",
+    "default",
+    "test",
+    "guest",
+    "root",
+    "login",
+    "pass",
+    "nothing",
+    "passpass",
+    "qwerty123",
+    "password1",
+    "abcd1234",
+    "aa123456",
+    "777777",
+    "121212",
+    "000000",
+    "987654321",
+];
+
+/// Common English words, roughly by frequency.
+const COMMON_WORDS: &[&str] = &[
+    "the", "and", "you", "that", "was", "for", "are", "with", "his", "they", "this", "have",
+    "from", "one", "had", "word", "but", "not", "what", "all", "were", "when", "your", "can",
+    "said", "there", "use", "each", "which", "she", "how", "their", "will", "other", "about",
+    "out", "many", "then", "them", "these", "some", "her", "would", "make", "like", "him", "into",
+    "time", "has", "look", "two", "more", "write", "see", "number", "way", "could", "people",
+    "than", "first", "water", "been", "call", "who", "now", "find", "long", "down", "day", "did",
+    "get", "come", "made", "may", "part", "house", "home", "family", "money", "world", "life",
+    "good", "great", "little", "work", "year", "back", "name", "very", "just", "know", "take",
+    "place", "live", "love", "girl", "boy", "baby", "blue", "red", "green", "black", "white",
+    "happy", "dog", "cat", "fish", "bird", "horse", "star", "moon", "sun", "fire", "king", "queen",
+    "game", "music", "magic", "angel", "devil", "heaven", "secure", "private", "mobile", "phone",
+    "email", "account", "user", "company", "office", "welcome", "hello",
+];
+
+/// Words anyone targeting this app's backups would try first.
+const APP_WORDS: &[&str] = &[
+    "ghost",
+    "auth",
+    "ghostauth",
+    "authenticator",
+    "backup",
+    "totp",
+    "vault",
+    "twofactor",
+    "2fa",
+    "otp",
+];
+
+const KEYBOARD_ROWS: &[&str] = &[
+    "`1234567890-=",
+    "qwertyuiop[]\\",
+    "asdfghjkl;'",
+    "zxcvbnm,./",
+    "qwertzuiop",
+    "azertyuiop",
+    "1qaz2wsx3edc4rfv5tgb6yhn7ujm8ik9ol0p",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    CommonPassword,
+    Word,
+    AppWord,
+    Sequence,
+    Repeat,
+    Keyboard,
+    Year,
+    Bruteforce,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Match {
+    start: usize,
+    end: usize,
+    guesses_log10: f64,
+    kind: Kind,
+    l33t: bool,
+    uppercase: bool,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct PasswordStrength {
+    /// 0 (trivially guessable) to 4 (very strong).
+    pub score: u8,
+    /// Estimated guesses needed to crack the password, as a power of ten.
+    pub guesses_log10: f64,
+    pub warning: Option<String>,
+    pub suggestions: Vec<String>,
+}
+
+/// Estimate how hard `password` is to guess.
+pub fn estimate(password: &str) -> PasswordStrength {
+    let chars: Vec<char> = password.chars().collect();
+    let analyzed = &chars[..chars.len().min(MAX_ANALYZED_LEN)];
+    let (mut guesses_log10, sequence) = cheapest_sequence(analyzed);
+    guesses_log10 += chars[analyzed.len()..]
+        .iter()
+        .map(|&c| cardinality(c).log10())
+        .sum::<f64>();
+
+    let score = match guesses_log10 {
+        g if g < 3.0 => 0,
+        g if g < 6.0 => 1,
+        g if g < 8.0 => 2,
+        g if g < 10.0 => 3,
+        _ => 4,
+    };
+    let (warning, suggestions) = feedback(&sequence, chars.len(), score);
+    PasswordStrength {
+        score,
+        guesses_log10,
+        warning,
+        suggestions,
+    }
+}
+
+/// Enforce the length floor and a minimum strength score on a password
+/// that will encrypt the vault.
+pub fn check_policy(password: &str, min_score: u8) -> Result<(), String> {
+    if password.chars().count() < MIN_PASSWORD_LEN {
+        return Err(format!(
+            "Backup password must be at least {MIN_PASSWORD_LEN} characters"
+        ));
+    }
+    if min_score == 0 {
+        return Ok(());
+    }
+    let strength = estimate(password);
+    if strength.score < min_score {
+        let reason = strength
+            .warning
+            .unwrap_or_else(|| "It would be easy to guess".to_string());
+        return Err(format!("Backup password is too weak: {reason}"));
+    }
+    Ok(())
+}
+
+/// The minimum-guesses split of `chars` into patterns (dynamic programming
+/// over every pattern match, with single random characters as fallback).
+fn cheapest_sequence(chars: &[char]) -> (f64, Vec<Match>) {
+    let n = chars.len();
+    if n == 0 {
+        return (0.0, Vec::new());
+    }
+    let mut by_end: Vec<Vec<Match>> = vec![Vec::new(); n + 1];
+    for m in find_matches(chars) {
+        by_end[m.end].push(m);
+    }
+
+    let mut best = vec![f64::INFINITY; n + 1];
+    let mut choice: Vec<Option<Match>> = vec![None; n + 1];
+    best[0] = 0.0;
+    for end in 1..=n {
+        for m in &by_end[end] {
+            let total = best[m.start] + m.guesses_log10;
+            if total < best[end] {
+                best[end] = total;
+                choice[end] = Some(*m);
+            }
+        }
+    }
+
+    let mut sequence = Vec::new();
+    let mut end = n;
+    while let Some(m) = choice[end] {
+        sequence.push(m);
+        end = m.start;
+        if end == 0 {
+            break;
+        }
+    }
+    sequence.reverse();
+    (best[n], sequence)
+}
+
+fn find_matches(chars: &[char]) -> Vec<Match> {
+    let lower: Vec<char> = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+    let mut matches: Vec<Match> = chars
+        .iter()
+        .enumerate()
+        .map(|(i, &c)| Match {
+            start: i,
+            end: i + 1,
+            guesses_log10: cardinality(c).log10(),
+            kind: Kind::Bruteforce,
+            l33t: false,
+            uppercase: false,
+        })
+        .collect();
+    dictionary_matches(chars, &lower, &mut matches);
+    sequence_matches(&lower, &mut matches);
+    repeat_matches(chars, &mut matches);
+    keyboard_matches(&lower, &mut matches);
+    year_matches(chars, &mut matches);
+    for m in &mut matches {
+        if m.kind != Kind::Bruteforce {
+            m.guesses_log10 = m.guesses_log10.max(MIN_PATTERN_GUESSES_LOG10);
+        }
+    }
+    matches
+}
+
+fn dictionary_matches(chars: &[char], lower: &[char], matches: &mut Vec<Match>) {
+    let mut ranked: HashMap<&str, (usize, Kind)> = HashMap::new();
+    let lists = [
+        (APP_WORDS, Kind::AppWord),
+        (COMMON_PASSWORDS, Kind::CommonPassword),
+        (COMMON_WORDS, Kind::Word),
+    ];
+    for (list, kind) in lists {
+        for (rank, word) in list.iter().enumerate() {
+            ranked.entry(word).or_insert((rank + 1, kind));
+        }
+    }
+
+    let longest = ranked.keys().map(|w| w.chars().count()).max().unwrap_or(0);
+    let n = chars.len();
+    for start in 0..n {
+        for end in start + 2..=n.min(start + longest) {
+            let token: String = lower[start..end].iter().collect();
+            let unl33t: String = token.chars().map(unl33t).collect();
+            let reversed: String = token.chars().rev().collect();
+            let candidates = [
+                (&token, 1.0, false),
+                (&unl33t, 1.0, true),
+                (&reversed, 2.0, false),
+            ];
+            let Some((rank, kind, factor, l33t)) = candidates
+                .into_iter()
+                .filter(|(word, _, l33t)| !*l33t || **word != token)
+                .find_map(|(word, factor, l33t)| {
+                    ranked
+                        .get(word.as_str())
+                        .map(|&(rank, kind)| (rank, kind, factor, l33t))
+                })
+            else {
+                continue;
+            };
+
+            let original = &chars[start..end];
+            let upper = original.iter().filter(|c| c.is_uppercase()).count();
+            let mut guesses = rank as f64 * factor;
+            if upper > 0 {
+                // First letter or all caps are the usual variations
+                let first_only = upper == 1 && original[0].is_uppercase();
+                guesses *= if first_only || upper == original.len() {
+                    2.0
+                } else {
+                    2f64.powi(upper.min(10) as i32)
+                };
+            }
+            if l33t {
+                let subs = token.chars().zip(unl33t.chars()).filter(|(a, b)| a != b);
+                guesses *= 2f64.powi(subs.count().min(10) as i32);
+            }
+            if kind == Kind::Word {
+                // The word lists are short; real attackers use far bigger ones
+                guesses *= 10.0;
+            }
+            matches.push(Match {
+                start,
+                end,
+                guesses_log10: guesses.log10(),
+                kind,
+                l33t,
+                uppercase: upper > 0,
+            });
+        }
+    }
+}
+
+fn unl33t(c: char) -> char {
+    match c {
+        '4' | '@' => 'a',
+        '3' => 'e',
+        '1' | '!' => 'i',
+        '0' => 'o',
+        '$' | '5' => 's',
+        '7' | '+' => 't',
+        _ => c,
+    }
+}
+
+/// Runs of 3+ characters stepping by a constant +1 or -1: "abc", "9876".
+fn sequence_matches(lower: &[char], matches: &mut Vec<Match>) {
+    let n = lower.len();
+    let mut start = 0;
+    while start + 2 < n {
+        let step = lower[start + 1] as i64 - lower[start] as i64;
+        if step.abs() != 1 || !lower[start].is_ascii_alphanumeric() {
+            start += 1;
+            continue;
+        }
+        let mut end = start + 2;
+        while end < n
+            && lower[end] as i64 - lower[end - 1] as i64 == step
+            && lower[end].is_ascii_alphanumeric()
+        {
+            end += 1;
+        }
+        if end - start >= 3 {
+            let first = lower[start];
+            let base: f64 = if matches!(first, 'a' | 'z' | '0' | '1' | '9') {
+                4.0
+            } else if first.is_ascii_digit() {
+                10.0
+            } else {
+                26.0
+            };
+            let direction = if step < 0 { 2.0 } else { 1.0 };
+            matches.push(Match {
+                start,
+                end,
+                guesses_log10: (base * (end - start) as f64 * direction).log10(),
+                kind: Kind::Sequence,
+                l33t: false,
+                uppercase: false,
+            });
+            start = end - 1;
+        } else {
+            start += 1;
+        }
+    }
+}
+
+/// Repeated blocks: "aaa", "abcabc", "1212". Only the smallest repeating
+/// block at each position is considered.
+fn repeat_matches(chars: &[char], matches: &mut Vec<Match>) {
+    let n = chars.len();
+    for start in 0..n {
+        for block in 1..=(n - start) / 2 {
+            let mut end = start + block;
+            while end + block <= n && chars[end..end + block] == chars[start..start + block] {
+                end += block;
+            }
+            let count = (end - start) / block;
+            if count < 2 || (block == 1 && count < 3) {
+                continue;
+            }
+            let (block_guesses, _) = cheapest_sequence(&chars[start..start + block]);
+            matches.push(Match {
+                start,
+                end,
+                guesses_log10: block_guesses + (count as f64).log10(),
+                kind: Kind::Repeat,
+                l33t: false,
+                uppercase: false,
+            });
+            break;
+        }
+    }
+}
+
+/// Runs of 4+ adjacent keys along a keyboard row, either direction.
+fn keyboard_matches(lower: &[char], matches: &mut Vec<Match>) {
+    let rows: Vec<Vec<char>> = KEYBOARD_ROWS.iter().map(|r| r.chars().collect()).collect();
+    let n = lower.len();
+    for start in 0..n {
+        let mut longest = 0;
+        for row in &rows {
+            for reversed in [false, true] {
+                let row: Vec<char> = if reversed {
+                    row.iter().rev().copied().collect()
+                } else {
+                    row.clone()
+                };
+                let Some(pos) = row.iter().position(|&c| c == lower[start]) else {
+                    continue;
+                };
+                let len = lower[start..]
+                    .iter()
+                    .zip(&row[pos..])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                longest = longest.max(len);
+            }
+        }
+        for end in start + 4..=start + longest {
+            let guesses = KEYBOARD_ROWS.len() as f64 * 2.0 * 10.0 * (end - start) as f64;
+            matches.push(Match {
+                start,
+                end,
+                guesses_log10: guesses.log10(),
+                kind: Kind::Keyboard,
+                l33t: false,
+                uppercase: false,
+            });
+        }
+    }
+}
+
+/// Four-digit years from 1900 to 2049.
+fn year_matches(chars: &[char], matches: &mut Vec<Match>) {
+    for start in 0..chars.len().saturating_sub(3) {
+        let token: String = chars[start..start + 4].iter().collect();
+        if let Ok(year) = token.parse::<u32>() {
+            if (1900..=2049).contains(&year) {
+                matches.push(Match {
+                    start,
+                    end: start + 4,
+                    guesses_log10: 150f64.log10(),
+                    kind: Kind::Year,
+                    l33t: false,
+                    uppercase: false,
+                });
+            }
+        }
+    }
+}
+
+fn cardinality(c: char) -> f64 {
+    if c.is_ascii_digit() {
+        10.0
+    } else if c.is_ascii_lowercase() || c.is_ascii_uppercase() {
+        26.0
+    } else if c.is_ascii() {
+        33.0
+    } else {
+        100.0
+    }
+}
+
+fn feedback(sequence: &[Match], len: usize, score: u8) -> (Option<String>, Vec<String>) {
+    if len == 0 {
+        return (
+            Some("Enter a password".to_string()),
+            vec!["Use a few unrelated words or a long passphrase".to_string()],
+        );
+    }
+    if score >= DEFAULT_MIN_SCORE && len >= MIN_PASSWORD_LEN {
+        return (None, Vec::new());
+    }
+
+    // The pattern covering the most characters explains the weakness best
+    let dominant = sequence
+        .iter()
+        .filter(|m| m.kind != Kind::Bruteforce)
+        .max_by_key(|m| m.end - m.start);
+    let whole = dominant.is_some_and(|m| m.start == 0 && m.end == len);
+    let warning = match dominant.map(|m| m.kind) {
+        Some(Kind::CommonPassword) if whole => "This is one of the most common passwords",
+        Some(Kind::CommonPassword) => "This is similar to a commonly used password",
+        Some(Kind::Word) if whole => "A single word is easy to guess",
+        Some(Kind::Word) => "Common words are easy to guess",
+        Some(Kind::AppWord) => "Avoid the app's name and related words",
+        Some(Kind::Sequence) => "Sequences like abc or 6543 are easy to guess",
+        Some(Kind::Repeat) => "Repeats like \"aaa\" or \"abcabc\" are easy to guess",
+        Some(Kind::Keyboard) => "Straight rows of keys are easy to guess",
+        Some(Kind::Year) => "Years are easy to guess",
+        Some(Kind::Bruteforce) | None if len < MIN_PASSWORD_LEN => "This password is too short",
+        Some(Kind::Bruteforce) | None => "This password could be guessed with enough tries",
+    };
+
+    let mut suggestions = vec!["Add another word or two. Uncommon words are better.".to_string()];
+    let patterns = |kinds: &[Kind]| sequence.iter().any(|m| kinds.contains(&m.kind));
+    if patterns(&[Kind::Sequence, Kind::Repeat, Kind::Keyboard, Kind::Year]) {
+        suggestions.push("Avoid sequences, repeats, keyboard patterns and dates".to_string());
+    }
+    if sequence.iter().any(|m| m.l33t) {
+        suggestions
+            .push("Predictable substitutions like '@' instead of 'a' don't help much".to_string());
+    }
+    if sequence.iter().any(|m| m.uppercase) {
+        suggestions.push("Capitalization doesn't help very much".to_string());
+    }
+    (Some(warning.to_string()), suggestions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_common_passwords_are_weak() {
+        for password in [
+            "password",
+            "12345678",
+            "qwertyuiop",
+            "iloveyou",
+            "Password1",
+        ] {
+            let strength = estimate(password);
+            assert!(strength.score <= 1, "{password}: {strength:?}");
+            assert!(strength.warning.is_some());
+        }
+        assert_eq!(
+            estimate("password").warning.as_deref(),
+            Some("This is one of the most common passwords")
+        );
+    }
+
+    #[test]
+    fn test_patterns_are_weak() {
+        let cases = [
+            ("abcdefghij", "Sequences"),
+            ("zzzzzzzzzz", "Repeats"),
+            ("asdfghjkl;", "rows of keys"),
+            ("p@$$w0rd", "common password"),
+            ("ghostauth2024", "app's name"),
+        ];
+        for (password, warning) in cases {
+            let strength = estimate(password);
+            assert!(strength.score <= 2, "{password}: {strength:?}");
+            assert!(
+                strength
+                    .warning
+                    .as_deref()
+                    .unwrap_or_default()
+                    .contains(warning),
+                "{password}: {strength:?}"
+            );
+        }
+        assert!(estimate("p@$$w0rd")
+            .suggestions
+            .iter()
+            .any(|s| s.contains("substitutions")));
+    }
+
+    #[test]
+    fn test_strong_passwords() {
+        for password in [
+            "correct horse battery staple",
+            "Tr0ub4dor&3-xylophone-quasar",
+            "mK9#vQ2$wL7!pZ4x",
+        ] {
+            let strength = estimate(password);
+            assert!(strength.score >= 3, "{password}: {strength:?}");
+            assert!(strength.warning.is_none());
+            assert!(strength.suggestions.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_score_grows_with_length() {
+        let short = estimate("kx9vq");
+        let long = estimate("kx9vqt7wmz2r");
+        assert!(long.guesses_log10 > short.guesses_log10);
+        assert!(long.score > short.score);
+        assert_eq!(estimate("").score, 0);
+    }
+
+    #[test]
+    fn test_policy() {
+        let err = check_policy("short", 0).unwrap_err();
+        assert!(err.contains("at least 8 characters"));
+        assert!(check_policy("password", 0).is_ok());
+        let err = check_policy("password", DEFAULT_MIN_SCORE).unwrap_err();
+        assert!(err.starts_with("Backup password is too weak"), "{err}");
+        assert!(check_policy("correct horse battery staple", DEFAULT_MIN_SCORE).is_ok());
+        assert!(check_policy("correct horse battery staple", MAX_SCORE).is_ok());
+    }
+
+    #[test]
+    fn test_long_passwords_are_bounded() {
+        let long = "a".repeat(1000);
+        let strength = estimate(&long);
+        assert!(strength.guesses_log10.is_finite());
+        assert!(strength.score <= 4);
+    }
+}
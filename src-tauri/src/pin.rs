@@ -1,6 +1,6 @@
 use argon2::{
     password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
-    Argon2,
+    Algorithm, Argon2, Params, Version,
 };
 use rand::Rng;
 use serde::{Deserialize, Serialize};
@@ -16,6 +16,42 @@ fn now_secs() -> u64 {
         .as_secs()
 }
 
+// Target Argon2id parameters for PIN and recovery-code hashes, matching the
+// backup Standard preset. Each hash records its own parameters, so raising
+// these only affects new hashes; a PIN hash is upgraded the next time it
+// verifies.
+const HASH_MEMORY_KIB: u32 = 64 * 1024;
+const HASH_ITERATIONS: u32 = 3;
+const HASH_PARALLELISM: u32 = 1;
+
+fn hasher() -> Argon2<'static> {
+    let params =
+        Params::new(HASH_MEMORY_KIB, HASH_ITERATIONS, HASH_PARALLELISM, None).unwrap_or_default();
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+}
+
+fn hash_secret(secret: &[u8]) -> Result<String, argon2::password_hash::Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    Ok(hasher().hash_password(secret, &salt)?.to_string())
+}
+
+/// Whether a stored hash is weaker than the current target parameters.
+fn needs_rehash(hash: &PasswordHash) -> bool {
+    if hash.algorithm != Algorithm::Argon2id.ident()
+        || hash.version != Some(u32::from(Version::V0x13))
+    {
+        return true;
+    }
+    match Params::try_from(hash) {
+        Ok(params) => {
+            params.m_cost() < HASH_MEMORY_KIB
+                || params.t_cost() < HASH_ITERATIONS
+                || params.p_cost() < HASH_PARALLELISM
+        }
+        Err(_) => true,
+    }
+}
+
 const RECOVERY_CODE_COUNT: usize = 8;
 // Excludes ambiguous characters: 0/O, 1/I/L
 const RECOVERY_CODE_CHARS: &[u8] = b"ABCDEFGHJKMNPQRSTUVWXYZ23456789";
@@ -114,15 +150,12 @@ impl PinManager {
     }
 
     pub fn set_pin(&self, pin: &str) -> Result<Vec<String>, String> {
-        let salt = SaltString::generate(&mut OsRng);
-        let argon2 = Argon2::default();
-
-        let hash = argon2.hash_password(pin.as_bytes(), &salt).map_err(|e| {
+        let hash = hash_secret(pin.as_bytes()).map_err(|e| {
             tracing::error!(error = %e, "Failed to hash PIN");
             "Failed to set PIN".to_string()
         })?;
 
-        write_restricted(&self.hash_path, &hash.to_string())?;
+        write_restricted(&self.hash_path, &hash)?;
 
         let codes = Self::generate_recovery_codes();
         self.store_recovery_hashes(&codes)?;
@@ -160,13 +193,14 @@ impl PinManager {
             "PIN data corrupted".to_string()
         })?;
 
-        let valid = Argon2::default()
-            .verify_password(pin.as_bytes(), &hash)
-            .is_ok();
+        let valid = hasher().verify_password(pin.as_bytes(), &hash).is_ok();
 
         if valid {
             tracing::info!(event = "pin_verified", "PIN verified successfully");
             rl.reset();
+            if needs_rehash(&hash) {
+                self.upgrade_pin_hash(pin);
+            }
         } else {
             rl.record_failure();
             tracing::warn!(
@@ -180,6 +214,18 @@ impl PinManager {
         Ok(valid)
     }
 
+    /// Rehash a just-verified PIN with the current parameters. Failure is
+    /// logged and otherwise ignored, since the old hash still works.
+    fn upgrade_pin_hash(&self, pin: &str) {
+        let result = hash_secret(pin.as_bytes())
+            .map_err(|e| e.to_string())
+            .and_then(|hash| write_restricted(&self.hash_path, &hash));
+        match result {
+            Ok(()) => tracing::info!(event = "pin_rehashed", "PIN hash upgraded"),
+            Err(e) => tracing::warn!(error = %e, "Failed to upgrade PIN hash"),
+        }
+    }
+
     /// Internal: removes PIN hash file without touching the rate limit lock.
     fn remove_pin_files(&self) -> Result<(), String> {
         if self.hash_path.exists() {
@@ -220,22 +266,15 @@ impl PinManager {
     }
 
     fn store_recovery_hashes(&self, codes: &[String]) -> Result<(), String> {
-        let argon2 = Argon2::default();
         let entries: Result<Vec<RecoveryCodeEntry>, String> = codes
             .iter()
             .map(|code| {
                 let normalized = code.replace('-', "").to_uppercase();
-                let salt = SaltString::generate(&mut OsRng);
-                let hash = argon2
-                    .hash_password(normalized.as_bytes(), &salt)
-                    .map_err(|e| {
-                        tracing::error!(error = %e, "Failed to hash recovery code");
-                        "Failed to generate recovery codes".to_string()
-                    })?;
-                Ok(RecoveryCodeEntry {
-                    hash: hash.to_string(),
-                    used: false,
-                })
+                let hash = hash_secret(normalized.as_bytes()).map_err(|e| {
+                    tracing::error!(error = %e, "Failed to hash recovery code");
+                    "Failed to generate recovery codes".to_string()
+                })?;
+                Ok(RecoveryCodeEntry { hash, used: false })
             })
             .collect();
 
@@ -248,6 +287,9 @@ impl PinManager {
         write_restricted(&self.recovery_path, &json)
     }
 
+    /// Check a recovery code and, if it matches, consume it and remove the
+    /// PIN. Consumed codes are never checked again, so their hashes are left
+    /// as they are; codes move to new parameters when they are regenerated.
     pub fn verify_recovery_code(&self, code: &str) -> Result<bool, String> {
         // Shared rate limiting with PIN
         let mut rl = self.rate_limit.lock().map_err(|_| {
@@ -279,7 +321,7 @@ impl PinManager {
         })?;
 
         let normalized = code.replace('-', "").to_uppercase();
-        let argon2 = Argon2::default();
+        let argon2 = hasher();
 
         let mut matched_idx: Option<usize> = None;
         for (i, entry) in store.codes.iter().enumerate() {
@@ -588,6 +630,71 @@ mod tests {
         assert!(result.unwrap_err().contains("Too many attempts"));
     }
 
+    fn weak_hash(secret: &str) -> String {
+        let params = Params::new(8 * 1024, 1, 1, None).unwrap();
+        let salt = SaltString::generate(&mut OsRng);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password(secret.as_bytes(), &salt)
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_verify_pin_upgrades_weak_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let pm = PinManager::new(dir.path().to_path_buf());
+        let hash_path = dir.path().join("pin.hash");
+        fs::write(&hash_path, weak_hash("1234")).unwrap();
+
+        // A wrong PIN leaves the hash alone
+        assert!(!pm.verify_pin("9999").unwrap());
+        let stored = fs::read_to_string(&hash_path).unwrap();
+        assert!(needs_rehash(&PasswordHash::new(&stored).unwrap()));
+
+        assert!(pm.verify_pin("1234").unwrap());
+        let upgraded = fs::read_to_string(&hash_path).unwrap();
+        assert!(!needs_rehash(&PasswordHash::new(&upgraded).unwrap()));
+        assert!(pm.verify_pin("1234").unwrap());
+        // Already current, so not rewritten again
+        assert_eq!(fs::read_to_string(&hash_path).unwrap(), upgraded);
+    }
+
+    #[test]
+    fn test_recovery_code_accepts_weak_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let pm = PinManager::new(dir.path().to_path_buf());
+        pm.set_pin("1234").unwrap();
+        let store = RecoveryStore {
+            codes: vec![RecoveryCodeEntry {
+                hash: weak_hash("ABCDEFGH"),
+                used: false,
+            }],
+        };
+        let recovery_path = dir.path().join("pin.recovery");
+        fs::write(&recovery_path, serde_json::to_string(&store).unwrap()).unwrap();
+
+        assert!(pm.verify_recovery_code("abcd-efgh").unwrap());
+        let store: RecoveryStore =
+            serde_json::from_str(&fs::read_to_string(&recovery_path).unwrap()).unwrap();
+        assert!(store.codes[0].used);
+        assert!(needs_rehash(
+            &PasswordHash::new(&store.codes[0].hash).unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_needs_rehash() {
+        assert!(needs_rehash(
+            &PasswordHash::new(&weak_hash("1234")).unwrap()
+        ));
+        // Hashes made with the argon2 crate defaults predate the current target
+        let salt = SaltString::generate(&mut OsRng);
+        let default = Argon2::default().hash_password(b"1234", &salt).unwrap();
+        assert!(needs_rehash(&default));
+        let current = hash_secret(b"1234").unwrap();
+        assert!(!needs_rehash(&PasswordHash::new(&current).unwrap()));
+    }
+
     #[test]
     fn test_has_recovery_codes_false_without_pin() {
         let dir = tempfile::tempdir().unwrap();
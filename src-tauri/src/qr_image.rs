@@ -0,0 +1,240 @@
+//! Reading QR codes from screenshots and photos, for desktops where the
+//! barcode scanner plugin has no camera to work with. Every code in the image
+//! is decoded; otpauth:// codes become accounts and Google Authenticator
+//! export codes are assembled into one import.
+
+use std::io::Cursor;
+
+use image::{ImageFormat, ImageReader, Limits};
+
+use crate::import::{self, ImportResult, SkipReason};
+
+/// Larger images are rejected before decoding.
+const MAX_DIMENSION: u32 = 8192;
+const MAX_ALLOC: u64 = 256 * 1024 * 1024;
+
+/// Whether `data` starts like a PNG, JPEG or WebP file.
+pub fn looks_like_image(data: &[u8]) -> bool {
+    matches!(
+        image::guess_format(data),
+        Ok(ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::WebP)
+    )
+}
+
+/// Decode every QR code in a PNG, JPEG or WebP image, in detection order and
+/// without repeats.
+pub fn decode(data: &[u8]) -> Result<Vec<String>, String> {
+    let mut reader = ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(|e| format!("Failed to read image: {e}"))?;
+    match reader.format() {
+        Some(ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::WebP) => {}
+        _ => return Err("Unsupported image format. Use a PNG, JPEG or WebP image.".to_string()),
+    }
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+    limits.max_alloc = Some(MAX_ALLOC);
+    reader.limits(limits);
+
+    let image = reader
+        .decode()
+        .map_err(|e| format!("Failed to decode image: {e}"))?
+        .to_luma8();
+    let mut prepared = rqrr::PreparedImage::prepare_from_greyscale(
+        image.width() as usize,
+        image.height() as usize,
+        |x, y| image.get_pixel(x as u32, y as u32).0[0],
+    );
+
+    let mut contents: Vec<String> = Vec::new();
+    for grid in prepared.detect_grids() {
+        match grid.decode() {
+            Ok((_, content)) => {
+                if !contents.contains(&content) {
+                    contents.push(content);
+                }
+            }
+            Err(e) => tracing::warn!(error = ?e, "Skipping unreadable QR code"),
+        }
+    }
+    if contents.is_empty() {
+        return Err("No QR code found in the image".to_string());
+    }
+    Ok(contents)
+}
+
+/// Import the accounts from every QR code in an image. Entries are numbered
+/// by code in the order found, then the accounts inside Google Authenticator
+/// export codes; codes without an account are reported as skipped.
+pub fn parse_image(data: &[u8]) -> Result<ImportResult, String> {
+    let contents = decode(data)?;
+
+    let mut result = ImportResult::new("QR code image");
+    let mut migration = Vec::new();
+    for (index, content) in contents.iter().enumerate() {
+        let content = content.trim();
+        if content.starts_with("otpauth-migration://") {
+            migration.push(content);
+        } else if content.starts_with("otpauth://") {
+            let (issuer, label) = import::uri_identity(content);
+            result.record(index, &issuer, &label, import::parse_totp_uri(content));
+        } else {
+            result.skip(index, "", "", SkipReason::NoTotp);
+        }
+    }
+
+    // All export codes of one screenshot go through the regular import path,
+    // which assembles them by batch
+    if !migration.is_empty() {
+        let exported = import::parse_import(migration.join("\n").as_bytes(), None)?;
+        result.append(exported);
+    }
+
+    if result.accounts.is_empty() && migration.is_empty() {
+        return Err("The QR code does not contain an authenticator account".to_string());
+    }
+    for account in &mut result.accounts {
+        crate::icons::apply(account);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::google_auth_proto;
+    use image::{GrayImage, Luma};
+    use prost::Message;
+
+    const SCALE: u32 = 4;
+    const QUIET: u32 = 4;
+
+    /// Render QR codes side by side onto one white image.
+    fn render(payloads: &[&str]) -> GrayImage {
+        let codes: Vec<qrcode::QrCode> = payloads
+            .iter()
+            .map(|p| qrcode::QrCode::new(p.as_bytes()).unwrap())
+            .collect();
+        let sizes: Vec<u32> = codes.iter().map(|c| c.width() as u32 + 2 * QUIET).collect();
+        let height = sizes.iter().max().unwrap() * SCALE;
+        let width = sizes.iter().sum::<u32>() * SCALE;
+        let mut image = GrayImage::from_pixel(width, height, Luma([255]));
+
+        let mut left = 0;
+        for (code, size) in codes.iter().zip(&sizes) {
+            let n = code.width();
+            for (i, color) in code.to_colors().into_iter().enumerate() {
+                if color != qrcode::Color::Dark {
+                    continue;
+                }
+                let mx = left + (QUIET + (i % n) as u32) * SCALE;
+                let my = (QUIET + (i / n) as u32) * SCALE;
+                for dy in 0..SCALE {
+                    for dx in 0..SCALE {
+                        image.put_pixel(mx + dx, my + dy, Luma([0]));
+                    }
+                }
+            }
+            left += size * SCALE;
+        }
+        image
+    }
+
+    fn encode(image: &GrayImage, format: ImageFormat) -> Vec<u8> {
+        let mut out = Cursor::new(Vec::new());
+        image.write_to(&mut out, format).unwrap();
+        out.into_inner()
+    }
+
+    fn migration_uri(batch_size: i32, batch_index: i32, issuer: &str) -> String {
+        let payload = google_auth_proto::MigrationPayload {
+            otp_parameters: vec![google_auth_proto::OtpParameters {
+                secret: b"Hello!".to_vec(),
+                name: format!("{issuer}:me"),
+                issuer: issuer.to_string(),
+                algorithm: google_auth_proto::Algorithm::Sha1 as i32,
+                digits: google_auth_proto::DigitCount::Six as i32,
+                otp_type: google_auth_proto::OtpType::Totp as i32,
+                counter: 0,
+            }],
+            version: 1,
+            batch_size,
+            batch_index,
+            batch_id: 4242,
+        };
+        let b64 = base64::Engine::encode(
+            &base64::engine::general_purpose::STANDARD,
+            payload.encode_to_vec(),
+        );
+        format!(
+            "otpauth-migration://offline?data={}",
+            b64.replace('+', "%2B")
+        )
+    }
+
+    const GITHUB_URI: &str = "otpauth://totp/GitHub:octocat?secret=JBSWY3DPEHPK3PXP&issuer=GitHub";
+
+    #[test]
+    fn test_decode_png_jpeg_and_webp() {
+        let image = render(&[GITHUB_URI]);
+        for format in [ImageFormat::Png, ImageFormat::Jpeg, ImageFormat::WebP] {
+            let data = encode(&image, format);
+            assert!(looks_like_image(&data), "{format:?}");
+            assert_eq!(decode(&data).unwrap(), vec![GITHUB_URI], "{format:?}");
+        }
+    }
+
+    #[test]
+    fn test_parse_image_otpauth_codes() {
+        let other = "otpauth://totp/alice%40example.com?secret=GEZDGNBVGY3TQOJQ&issuer=Example";
+        let data = encode(
+            &render(&[GITHUB_URI, other, "https://example.com"]),
+            ImageFormat::Png,
+        );
+        let result = parse_image(&data).unwrap();
+        assert_eq!(result.format, "QR code image");
+        assert_eq!(result.accounts.len(), 2);
+        assert_eq!(result.accounts[0].issuer, "GitHub");
+        assert_eq!(result.accounts[0].icon.as_deref(), Some("github"));
+        assert_eq!(result.accounts[1].label, "alice@example.com");
+        assert_eq!(result.skipped.len(), 1);
+        assert_eq!(result.skipped[0].index, 2);
+        assert_eq!(result.skipped[0].reason, SkipReason::NoTotp);
+    }
+
+    #[test]
+    fn test_parse_image_assembles_migration_batches() {
+        let first = migration_uri(2, 0, "First");
+        let second = migration_uri(2, 1, "Second");
+        let data = encode(&render(&[&second, &first]), ImageFormat::Png);
+        let result = parse_image(&data).unwrap();
+        let issuers: Vec<&str> = result.accounts.iter().map(|a| a.issuer.as_str()).collect();
+        assert_eq!(issuers, vec!["First", "Second"]);
+
+        // One code of a two-code export imports on its own, as when scanned
+        let data = encode(&render(&[&first]), ImageFormat::Png);
+        assert_eq!(parse_image(&data).unwrap().accounts.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_import_routes_images() {
+        let data = encode(&render(&[GITHUB_URI]), ImageFormat::Png);
+        let result = import::parse_import(&data, None).unwrap();
+        assert_eq!(result.format, "QR code image");
+        assert_eq!(result.accounts[0].label, "octocat");
+    }
+
+    #[test]
+    fn test_image_without_qr_code() {
+        let blank = GrayImage::from_pixel(64, 64, Luma([255]));
+        let err = parse_image(&encode(&blank, ImageFormat::Png)).unwrap_err();
+        assert_eq!(err, "No QR code found in the image");
+
+        let data = encode(&render(&["just some text"]), ImageFormat::Png);
+        let err = parse_image(&data).unwrap_err();
+        assert!(err.contains("does not contain"));
+
+        assert!(!looks_like_image(b"otpauth://totp/x?secret=JBSWY3DP"));
+    }
+}
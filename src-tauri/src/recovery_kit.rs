@@ -0,0 +1,457 @@
+//! Recovery kits: a backup whose key is split with Shamir secret sharing, so
+//! any `threshold` of the custodians' shares can open it, but fewer reveal
+//! nothing.
+//!
+//! A share is a printable string that also fits in an alphanumeric QR code:
+//! `GHOST-SHARE-1-<kit id>-<threshold>-<index>-<base32 share>-<checksum>`.
+//! The checksum catches typos and names the broken share on import.
+//!
+//! The key is split byte by byte over GF(256) with the 0x11d polynomial. Each
+//! share carries its x coordinate followed by one y value per key byte.
+
+use rand::{rngs::OsRng, RngCore};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use crate::backup::{self, BackupContents, KeySplit};
+
+const SHARE_PREFIX: &str = "GHOST-SHARE-1";
+const MAX_SHARES: u8 = 16;
+
+pub struct RecoveryKit {
+    pub kit_id: String,
+    pub threshold: u8,
+    pub shares: Vec<String>,
+    /// The encrypted GHST backup the shares unlock.
+    pub backup: Vec<u8>,
+}
+
+struct Share {
+    x: u8,
+    y: Zeroizing<Vec<u8>>,
+}
+
+struct ParsedShare {
+    kit_id: String,
+    threshold: u8,
+    share: Share,
+}
+
+/// Multiply in GF(256) without data-dependent branches or table lookups.
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    for _ in 0..8 {
+        product ^= a & (b & 1).wrapping_neg();
+        let carry = (a >> 7).wrapping_neg();
+        a = (a << 1) ^ (0x1d & carry);
+        b >>= 1;
+    }
+    product
+}
+
+/// Multiplicative inverse, as `a^254`. Only called with non-zero `a`.
+fn gf_inv(a: u8) -> u8 {
+    let mut result = 1;
+    let mut power = a;
+    for bit in 0..8 {
+        if 254 & (1 << bit) != 0 {
+            result = gf_mul(result, power);
+        }
+        power = gf_mul(power, power);
+    }
+    result
+}
+
+/// Coefficients of one polynomial per secret byte, lowest degree first:
+/// `coefficients[0]` is the secret and the rest are uniform over GF(256),
+/// zero included. Excluding zero would bias the shares and leak information
+/// about the secret to anyone holding fewer than `threshold` of them.
+fn random_coefficients(secret: &[u8], threshold: u8) -> Vec<Zeroizing<Vec<u8>>> {
+    let mut coefficients = vec![Zeroizing::new(secret.to_vec())];
+    for _ in 1..threshold {
+        let mut random = Zeroizing::new(vec![0u8; secret.len()]);
+        OsRng.fill_bytes(&mut random);
+        coefficients.push(random);
+    }
+    coefficients
+}
+
+/// Split `secret` into `share_count` shares with x = 1, 2, ...
+fn split_secret(secret: &[u8], threshold: u8, share_count: u8) -> Vec<Share> {
+    let coefficients = random_coefficients(secret, threshold);
+    (1..=share_count)
+        .map(|x| {
+            let y = (0..secret.len())
+                .map(|i| {
+                    coefficients
+                        .iter()
+                        .rev()
+                        .fold(0, |acc, c| gf_mul(acc, x) ^ c[i])
+                })
+                .collect();
+            Share {
+                x,
+                y: Zeroizing::new(y),
+            }
+        })
+        .collect()
+}
+
+/// Lagrange interpolation at x = 0. The shares must have distinct, non-zero
+/// x coordinates and equal lengths.
+fn combine_shares(shares: &[&Share]) -> Zeroizing<Vec<u8>> {
+    let len = shares[0].y.len();
+    let mut secret = Zeroizing::new(vec![0u8; len]);
+    for share in shares {
+        let basis = shares
+            .iter()
+            .filter(|other| other.x != share.x)
+            .fold(1, |acc, other| {
+                gf_mul(acc, gf_mul(other.x, gf_inv(other.x ^ share.x)))
+            });
+        for (byte, y) in secret.iter_mut().zip(share.y.iter()) {
+            *byte ^= gf_mul(basis, *y);
+        }
+    }
+    secret
+}
+
+/// Encrypt `contents` with a random key and split the key into `share_count`
+/// shares, any `threshold` of which recover it.
+pub fn create_kit(
+    contents: &BackupContents,
+    threshold: u8,
+    share_count: u8,
+) -> Result<RecoveryKit, String> {
+    if !(2..=MAX_SHARES).contains(&share_count) {
+        return Err(format!(
+            "A recovery kit needs between 2 and {MAX_SHARES} shares"
+        ));
+    }
+    if !(2..=share_count).contains(&threshold) {
+        return Err(format!(
+            "The number of shares needed must be between 2 and {share_count}"
+        ));
+    }
+
+    let mut key = Zeroizing::new([0u8; 32]);
+    OsRng.fill_bytes(&mut *key);
+    let mut id = [0u8; 4];
+    OsRng.fill_bytes(&mut id);
+    let kit_id = data_encoding::HEXUPPER.encode(&id);
+
+    let split = KeySplit {
+        kit_id: kit_id.clone(),
+        threshold,
+        share_count,
+    };
+    let backup = backup::export_backup_with_split_key(contents, &key, &split)?;
+    let shares = split_secret(key.as_slice(), threshold, share_count)
+        .iter()
+        .map(|share| format_share(&kit_id, threshold, share))
+        .collect();
+
+    Ok(RecoveryKit {
+        kit_id,
+        threshold,
+        shares,
+        backup,
+    })
+}
+
+/// Open a recovery kit backup with at least `threshold` of its shares.
+pub fn open_kit(data: &[u8], shares: &[String]) -> Result<BackupContents, String> {
+    let (kit_id, key) = recover_key(shares)?;
+    backup::import_backup_with_split_key(data, &kit_id, &key)
+}
+
+fn format_share(kit_id: &str, threshold: u8, share: &Share) -> String {
+    let mut bytes = Zeroizing::new(Vec::with_capacity(1 + share.y.len()));
+    bytes.push(share.x);
+    bytes.extend_from_slice(&share.y);
+    let body = format!(
+        "{SHARE_PREFIX}-{kit_id}-{threshold}-{}-{}",
+        share.x,
+        data_encoding::BASE32_NOPAD.encode(&bytes)
+    );
+    let checksum = checksum(&body);
+    format!("{body}-{checksum}")
+}
+
+fn checksum(body: &str) -> String {
+    data_encoding::HEXUPPER.encode(&Sha256::digest(body.as_bytes())[..4])
+}
+
+fn parse_share(input: &str) -> Result<ParsedShare, String> {
+    // Shares are often re-typed from paper, possibly across several lines
+    let normalized: String = input
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_uppercase();
+    let body = normalized
+        .strip_prefix(SHARE_PREFIX)
+        .and_then(|rest| rest.strip_prefix('-'))
+        .ok_or_else(|| "not a Ghost Auth recovery share".to_string())?;
+
+    let parts: Vec<&str> = body.split('-').collect();
+    let [kit_id, threshold, index, data, check] = parts[..] else {
+        return Err("incomplete share — check that nothing was cut off".to_string());
+    };
+    let (signed, _) = normalized
+        .rsplit_once('-')
+        .ok_or_else(|| "incomplete share".to_string())?;
+    if checksum(signed) != check {
+        return Err("checksum mismatch — check for typos".to_string());
+    }
+
+    let threshold: u8 = threshold
+        .parse()
+        .map_err(|_| "invalid threshold".to_string())?;
+    let index: u8 = index
+        .parse()
+        .map_err(|_| "invalid share number".to_string())?;
+    let bytes = Zeroizing::new(
+        data_encoding::BASE32_NOPAD
+            .decode(data.as_bytes())
+            .map_err(|_| "invalid share data".to_string())?,
+    );
+    if bytes.len() != 33 || index == 0 || bytes[0] != index {
+        return Err("invalid share data".to_string());
+    }
+    let share = Share {
+        x: index,
+        y: Zeroizing::new(bytes[1..].to_vec()),
+    };
+
+    Ok(ParsedShare {
+        kit_id: kit_id.to_string(),
+        threshold,
+        share,
+    })
+}
+
+/// Check every share and recover the kit id and key. Errors name shares by
+/// their position in `shares`, starting at 1.
+fn recover_key(shares: &[String]) -> Result<(String, Zeroizing<[u8; 32]>), String> {
+    let mut parsed = Vec::new();
+    let mut errors = Vec::new();
+    for (i, share) in shares.iter().enumerate() {
+        match parse_share(share) {
+            Ok(share) => parsed.push((i + 1, share)),
+            Err(e) => errors.push(format!("Share {}: {e}", i + 1)),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors.join("; "));
+    }
+    let Some((_, first)) = parsed.first() else {
+        return Err("Enter the custodians' recovery shares".to_string());
+    };
+
+    for (position, share) in &parsed[1..] {
+        if share.kit_id != first.kit_id || share.threshold != first.threshold {
+            errors.push(format!(
+                "Share {position}: belongs to a different recovery kit than share 1"
+            ));
+        } else if let Some((earlier, _)) = parsed
+            .iter()
+            .take_while(|(p, _)| p < position)
+            .find(|(_, other)| other.share.x == share.share.x)
+        {
+            errors.push(format!("Share {position}: same share as share {earlier}"));
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors.join("; "));
+    }
+    if parsed.len() < first.threshold as usize {
+        return Err(format!(
+            "This recovery kit needs {} shares, only {} entered",
+            first.threshold,
+            parsed.len()
+        ));
+    }
+
+    let shares: Vec<&Share> = parsed.iter().map(|(_, p)| &p.share).collect();
+    let recovered = combine_shares(&shares);
+    let mut key = Zeroizing::new([0u8; 32]);
+    key.copy_from_slice(&recovered);
+    Ok((first.kit_id.clone(), key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Account;
+
+    fn sample_contents() -> BackupContents {
+        BackupContents {
+            accounts: vec![Account {
+                id: "a1".into(),
+                issuer: "Team AWS".into(),
+                label: "root@example.com".into(),
+                secret: "JBSWY3DPEHPK3PXP".into(),
+                algorithm: "SHA1".into(),
+                digits: 6,
+                period: 30,
+                icon: None,
+                last_modified: 1700000000,
+                tags: vec![],
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_any_threshold_shares_open_kit() {
+        let kit = create_kit(&sample_contents(), 3, 5).unwrap();
+        assert_eq!(kit.shares.len(), 5);
+
+        for combo in [[0, 1, 2], [4, 2, 0], [1, 3, 4]] {
+            let shares: Vec<String> = combo.iter().map(|&i| kit.shares[i].clone()).collect();
+            let contents = open_kit(&kit.backup, &shares).unwrap();
+            assert_eq!(contents.accounts[0].issuer, "Team AWS");
+        }
+        // More than the threshold works too
+        assert!(open_kit(&kit.backup, &kit.shares).is_ok());
+    }
+
+    #[test]
+    fn test_too_few_shares() {
+        let kit = create_kit(&sample_contents(), 3, 5).unwrap();
+        let err = open_kit(&kit.backup, &kit.shares[..2]).unwrap_err();
+        assert!(err.contains("needs 3 shares"));
+        assert!(open_kit(&kit.backup, &[]).is_err());
+    }
+
+    #[test]
+    fn test_share_format_is_printable() {
+        let kit = create_kit(&sample_contents(), 2, 3).unwrap();
+        for (i, share) in kit.shares.iter().enumerate() {
+            assert!(share.starts_with(&format!("GHOST-SHARE-1-{}-2-{}-", kit.kit_id, i + 1)));
+            // QR alphanumeric mode: digits, upper case and '-'
+            assert!(share
+                .chars()
+                .all(|c| c.is_ascii_digit() || c.is_ascii_uppercase() || c == '-'));
+        }
+    }
+
+    #[test]
+    fn test_shares_tolerate_case_and_whitespace() {
+        let kit = create_kit(&sample_contents(), 2, 3).unwrap();
+        let (head, tail) = kit.shares[0].split_at(30);
+        let retyped = vec![
+            format!("  {}\n{} ", head.to_lowercase(), tail),
+            kit.shares[2].clone(),
+        ];
+        assert!(open_kit(&kit.backup, &retyped).is_ok());
+    }
+
+    #[test]
+    fn test_malformed_share_is_named() {
+        let kit = create_kit(&sample_contents(), 2, 3).unwrap();
+        let mut typo = kit.shares[1].clone();
+        let pos = typo.len() - 20;
+        let replacement = if &typo[pos..pos + 1] == "A" { "B" } else { "A" };
+        typo.replace_range(pos..pos + 1, replacement);
+
+        let err = open_kit(&kit.backup, &[kit.shares[0].clone(), typo]).unwrap_err();
+        assert!(err.starts_with("Share 2: checksum mismatch"), "{err}");
+
+        let err = open_kit(
+            &kit.backup,
+            &[
+                kit.shares[0].clone(),
+                "hello".into(),
+                "GHOST-SHARE-1-AB".into(),
+            ],
+        )
+        .unwrap_err();
+        assert!(
+            err.contains("Share 2: not a Ghost Auth recovery share"),
+            "{err}"
+        );
+        assert!(err.contains("Share 3: incomplete share"), "{err}");
+    }
+
+    #[test]
+    fn test_duplicate_and_foreign_shares_are_named() {
+        let kit = create_kit(&sample_contents(), 2, 3).unwrap();
+        let other = create_kit(&sample_contents(), 2, 3).unwrap();
+
+        let err =
+            open_kit(&kit.backup, &[kit.shares[0].clone(), kit.shares[0].clone()]).unwrap_err();
+        assert_eq!(err, "Share 2: same share as share 1");
+
+        let err = open_kit(
+            &kit.backup,
+            &[kit.shares[0].clone(), other.shares[1].clone()],
+        )
+        .unwrap_err();
+        assert!(err.contains("Share 2: belongs to a different recovery kit"));
+
+        // A complete set from another kit can't open this backup
+        let err = open_kit(&kit.backup, &other.shares[..2]).unwrap_err();
+        assert!(err.contains("different recovery kit"));
+    }
+
+    #[test]
+    fn test_kit_backup_needs_shares() {
+        let kit = create_kit(&sample_contents(), 2, 2).unwrap();
+        let err = backup::import_backup(&kit.backup, "password1234").unwrap_err();
+        assert!(err.contains("recovery kit"));
+        assert_eq!(
+            backup::key_split(&kit.backup).unwrap(),
+            Some(KeySplit {
+                kit_id: kit.kit_id.clone(),
+                threshold: 2,
+                share_count: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn test_gf256_arithmetic() {
+        assert_eq!(gf_mul(0, 0x53), 0);
+        assert_eq!(gf_mul(1, 0x53), 0x53);
+        assert_eq!(gf_mul(2, 0x80), 0x1d);
+        for a in 1..=255 {
+            assert_eq!(gf_mul(a, gf_inv(a)), 1, "{a}");
+        }
+    }
+
+    #[test]
+    fn test_coefficients_can_be_zero() {
+        // 15 random coefficients per byte of a 32-byte key; a sampler that
+        // skipped zero would never produce one
+        let zero_seen = (0..20).any(|_| {
+            random_coefficients(&[0xAB; 32], 16)[1..]
+                .iter()
+                .any(|c| c.contains(&0))
+        });
+        assert!(zero_seen);
+
+        let coefficients = random_coefficients(&[0xAB; 32], 3);
+        assert_eq!(coefficients.len(), 3);
+        assert_eq!(coefficients[0].as_slice(), &[0xAB; 32]);
+    }
+
+    #[test]
+    fn test_split_and_combine() {
+        let secret = [0x42u8; 32];
+        let shares = split_secret(&secret, 3, 5);
+        let pick = |idx: &[usize]| -> Vec<&Share> { idx.iter().map(|&i| &shares[i]).collect() };
+        assert_eq!(combine_shares(&pick(&[0, 1, 2])).as_slice(), &secret);
+        assert_eq!(combine_shares(&pick(&[4, 0, 3])).as_slice(), &secret);
+        assert_eq!(combine_shares(&pick(&[0, 1, 2, 3, 4])).as_slice(), &secret);
+        assert_ne!(combine_shares(&pick(&[0, 1])).as_slice(), &secret);
+    }
+
+    #[test]
+    fn test_kit_parameter_bounds() {
+        assert!(create_kit(&sample_contents(), 1, 3).is_err());
+        assert!(create_kit(&sample_contents(), 4, 3).is_err());
+        assert!(create_kit(&sample_contents(), 2, MAX_SHARES + 1).is_err());
+    }
+}
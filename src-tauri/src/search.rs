@@ -0,0 +1,473 @@
+//! Typo-tolerant account search with ranking and match highlighting.
+//!
+//! Matches each query term against an account's issuer, label, tags, and
+//! domain. Matching is case-insensitive and ignores common Latin diacritics,
+//! so "zurich" finds "Zürich". Every term has to match somewhere; results are
+//! ranked by match quality with a boost for frequently used accounts.
+
+use serde::Serialize;
+
+use crate::storage::Account;
+
+const QUALITY_EXACT: u32 = 100;
+const QUALITY_PREFIX: u32 = 90;
+const QUALITY_WORD_PREFIX: u32 = 80;
+const QUALITY_SUBSTRING: u32 = 60;
+const QUALITY_TYPO: u32 = 50;
+/// Quality lost per edit in a typo match.
+const TYPO_PENALTY: u32 = 15;
+
+/// Field weights in percent.
+const WEIGHT_ISSUER: u32 = 100;
+const WEIGHT_TAG: u32 = 90;
+const WEIGHT_LABEL: u32 = 80;
+const WEIGHT_DOMAIN: u32 = 70;
+
+/// Upper bound on the ranking boost from usage. Kept below the gap between
+/// quality tiers so usage reorders similar matches but never outranks a
+/// better kind of match.
+const MAX_USAGE_BOOST: u32 = 9;
+
+/// A matched range within one searchable field of an account.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Highlight {
+    /// "issuer", "label", "tag", or "domain".
+    pub field: String,
+    /// Index into the account's tags when `field` is "tag".
+    pub tag_index: Option<usize>,
+    /// Start of the match in characters (Unicode scalar values).
+    pub start: usize,
+    /// End of the match in characters, exclusive.
+    pub end: usize,
+}
+
+pub struct SearchHit<'a> {
+    pub account: &'a Account,
+    pub score: u32,
+    pub highlights: Vec<Highlight>,
+}
+
+/// Search accounts and return matches, best first.
+/// An empty query matches every account in vault order.
+pub fn search<'a>(
+    accounts: &'a [Account],
+    query: &str,
+    usage: impl Fn(&str) -> u64,
+) -> Vec<SearchHit<'a>> {
+    let terms: Vec<Vec<char>> = query
+        .split_whitespace()
+        .map(|t| t.chars().map(fold).collect())
+        .collect();
+
+    if terms.is_empty() {
+        return accounts
+            .iter()
+            .map(|account| SearchHit {
+                account,
+                score: 0,
+                highlights: Vec::new(),
+            })
+            .collect();
+    }
+
+    let mut hits: Vec<(SearchHit<'a>, u64)> = accounts
+        .iter()
+        .filter_map(|account| {
+            let (score, highlights) = match_account(account, &terms)?;
+            let uses = usage(&account.id);
+            Some((
+                SearchHit {
+                    account,
+                    score: score + usage_boost(uses),
+                    highlights,
+                },
+                uses,
+            ))
+        })
+        .collect();
+
+    // Stable sort keeps vault order among equally ranked hits
+    hits.sort_by(|(a, a_uses), (b, b_uses)| b.score.cmp(&a.score).then(b_uses.cmp(a_uses)));
+    hits.into_iter().map(|(hit, _)| hit).collect()
+}
+
+struct Field {
+    name: &'static str,
+    tag_index: Option<usize>,
+    weight: u32,
+    chars: Vec<char>,
+}
+
+fn searchable_fields(account: &Account) -> Vec<Field> {
+    let field = |name, tag_index, weight, text: &str| Field {
+        name,
+        tag_index,
+        weight,
+        chars: text.chars().map(fold).collect(),
+    };
+
+    let mut fields = vec![
+        field("issuer", None, WEIGHT_ISSUER, &account.issuer),
+        field("label", None, WEIGHT_LABEL, &account.label),
+    ];
+    for (i, tag) in account.tags.iter().enumerate() {
+        fields.push(field("tag", Some(i), WEIGHT_TAG, tag));
+    }
+    if let Some(domain) = account_domain(account) {
+        fields.push(field("domain", None, WEIGHT_DOMAIN, domain));
+    }
+    fields
+}
+
+/// The domain associated with an account: its issuer's domain when it has a
+/// bundled icon, otherwise the domain of an email-style label.
+fn account_domain(account: &Account) -> Option<&str> {
+    account
+        .icon
+        .as_deref()
+        .and_then(crate::icons::primary_domain)
+        .or_else(|| account.label.rsplit_once('@').map(|(_, domain)| domain))
+        .filter(|d| !d.is_empty())
+}
+
+/// Match all terms against an account. Returns the combined score and the
+/// highlighted ranges, or `None` if any term matches nowhere.
+fn match_account(account: &Account, terms: &[Vec<char>]) -> Option<(u32, Vec<Highlight>)> {
+    let fields = searchable_fields(account);
+    let mut score = 0;
+    let mut highlights: Vec<Highlight> = Vec::new();
+
+    for term in terms {
+        let (field, quality, start, end) = fields
+            .iter()
+            .filter_map(|f| {
+                match_field(term, &f.chars)
+                    .map(|(q, start, end)| (f, q * f.weight / 100, start, end))
+            })
+            .max_by_key(|(_, q, _, _)| *q)?;
+
+        score += quality;
+        let highlight = Highlight {
+            field: field.name.to_string(),
+            tag_index: field.tag_index,
+            start,
+            end,
+        };
+        if !highlights.contains(&highlight) {
+            highlights.push(highlight);
+        }
+    }
+
+    Some((score, highlights))
+}
+
+/// Find the best match of a term in a field.
+/// Returns (quality, start, end) in character offsets.
+fn match_field(term: &[char], field: &[char]) -> Option<(u32, usize, usize)> {
+    let len = term.len();
+    if len == 0 || field.is_empty() {
+        return None;
+    }
+
+    if field == term {
+        return Some((QUALITY_EXACT, 0, len));
+    }
+    if field.starts_with(term) {
+        return Some((QUALITY_PREFIX, 0, len));
+    }
+
+    let mut substring = None;
+    if len <= field.len() {
+        for start in 1..=field.len() - len {
+            if &field[start..start + len] != term {
+                continue;
+            }
+            if !field[start - 1].is_alphanumeric() {
+                return Some((QUALITY_WORD_PREFIX, start, start + len));
+            }
+            substring.get_or_insert((QUALITY_SUBSTRING, start, start + len));
+        }
+    }
+    if substring.is_some() {
+        return substring;
+    }
+
+    match_typo(term, field)
+}
+
+/// Match a term against the start of each word in the field, allowing one
+/// edit for short terms and two for long ones.
+fn match_typo(term: &[char], field: &[char]) -> Option<(u32, usize, usize)> {
+    let max_edits = match term.len() {
+        0..=2 => return None,
+        3..=7 => 1,
+        _ => 2,
+    };
+
+    let mut best: Option<(usize, usize, usize)> = None;
+    for (word_start, word) in words(field) {
+        let min_len = term.len().saturating_sub(max_edits).max(1);
+        let max_len = (term.len() + max_edits).min(word.len());
+        for prefix_len in min_len..=max_len {
+            let distance = edit_distance(term, &word[..prefix_len]);
+            if distance <= max_edits && best.is_none_or(|(d, _, _)| distance < d) {
+                best = Some((distance, word_start, word_start + prefix_len));
+            }
+        }
+    }
+
+    best.map(|(distance, start, end)| {
+        (
+            QUALITY_TYPO.saturating_sub(TYPO_PENALTY * distance as u32),
+            start,
+            end,
+        )
+    })
+}
+
+/// Split a field into alphanumeric words with their starting offsets.
+fn words(field: &[char]) -> Vec<(usize, &[char])> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, c) in field.iter().enumerate() {
+        match (c.is_alphanumeric(), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                words.push((s, &field[s..i]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        words.push((s, &field[s..]));
+    }
+    words
+}
+
+/// Optimal string alignment distance (Levenshtein plus adjacent transpositions).
+pub(crate) fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut d = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+fn usage_boost(uses: u64) -> u32 {
+    let boost = ((uses as f64).ln_1p() * 3.0) as u32;
+    boost.min(MAX_USAGE_BOOST)
+}
+
+/// Case-fold a character and strip common Latin diacritics. Always maps one
+/// character to one character so match offsets line up with the original text.
+fn fold(c: char) -> char {
+    let lower = c.to_lowercase().next().unwrap_or(c);
+    match lower {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => 'a',
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => 'c',
+        'ď' | 'đ' => 'd',
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => 'e',
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => 'g',
+        'ĥ' | 'ħ' => 'h',
+        'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => 'i',
+        'ĵ' => 'j',
+        'ķ' => 'k',
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => 'l',
+        'ñ' | 'ń' | 'ņ' | 'ň' => 'n',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => 'o',
+        'ŕ' | 'ŗ' | 'ř' => 'r',
+        'ś' | 'ŝ' | 'ş' | 'š' | 'ß' => 's',
+        'ţ' | 'ť' | 'ŧ' => 't',
+        'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => 'u',
+        'ŵ' => 'w',
+        'ý' | 'ÿ' | 'ŷ' => 'y',
+        'ź' | 'ż' | 'ž' => 'z',
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_account(id: &str, issuer: &str, label: &str, tags: &[&str]) -> Account {
+        Account {
+            id: id.to_string(),
+            issuer: issuer.to_string(),
+            label: label.to_string(),
+            secret: "JBSWY3DPEHPK3PXP".to_string(),
+            algorithm: "SHA1".to_string(),
+            digits: 6,
+            period: 30,
+            icon: crate::icons::resolve(issuer, label).map(str::to_string),
+            last_modified: 0,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+
+    fn ids(hits: &[SearchHit]) -> Vec<String> {
+        hits.iter().map(|h| h.account.id.clone()).collect()
+    }
+
+    fn no_usage(_: &str) -> u64 {
+        0
+    }
+
+    #[test]
+    fn test_empty_query_returns_all_in_order() {
+        let accounts = vec![
+            make_account("a1", "GitHub", "alice", &[]),
+            make_account("a2", "GitLab", "bob", &[]),
+        ];
+        let hits = search(&accounts, "   ", no_usage);
+        assert_eq!(ids(&hits), vec!["a1", "a2"]);
+    }
+
+    #[test]
+    fn test_ranks_exact_over_prefix_over_substring() {
+        let accounts = vec![
+            make_account("sub", "MyGit", "", &[]),
+            make_account("prefix", "GitLab", "", &[]),
+            make_account("exact", "Git", "", &[]),
+        ];
+        let hits = search(&accounts, "git", no_usage);
+        assert_eq!(ids(&hits), vec!["exact", "prefix", "sub"]);
+    }
+
+    #[test]
+    fn test_typo_tolerance() {
+        let accounts = vec![
+            make_account("a1", "GitHub", "alice", &[]),
+            make_account("a2", "Dropbox", "bob", &[]),
+        ];
+        assert_eq!(ids(&search(&accounts, "gihtub", no_usage)), vec!["a1"]);
+        assert_eq!(ids(&search(&accounts, "dropbx", no_usage)), vec!["a2"]);
+        assert!(search(&accounts, "zzzzzz", no_usage).is_empty());
+    }
+
+    #[test]
+    fn test_matches_tags_label_and_domain() {
+        let accounts = vec![
+            make_account("a1", "GitHub", "alice@corp.example", &["work"]),
+            make_account("a2", "Amazon Web Services", "root", &["billing"]),
+        ];
+
+        let hits = search(&accounts, "work", no_usage);
+        assert_eq!(ids(&hits), vec!["a1"]);
+        assert_eq!(hits[0].highlights[0].field, "tag");
+        assert_eq!(hits[0].highlights[0].tag_index, Some(0));
+
+        let hits = search(&accounts, "corp.example", no_usage);
+        assert_eq!(ids(&hits), vec!["a1"]);
+
+        let hits = search(&accounts, "aws.amazon", no_usage);
+        assert_eq!(ids(&hits), vec!["a2"]);
+        assert_eq!(hits[0].highlights[0].field, "domain");
+    }
+
+    #[test]
+    fn test_all_terms_must_match() {
+        let accounts = vec![
+            make_account("a1", "GitHub", "alice", &["work"]),
+            make_account("a2", "GitHub", "bob", &["personal"]),
+        ];
+        let hits = search(&accounts, "github work", no_usage);
+        assert_eq!(ids(&hits), vec!["a1"]);
+        assert_eq!(hits[0].highlights.len(), 2);
+    }
+
+    #[test]
+    fn test_usage_breaks_ties() {
+        let accounts = vec![
+            make_account("rare", "GitHub", "alice", &[]),
+            make_account("often", "GitHub", "bob", &[]),
+        ];
+        let hits = search(&accounts, "github", |id| if id == "often" { 40 } else { 0 });
+        assert_eq!(ids(&hits), vec!["often", "rare"]);
+        assert!(hits[0].score > hits[1].score);
+    }
+
+    #[test]
+    fn test_usage_does_not_beat_much_better_match() {
+        let accounts = vec![
+            make_account("popular", "Forgejo", "", &[]),
+            make_account("exact", "Forge", "", &[]),
+        ];
+        let hits = search(
+            &accounts,
+            "forge",
+            |id| if id == "popular" { 10_000 } else { 0 },
+        );
+        assert_eq!(ids(&hits), vec!["exact", "popular"]);
+    }
+
+    #[test]
+    fn test_unicode_diacritics_are_folded() {
+        let accounts = vec![make_account("a1", "Zürich Bank", "Müller", &[])];
+
+        let hits = search(&accounts, "zurich", no_usage);
+        assert_eq!(ids(&hits), vec!["a1"]);
+        assert_eq!(
+            (hits[0].highlights[0].start, hits[0].highlights[0].end),
+            (0, 6)
+        );
+
+        let hits = search(&accounts, "MÜLLER", no_usage);
+        assert_eq!(hits[0].highlights[0].field, "label");
+    }
+
+    #[test]
+    fn test_unicode_highlights_use_character_offsets() {
+        let accounts = vec![
+            make_account("jp", "日本銀行", "", &[]),
+            make_account("ru", "Яндекс ID", "", &[]),
+        ];
+
+        let hits = search(&accounts, "銀行", no_usage);
+        assert_eq!(ids(&hits), vec!["jp"]);
+        let h = &hits[0].highlights[0];
+        assert_eq!((h.start, h.end), (2, 4));
+
+        let hits = search(&accounts, "яндекс", no_usage);
+        assert_eq!(ids(&hits), vec!["ru"]);
+
+        let hits = search(&accounts, "id", no_usage);
+        let h = &hits[0].highlights[0];
+        assert_eq!((h.start, h.end), (7, 9));
+    }
+
+    #[test]
+    fn test_unicode_typo() {
+        let accounts = vec![make_account("a1", "Société Générale", "", &[])];
+        assert_eq!(ids(&search(&accounts, "generle", no_usage)), vec!["a1"]);
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        let d = |a: &str, b: &str| {
+            edit_distance(
+                &a.chars().collect::<Vec<_>>(),
+                &b.chars().collect::<Vec<_>>(),
+            )
+        };
+        assert_eq!(d("github", "github"), 0);
+        assert_eq!(d("gihtub", "github"), 1);
+        assert_eq!(d("gitub", "github"), 1);
+        assert_eq!(d("kitten", "sitting"), 3);
+    }
+}
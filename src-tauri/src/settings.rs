@@ -0,0 +1,209 @@
+//! User-configurable app settings, stored as JSON in the app data directory.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::auto_backup::AutoBackupSettings;
+use crate::backup::{KdfPreset, Recipient};
+use crate::password_strength;
+use crate::storage::{DEFAULT_MAX_TOMBSTONE_RETENTION_DAYS, MIN_TOMBSTONE_RETENTION_DAYS};
+
+const SETTINGS_FILE: &str = "settings.json";
+const MAX_TOMBSTONE_RETENTION_LIMIT_DAYS: u64 = 10 * 365;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    /// Upper bound on how long deletions are remembered for sync, in days.
+    pub tombstone_max_retention_days: u64,
+    /// Key derivation strength used for new backups.
+    pub backup_kdf_preset: KdfPreset,
+    /// Minimum strength score (0–4) for backup passwords; 0 only enforces
+    /// the length floor.
+    pub backup_min_password_score: u8,
+    /// Recovery public keys that automatic backups are encrypted to.
+    pub backup_recipients: Vec<String>,
+    pub auto_backup: AutoBackupSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            tombstone_max_retention_days: DEFAULT_MAX_TOMBSTONE_RETENTION_DAYS,
+            backup_kdf_preset: KdfPreset::Standard,
+            backup_min_password_score: password_strength::DEFAULT_MIN_SCORE,
+            backup_recipients: Vec::new(),
+            auto_backup: AutoBackupSettings::default(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join(SETTINGS_FILE);
+        if let Ok(data) = fs::read_to_string(&path) {
+            serde_json::from_str(&data).unwrap_or_default()
+        } else {
+            Self::default()
+        }
+    }
+
+    /// Whether settings have ever been saved on this device.
+    pub fn is_saved(data_dir: &Path) -> bool {
+        data_dir.join(SETTINGS_FILE).exists()
+    }
+
+    pub fn save(&self, data_dir: &Path) -> Result<(), String> {
+        let path = data_dir.join(SETTINGS_FILE);
+        let json = serde_json::to_string_pretty(self).map_err(|e| {
+            tracing::error!(error = %e, "Failed to serialize settings");
+            "Failed to save settings".to_string()
+        })?;
+        fs::write(&path, json).map_err(|e| {
+            tracing::error!(error = %e, "Failed to write settings");
+            "Failed to save settings".to_string()
+        })
+    }
+
+    /// The part of a backup's settings that is safe to adopt on restore.
+    /// Recovery keys and the automatic backup schedule stay at this device's
+    /// defaults, and the password policy is never lowered, so a foreign
+    /// backup cannot redirect or weaken future backups.
+    pub fn restorable(&self) -> Self {
+        Self {
+            tombstone_max_retention_days: self.tombstone_max_retention_days,
+            backup_kdf_preset: self.backup_kdf_preset,
+            backup_min_password_score: self
+                .backup_min_password_score
+                .max(password_strength::DEFAULT_MIN_SCORE),
+            ..Self::default()
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !(MIN_TOMBSTONE_RETENTION_DAYS..=MAX_TOMBSTONE_RETENTION_LIMIT_DAYS)
+            .contains(&self.tombstone_max_retention_days)
+        {
+            return Err(format!(
+                "Deletion retention must be between {MIN_TOMBSTONE_RETENTION_DAYS} and {MAX_TOMBSTONE_RETENTION_LIMIT_DAYS} days"
+            ));
+        }
+        if self.backup_min_password_score > password_strength::MAX_SCORE {
+            return Err(format!(
+                "Minimum password score must be between 0 and {}",
+                password_strength::MAX_SCORE
+            ));
+        }
+        for recipient in &self.backup_recipients {
+            Recipient::parse(recipient)?;
+        }
+        self.auto_backup.validate()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = Settings {
+            tombstone_max_retention_days: 200,
+            backup_kdf_preset: KdfPreset::Strong,
+            backup_min_password_score: 4,
+            backup_recipients: vec![crate::backup::Identity::generate().recipient().to_string()],
+            auto_backup: AutoBackupSettings {
+                enabled: true,
+                directory: Some("/backups".into()),
+                ..Default::default()
+            },
+        };
+        assert!(!Settings::is_saved(dir.path()));
+        settings.save(dir.path()).unwrap();
+        assert!(Settings::is_saved(dir.path()));
+        assert_eq!(Settings::load(dir.path()), settings);
+    }
+
+    #[test]
+    fn test_restorable_drops_security_settings() {
+        let backup = Settings {
+            tombstone_max_retention_days: 200,
+            backup_kdf_preset: KdfPreset::Strong,
+            backup_min_password_score: 0,
+            backup_recipients: vec![crate::backup::Identity::generate().recipient().to_string()],
+            auto_backup: AutoBackupSettings {
+                enabled: true,
+                directory: Some("/elsewhere".into()),
+                ..Default::default()
+            },
+        };
+        let restored = backup.restorable();
+        assert_eq!(restored.tombstone_max_retention_days, 200);
+        assert_eq!(restored.backup_kdf_preset, KdfPreset::Strong);
+        assert_eq!(
+            restored.backup_min_password_score,
+            password_strength::DEFAULT_MIN_SCORE
+        );
+        assert!(restored.backup_recipients.is_empty());
+        assert_eq!(restored.auto_backup, AutoBackupSettings::default());
+
+        let strict = Settings {
+            backup_min_password_score: password_strength::MAX_SCORE,
+            ..Settings::default()
+        };
+        assert_eq!(
+            strict.restorable().backup_min_password_score,
+            password_strength::MAX_SCORE
+        );
+    }
+
+    #[test]
+    fn test_settings_defaults_for_missing_or_partial_file() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(Settings::load(dir.path()), Settings::default());
+
+        fs::write(dir.path().join(SETTINGS_FILE), "{}").unwrap();
+        assert_eq!(Settings::load(dir.path()), Settings::default());
+    }
+
+    #[test]
+    fn test_validate_retention_bounds() {
+        let with_days = |days| Settings {
+            tombstone_max_retention_days: days,
+            ..Default::default()
+        };
+        assert!(with_days(MIN_TOMBSTONE_RETENTION_DAYS).validate().is_ok());
+        assert!(with_days(365).validate().is_ok());
+        assert!(with_days(MIN_TOMBSTONE_RETENTION_DAYS - 1)
+            .validate()
+            .is_err());
+        assert!(with_days(MAX_TOMBSTONE_RETENTION_LIMIT_DAYS + 1)
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn test_validate_backup_recipients() {
+        let with_recipient = |recipient: String| Settings {
+            backup_recipients: vec![recipient],
+            ..Default::default()
+        };
+        let valid = crate::backup::Identity::generate().recipient().to_string();
+        assert!(with_recipient(valid).validate().is_ok());
+        assert!(with_recipient("ghostpub1nope".into()).validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_min_password_score() {
+        let with_score = |score| Settings {
+            backup_min_password_score: score,
+            ..Default::default()
+        };
+        assert_eq!(Settings::default().backup_min_password_score, 3);
+        assert!(with_score(0).validate().is_ok());
+        assert!(with_score(4).validate().is_ok());
+        assert!(with_score(5).validate().is_err());
+    }
+}
@@ -4,15 +4,20 @@ use aes_gcm::{
 };
 use rand::{rngs::OsRng, RngCore};
 use serde::{Deserialize, Serialize};
-use std::fs;
-use std::path::{Path, PathBuf};
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::time::{SystemTime, UNIX_EPOCH};
 use zeroize::Zeroizing;
 
-use crate::keystore;
+use crate::backup::BackupKey;
+use crate::storage_backend::{FileBackend, StorageBackend};
 
 const STORAGE_VERSION: u8 = 2;
-const TOMBSTONE_RETENTION_DAYS: u64 = 90;
+const DAY_SECS: u64 = 24 * 60 * 60;
+/// Tombstones are always kept at least this long, so devices that haven't
+/// synced yet (or haven't been seen before) still learn about deletions.
+pub const MIN_TOMBSTONE_RETENTION_DAYS: u64 = 90;
+pub const DEFAULT_MAX_TOMBSTONE_RETENTION_DAYS: u64 = 365;
 
 pub fn now_secs() -> u64 {
     SystemTime::now()
@@ -29,12 +34,83 @@ struct StoragePayload {
     accounts: Vec<Account>,
     #[serde(default)]
     tombstones: Vec<Tombstone>,
+    #[serde(default)]
+    meta: VaultMeta,
+    /// Key for automatic backups, derived from the user's backup password.
+    #[serde(default)]
+    backup_key: Option<BackupKey>,
+}
+
+impl StoragePayload {
+    fn empty() -> Self {
+        Self {
+            version: STORAGE_VERSION,
+            device_id: generate_device_id(),
+            accounts: Vec::new(),
+            tombstones: Vec::new(),
+            meta: VaultMeta::default(),
+            backup_key: None,
+        }
+    }
 }
 
 fn generate_device_id() -> String {
     uuid::Uuid::new_v4().to_string()
 }
 
+/// Device-local vault metadata that isn't part of any account and is never synced.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct VaultMeta {
+    /// How often each account's code has been used, keyed by account id.
+    #[serde(default)]
+    pub usage: HashMap<String, u64>,
+    /// When a backup was last exported from this device.
+    #[serde(default)]
+    pub last_backup_at: Option<u64>,
+    /// Latest deletion time among pruned tombstones. A peer that last synced
+    /// before this may still hold accounts deleted here.
+    #[serde(default)]
+    pub tombstones_pruned_through: Option<u64>,
+    /// Account changes since the last backup; drives change-triggered automatic backups.
+    #[serde(default)]
+    pub changes_since_backup: u64,
+}
+
+/// When tombstones may be garbage collected.
+#[derive(Clone, Debug)]
+pub struct TombstoneRetention {
+    /// Hard upper bound: older tombstones are pruned even if a peer never synced.
+    pub max_days: u64,
+    /// Last successful sync with each known peer (from `sync::SyncHistory`).
+    pub peer_last_sync: HashMap<String, u64>,
+}
+
+impl Default for TombstoneRetention {
+    fn default() -> Self {
+        Self {
+            max_days: DEFAULT_MAX_TOMBSTONE_RETENTION_DAYS,
+            peer_last_sync: HashMap::new(),
+        }
+    }
+}
+
+impl TombstoneRetention {
+    /// A tombstone is kept for the minimum retention period, then until every
+    /// known peer has synced after the deletion, but never past `max_days`.
+    fn keep(&self, tombstone: &Tombstone, now: u64) -> bool {
+        let age = now.saturating_sub(tombstone.deleted_at);
+        if age > self.max_days * DAY_SECS {
+            return false;
+        }
+        if age <= MIN_TOMBSTONE_RETENTION_DAYS * DAY_SECS {
+            return true;
+        }
+        self.peer_last_sync
+            .values()
+            .any(|&last_sync| last_sync < tombstone.deleted_at)
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Tombstone {
     pub id: String,
@@ -53,32 +129,58 @@ pub struct Account {
     pub icon: Option<String>,
     #[serde(default = "now_secs")]
     pub last_modified: u64,
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 pub struct Storage {
-    data_dir: PathBuf,
+    backend: Box<dyn StorageBackend>,
     device_id: String,
     accounts: Vec<Account>,
     tombstones: Vec<Tombstone>,
+    meta: VaultMeta,
+    backup_key: Option<BackupKey>,
     key: Zeroizing<[u8; 32]>,
+    retention: TombstoneRetention,
+    /// Set while a `transaction` is running; saves are deferred until it ends.
+    in_transaction: bool,
+    /// Whether a save was requested during the current transaction.
+    dirty: bool,
+}
+
+/// In-memory state captured at the start of a transaction, for rollback.
+struct Snapshot {
+    accounts: Vec<Account>,
+    tombstones: Vec<Tombstone>,
+    meta: VaultMeta,
 }
 
 impl Storage {
     pub fn new(data_dir: PathBuf) -> Result<Self, String> {
-        fs::create_dir_all(&data_dir).map_err(|e| {
-            tracing::error!(error = %e, path = %data_dir.display(), "Failed to create data directory");
-            "Failed to initialize storage".to_string()
-        })?;
+        Self::with_backend(Box::new(FileBackend::new(data_dir)?))
+    }
+
+    /// File-backed storage with a caller-supplied key instead of the OS keychain.
+    #[allow(dead_code)]
+    pub fn new_with_key(data_dir: PathBuf, key: [u8; 32]) -> Result<Self, String> {
+        Self::with_backend(Box::new(FileBackend::with_key(data_dir, key)?))
+    }
 
-        let key = Self::load_or_create_key(&data_dir)?;
-        let (device_id, accounts, tombstones) = Self::load_payload(&data_dir, &key[..])?;
+    pub fn with_backend(mut backend: Box<dyn StorageBackend>) -> Result<Self, String> {
+        let key = backend.load_or_create_key()?;
+        let payload = Self::load_payload(backend.as_mut(), &key[..])?;
 
         Ok(Self {
-            data_dir,
-            device_id,
-            accounts,
-            tombstones,
+            backend,
+            device_id: payload.device_id,
+            accounts: payload.accounts,
+            tombstones: payload.tombstones,
+            meta: payload.meta,
+            backup_key: payload.backup_key,
             key,
+            retention: TombstoneRetention::default(),
+            in_transaction: false,
+            dirty: false,
         })
     }
 
@@ -90,96 +192,14 @@ impl Storage {
         &self.tombstones
     }
 
-    fn legacy_key_path(data_dir: &Path) -> PathBuf {
-        data_dir.join("ghost.key")
-    }
-
-    fn data_path(data_dir: &Path) -> PathBuf {
-        data_dir.join("accounts.enc")
-    }
-
-    /// Load the encryption key with the following priority:
-    /// 1. OS keychain (desktop: Credential Manager / Keychain / Secret Service)
-    /// 2. Legacy plaintext file (ghost.key) — migrated to keychain then deleted
-    /// 3. Generate new key — stored in keychain, file fallback if keychain unavailable
-    fn load_or_create_key(data_dir: &Path) -> Result<Zeroizing<[u8; 32]>, String> {
-        let legacy_path = Self::legacy_key_path(data_dir);
-
-        // 1. Try OS keychain
-        if let Some(key) = keystore::load_key() {
-            // Clean up legacy file if it still exists
-            if legacy_path.exists() {
-                let _ = fs::remove_file(&legacy_path);
-            }
-            return Ok(Zeroizing::new(key));
-        }
-
-        // 2. Migrate from legacy plaintext file → keychain
-        if legacy_path.exists() {
-            let key = Self::read_key_file(&legacy_path)?;
-            if keystore::store_key(&key) {
-                // Migrated successfully — delete the plaintext file
-                let _ = fs::remove_file(&legacy_path);
-            }
-            // Return key regardless of whether keychain store succeeded
-            // (on mobile, store_key returns false but the key is still valid)
-            return Ok(key);
-        }
-
-        // 3. Generate new key
-        let mut key = Zeroizing::new([0u8; 32]);
-        OsRng.fill_bytes(&mut *key);
-
-        if keystore::store_key(&key) {
-            // Stored securely in OS keychain — no file on disk
-            return Ok(key);
-        }
-
-        // Fallback: write to file (mobile platforms until keychain integration)
-        fs::write(&legacy_path, *key).map_err(|e| {
-            tracing::error!(error = %e, "Failed to write key file");
-            "Failed to initialize encryption".to_string()
-        })?;
-
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            let perms = fs::Permissions::from_mode(0o600);
-            let _ = fs::set_permissions(&legacy_path, perms);
-        }
-
-        Ok(key)
-    }
-
-    fn read_key_file(path: &Path) -> Result<Zeroizing<[u8; 32]>, String> {
-        let bytes = fs::read(path).map_err(|e| {
-            tracing::error!(error = %e, "Failed to read key file");
-            "Failed to load encryption key".to_string()
-        })?;
-        if bytes.len() != 32 {
-            return Err("Invalid encryption key".to_string());
-        }
-        let mut key = Zeroizing::new([0u8; 32]);
-        key.copy_from_slice(&bytes);
-        Ok(key)
-    }
-
     fn load_payload(
-        data_dir: &Path,
+        backend: &mut dyn StorageBackend,
         key: &[u8],
-    ) -> Result<(String, Vec<Account>, Vec<Tombstone>), String> {
-        let path = Self::data_path(data_dir);
-        if !path.exists() {
-            return Ok((generate_device_id(), Vec::new(), Vec::new()));
-        }
-
-        let data = fs::read(&path).map_err(|e| {
-            tracing::error!(error = %e, "Failed to read accounts file");
-            "Failed to load accounts".to_string()
-        })?;
-        if data.len() < 12 {
-            return Ok((generate_device_id(), Vec::new(), Vec::new()));
-        }
+    ) -> Result<StoragePayload, String> {
+        let data = match backend.read()? {
+            Some(data) if data.len() >= 12 => data,
+            _ => return Ok(StoragePayload::empty()),
+        };
 
         let (nonce_bytes, ciphertext) = data.split_at(12);
         let cipher = Aes256Gcm::new_from_slice(key).map_err(|e| {
@@ -192,37 +212,113 @@ impl Storage {
             Ok(pt) => pt,
             Err(e) => {
                 tracing::warn!(error = %e, "Decryption failed — encryption key does not match stored data. \
-                    Backing up unreadable file and starting fresh.");
-                let backup_path = path.with_extension("enc.bak");
-                if let Err(rename_err) = fs::rename(&path, &backup_path) {
-                    tracing::error!(error = %rename_err, "Failed to back up unreadable accounts file");
-                }
-                return Ok((generate_device_id(), Vec::new(), Vec::new()));
+                    Backing up unreadable data and starting fresh.");
+                // Failure is already logged by the backend; start fresh either way
+                let _ = backend.quarantine();
+                return Ok(StoragePayload::empty());
             }
         };
 
         // Try versioned format first, fall back to legacy Vec<Account>
         if let Ok(payload) = serde_json::from_slice::<StoragePayload>(&plaintext) {
-            return Ok((payload.device_id, payload.accounts, payload.tombstones));
+            return Ok(payload);
         }
 
         let accounts: Vec<Account> = serde_json::from_slice(&plaintext).map_err(|e| {
             tracing::error!(error = %e, "Failed to deserialize accounts");
             "Failed to load accounts".to_string()
         })?;
-        Ok((generate_device_id(), accounts, Vec::new()))
+        Ok(StoragePayload {
+            accounts,
+            ..StoragePayload::empty()
+        })
+    }
+
+    /// Run several mutations as one batch. Saves requested inside `f` are
+    /// deferred and the vault is written once at the end. If `f` or the final
+    /// save fails, the in-memory state is rolled back and nothing is written.
+    /// A nested call joins the outer transaction.
+    pub fn transaction<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, String>,
+    ) -> Result<T, String> {
+        if self.in_transaction {
+            return f(self);
+        }
+
+        let snapshot = Snapshot {
+            accounts: self.accounts.clone(),
+            tombstones: self.tombstones.clone(),
+            meta: self.meta.clone(),
+        };
+        self.in_transaction = true;
+        self.dirty = false;
+        let result = f(self);
+        self.in_transaction = false;
+
+        let result = match result {
+            Ok(value) if self.dirty => self.write().map(|_| value),
+            other => other,
+        };
+        self.dirty = false;
+
+        if result.is_err() {
+            tracing::warn!("Storage transaction failed, rolling back");
+            self.accounts = snapshot.accounts;
+            self.tombstones = snapshot.tombstones;
+            self.meta = snapshot.meta;
+        }
+        result
     }
 
     fn save(&mut self) -> Result<(), String> {
-        // Prune tombstones older than retention period
-        let cutoff = now_secs().saturating_sub(TOMBSTONE_RETENTION_DAYS * 24 * 60 * 60);
-        self.tombstones.retain(|t| t.deleted_at >= cutoff);
+        if self.in_transaction {
+            self.dirty = true;
+            return Ok(());
+        }
+        self.write()
+    }
+
+    /// Save after a change to the accounts, counting it towards the next
+    /// automatic backup.
+    fn save_change(&mut self) -> Result<(), String> {
+        self.meta.changes_since_backup += 1;
+        self.save()
+    }
+
+    fn prune_tombstones(&mut self, now: u64) {
+        let (kept, pruned): (Vec<Tombstone>, Vec<Tombstone>) = self
+            .tombstones
+            .drain(..)
+            .partition(|t| self.retention.keep(t, now));
+        self.tombstones = kept;
+
+        if let Some(latest) = pruned.iter().map(|t| t.deleted_at).max() {
+            let through = self.meta.tombstones_pruned_through.unwrap_or(0).max(latest);
+            self.meta.tombstones_pruned_through = Some(through);
+            tracing::debug!(count = pruned.len(), "Pruned tombstones");
+        }
+    }
+
+    /// Update the tombstone retention policy. Takes effect on the next save.
+    pub fn set_tombstone_retention(&mut self, retention: TombstoneRetention) {
+        self.retention = retention;
+    }
+
+    pub fn tombstones_pruned_through(&self) -> Option<u64> {
+        self.meta.tombstones_pruned_through
+    }
+
+    fn write(&mut self) -> Result<(), String> {
+        self.prune_tombstones(now_secs());
 
         let payload = StoragePayload {
             version: STORAGE_VERSION,
             device_id: self.device_id.clone(),
             accounts: self.accounts.clone(),
             tombstones: self.tombstones.clone(),
+            meta: self.meta.clone(),
+            backup_key: self.backup_key.clone(),
         };
         let plaintext = serde_json::to_vec(&payload).map_err(|e| {
             tracing::error!(error = %e, "Failed to serialize accounts");
@@ -247,27 +343,7 @@ impl Storage {
         data.extend_from_slice(&nonce_bytes);
         data.extend(ciphertext);
 
-        let path = Self::data_path(&self.data_dir);
-        let tmp_path = path.with_extension("enc.tmp");
-
-        fs::write(&tmp_path, &data).map_err(|e| {
-            tracing::error!(error = %e, "Failed to write temporary accounts file");
-            "Failed to save accounts".to_string()
-        })?;
-
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            let _ = fs::set_permissions(&tmp_path, fs::Permissions::from_mode(0o600));
-        }
-
-        fs::rename(&tmp_path, &path).map_err(|e| {
-            tracing::error!(error = %e, "Failed to rename temporary accounts file");
-            let _ = fs::remove_file(&tmp_path);
-            "Failed to save accounts".to_string()
-        })?;
-
-        Ok(())
+        self.backend.write(&data)
     }
 
     pub fn list(&self) -> &[Account] {
@@ -283,7 +359,7 @@ impl Storage {
     pub fn add(&mut self, mut account: Account) -> Result<(), String> {
         account.last_modified = now_secs();
         self.accounts.push(account);
-        self.save()
+        self.save_change()
     }
 
     pub fn delete(&mut self, id: &str) -> Result<(), String> {
@@ -292,7 +368,54 @@ impl Storage {
             deleted_at: now_secs(),
         });
         self.accounts.retain(|a| a.id != id);
-        self.save()
+        self.meta.usage.remove(id);
+        self.save_change()
+    }
+
+    /// Merge duplicates into the account `keep_id`. Tags are combined and
+    /// usage counts summed; the other accounts are deleted with tombstones
+    /// so sync removes them on other devices too.
+    pub fn merge(&mut self, keep_id: &str, remove_ids: &[String]) -> Result<(), String> {
+        if remove_ids.iter().any(|id| id == keep_id) {
+            return Err("Cannot merge an account into itself".to_string());
+        }
+        if self.get(keep_id).is_none() || remove_ids.iter().any(|id| self.get(id).is_none()) {
+            return Err("Account not found".to_string());
+        }
+
+        let mut tags = self
+            .get(keep_id)
+            .map(|a| a.tags.clone())
+            .unwrap_or_default();
+        let mut uses = 0;
+        for id in remove_ids {
+            for tag in self.get(id).map(|a| a.tags.as_slice()).unwrap_or_default() {
+                if !tags.iter().any(|t| t.to_lowercase() == tag.to_lowercase()) {
+                    tags.push(tag.clone());
+                }
+            }
+            uses += self.meta.usage.remove(id).unwrap_or(0);
+        }
+
+        let now = now_secs();
+        if let Some(keep) = self.accounts.iter_mut().find(|a| a.id == keep_id) {
+            if keep.tags != tags {
+                keep.tags = tags;
+                keep.last_modified = now;
+            }
+        }
+        if uses > 0 {
+            *self.meta.usage.entry(keep_id.to_string()).or_insert(0) += uses;
+        }
+
+        for id in remove_ids {
+            self.tombstones.push(Tombstone {
+                id: id.clone(),
+                deleted_at: now,
+            });
+        }
+        self.accounts.retain(|a| !remove_ids.contains(&a.id));
+        self.save_change()
     }
 
     pub fn get(&self, id: &str) -> Option<&Account> {
@@ -308,9 +431,97 @@ impl Storage {
         account.issuer = issuer;
         account.label = label;
         account.last_modified = now_secs();
+        self.save_change()
+    }
+
+    /// Replace the tags of an account.
+    pub fn set_tags(&mut self, id: &str, tags: Vec<String>) -> Result<(), String> {
+        let account = self
+            .accounts
+            .iter_mut()
+            .find(|a| a.id == id)
+            .ok_or_else(|| "Account not found".to_string())?;
+        account.tags = tags;
+        account.last_modified = now_secs();
+        self.save_change()
+    }
+
+    /// Count a use of an account's code (e.g. copying it), for ranking.
+    pub fn record_use(&mut self, id: &str) -> Result<(), String> {
+        if self.get(id).is_none() {
+            return Err("Account not found".to_string());
+        }
+        *self.meta.usage.entry(id.to_string()).or_insert(0) += 1;
+        self.save()
+    }
+
+    pub fn usage_count(&self, id: &str) -> u64 {
+        self.meta.usage.get(id).copied().unwrap_or(0)
+    }
+
+    pub fn meta(&self) -> &VaultMeta {
+        &self.meta
+    }
+
+    /// Bring in tombstones and metadata from a restored backup. Tombstones
+    /// are added unless the account exists here; usage counts are only kept
+    /// for accounts that exist.
+    pub fn restore_metadata(
+        &mut self,
+        tombstones: &[Tombstone],
+        meta: &VaultMeta,
+    ) -> Result<(), String> {
+        for tombstone in tombstones {
+            if self.get(&tombstone.id).is_some() {
+                continue;
+            }
+            match self.tombstones.iter_mut().find(|t| t.id == tombstone.id) {
+                Some(existing) => {
+                    existing.deleted_at = existing.deleted_at.max(tombstone.deleted_at)
+                }
+                None => self.tombstones.push(tombstone.clone()),
+            }
+        }
+
+        for (id, &uses) in &meta.usage {
+            if self.get(id).is_some() {
+                let count = self.meta.usage.entry(id.clone()).or_insert(0);
+                *count = (*count).max(uses);
+            }
+        }
+
+        if let Some(through) = meta.tombstones_pruned_through {
+            let current = self.meta.tombstones_pruned_through.unwrap_or(0);
+            self.meta.tombstones_pruned_through = Some(current.max(through));
+        }
+        self.save()
+    }
+
+    /// Remember that a backup of the vault was just exported.
+    pub fn record_backup(&mut self) -> Result<(), String> {
+        self.meta.last_backup_at = Some(now_secs());
+        self.meta.changes_since_backup = 0;
+        self.save()
+    }
+
+    pub fn changes_since_backup(&self) -> u64 {
+        self.meta.changes_since_backup
+    }
+
+    pub fn backup_key(&self) -> Option<&BackupKey> {
+        self.backup_key.as_ref()
+    }
+
+    /// Set or clear the key used for automatic backups.
+    pub fn set_backup_key(&mut self, key: Option<BackupKey>) -> Result<(), String> {
+        self.backup_key = key;
         self.save()
     }
 
+    pub fn last_backup_at(&self) -> Option<u64> {
+        self.meta.last_backup_at
+    }
+
     pub fn reorder(&mut self, ids: &[String]) -> Result<(), String> {
         // Build new order from the provided IDs
         let mut reordered = Vec::with_capacity(self.accounts.len());
@@ -326,13 +537,13 @@ impl Storage {
             }
         }
         self.accounts = reordered;
-        self.save()
+        self.save_change()
     }
 
     /// Add a synced account, preserving its original last_modified timestamp.
     pub fn add_synced(&mut self, account: Account) -> Result<(), String> {
         self.accounts.push(account);
-        self.save()
+        self.save_change()
     }
 
     /// Replace an existing account in-place (preserving list order).
@@ -343,37 +554,15 @@ impl Storage {
             .position(|a| a.id == account.id)
             .ok_or_else(|| "Account not found".to_string())?;
         self.accounts[pos] = account;
-        self.save()
-    }
-
-    pub fn data_dir(&self) -> &Path {
-        &self.data_dir
-    }
-}
-
-#[cfg(test)]
-impl Storage {
-    /// Test-only constructor that accepts a pre-generated key,
-    /// bypassing OS keychain access.
-    pub fn new_with_key(data_dir: PathBuf, key: [u8; 32]) -> Result<Self, String> {
-        fs::create_dir_all(&data_dir).map_err(|e| {
-            tracing::error!(error = %e, path = %data_dir.display(), "Failed to create data directory");
-            "Failed to initialize storage".to_string()
-        })?;
-        let (device_id, accounts, tombstones) = Self::load_payload(&data_dir, &key[..])?;
-        Ok(Self {
-            data_dir,
-            device_id,
-            accounts,
-            tombstones,
-            key: Zeroizing::new(key),
-        })
+        self.save_change()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::storage_backend::MemoryBackend;
+    use std::fs;
 
     fn test_key() -> [u8; 32] {
         [0xAA; 32]
@@ -390,6 +579,7 @@ mod tests {
             period: 30,
             icon: None,
             last_modified: 0,
+            tags: Vec::new(),
         }
     }
 
@@ -421,6 +611,165 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_memory_backend_persistence() {
+        let backend = MemoryBackend::new(test_key());
+        {
+            let mut s = Storage::with_backend(Box::new(backend.clone())).unwrap();
+            s.add(make_account("a1")).unwrap();
+            s.set_tags("a1", vec!["work".into()]).unwrap();
+        }
+
+        let s = Storage::with_backend(Box::new(backend)).unwrap();
+        assert_eq!(s.list().len(), 1);
+        assert_eq!(s.get("a1").unwrap().tags, vec!["work"]);
+    }
+
+    #[test]
+    fn test_undecryptable_payload_is_quarantined() {
+        let backend = MemoryBackend::new(test_key());
+        {
+            let mut s = Storage::with_backend(Box::new(backend.clone())).unwrap();
+            s.add(make_account("a1")).unwrap();
+        }
+        let saved = backend.read().unwrap().unwrap();
+
+        let wrong = MemoryBackend::new([0xBB; 32]);
+        let mut wrong_backend = wrong.clone();
+        wrong_backend.write(&saved).unwrap();
+
+        let s = Storage::with_backend(Box::new(wrong_backend)).unwrap();
+        assert_eq!(s.list().len(), 0);
+        assert_eq!(wrong.quarantined(), Some(saved));
+    }
+
+    /// Counts writes and can be told to fail them.
+    #[derive(Clone)]
+    struct CountingBackend {
+        inner: MemoryBackend,
+        writes: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+        fail_writes: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    }
+
+    impl CountingBackend {
+        fn new() -> Self {
+            Self {
+                inner: MemoryBackend::new(test_key()),
+                writes: Default::default(),
+                fail_writes: Default::default(),
+            }
+        }
+
+        fn writes(&self) -> usize {
+            self.writes.load(std::sync::atomic::Ordering::SeqCst)
+        }
+    }
+
+    impl StorageBackend for CountingBackend {
+        fn load_or_create_key(&mut self) -> Result<Zeroizing<[u8; 32]>, String> {
+            self.inner.load_or_create_key()
+        }
+
+        fn read(&self) -> Result<Option<Vec<u8>>, String> {
+            self.inner.read()
+        }
+
+        fn write(&mut self, data: &[u8]) -> Result<(), String> {
+            if self.fail_writes.load(std::sync::atomic::Ordering::SeqCst) {
+                return Err("Failed to save accounts".to_string());
+            }
+            self.writes
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            self.inner.write(data)
+        }
+
+        fn quarantine(&mut self) -> Result<(), String> {
+            self.inner.quarantine()
+        }
+    }
+
+    #[test]
+    fn test_transaction_saves_once() {
+        let backend = CountingBackend::new();
+        let mut s = Storage::with_backend(Box::new(backend.clone())).unwrap();
+
+        s.transaction(|s| {
+            for i in 0..50 {
+                s.add(make_account(&format!("a{i}")))?;
+            }
+            s.delete("a0")
+        })
+        .unwrap();
+
+        assert_eq!(backend.writes(), 1);
+        let reloaded = Storage::with_backend(Box::new(backend.inner.clone())).unwrap();
+        assert_eq!(reloaded.list().len(), 49);
+        assert_eq!(reloaded.tombstones().len(), 1);
+    }
+
+    #[test]
+    fn test_transaction_without_changes_does_not_write() {
+        let backend = CountingBackend::new();
+        let mut s = Storage::with_backend(Box::new(backend.clone())).unwrap();
+        let count = s.transaction(|s| Ok(s.list().len())).unwrap();
+        assert_eq!(count, 0);
+        assert_eq!(backend.writes(), 0);
+    }
+
+    #[test]
+    fn test_transaction_rolls_back_on_error() {
+        let backend = CountingBackend::new();
+        let mut s = Storage::with_backend(Box::new(backend.clone())).unwrap();
+        s.add(make_account("keep")).unwrap();
+
+        let result: Result<(), String> = s.transaction(|s| {
+            s.add(make_account("new"))?;
+            s.delete("keep")?;
+            s.update("missing", "X".into(), "Y".into())
+        });
+
+        assert!(result.is_err());
+        let ids: Vec<&str> = s.list().iter().map(|a| a.id.as_str()).collect();
+        assert_eq!(ids, vec!["keep"]);
+        assert!(s.tombstones().is_empty());
+        assert_eq!(backend.writes(), 1);
+    }
+
+    #[test]
+    fn test_transaction_rolls_back_when_save_fails() {
+        let backend = CountingBackend::new();
+        let mut s = Storage::with_backend(Box::new(backend.clone())).unwrap();
+        backend
+            .fail_writes
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+
+        let result = s.transaction(|s| s.add(make_account("a1")));
+        assert!(result.is_err());
+        assert!(s.list().is_empty());
+
+        // Storage stays usable afterwards
+        backend
+            .fail_writes
+            .store(false, std::sync::atomic::Ordering::SeqCst);
+        s.add(make_account("a2")).unwrap();
+        assert_eq!(backend.writes(), 1);
+    }
+
+    #[test]
+    fn test_nested_transaction_joins_outer() {
+        let backend = CountingBackend::new();
+        let mut s = Storage::with_backend(Box::new(backend.clone())).unwrap();
+
+        let result: Result<(), String> = s.transaction(|s| {
+            s.transaction(|s| s.add(make_account("inner")))?;
+            Err("outer failed".to_string())
+        });
+
+        assert!(result.is_err());
+        assert!(s.list().is_empty());
+        assert_eq!(backend.writes(), 0);
+    }
+
     #[test]
     fn test_wrong_key_recovers_gracefully() {
         let dir = tempfile::tempdir().unwrap();
@@ -537,4 +886,240 @@ mod tests {
         let ids: Vec<&str> = s.list().iter().map(|a| a.id.as_str()).collect();
         assert_eq!(ids, vec!["a3", "a2", "a1"]);
     }
+
+    #[test]
+    fn test_set_tags_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = test_key();
+        {
+            let mut s = Storage::new_with_key(dir.path().to_path_buf(), key).unwrap();
+            s.add(make_account("a1")).unwrap();
+            s.set_tags("a1", vec!["work".into(), "cloud".into()])
+                .unwrap();
+        }
+        let s = Storage::new_with_key(dir.path().to_path_buf(), key).unwrap();
+        assert_eq!(s.get("a1").unwrap().tags, vec!["work", "cloud"]);
+    }
+
+    #[test]
+    fn test_restore_metadata() {
+        let now = now_secs();
+        let mut s = Storage::with_backend(Box::new(MemoryBackend::new(test_key()))).unwrap();
+        s.add(make_account("live")).unwrap();
+        s.tombstones.push(Tombstone {
+            id: "gone".into(),
+            deleted_at: now - 100,
+        });
+
+        let mut meta = VaultMeta::default();
+        meta.usage.insert("live".into(), 7);
+        meta.usage.insert("missing".into(), 3);
+        meta.tombstones_pruned_through = Some(50);
+        let tombstones = vec![
+            Tombstone {
+                id: "gone".into(),
+                deleted_at: now - 50,
+            },
+            Tombstone {
+                id: "other".into(),
+                deleted_at: now - 30,
+            },
+            Tombstone {
+                id: "live".into(),
+                deleted_at: now - 10,
+            },
+        ];
+        s.restore_metadata(&tombstones, &meta).unwrap();
+
+        let restored: Vec<(&str, u64)> = s
+            .tombstones()
+            .iter()
+            .map(|t| (t.id.as_str(), t.deleted_at))
+            .collect();
+        assert_eq!(restored, vec![("gone", now - 50), ("other", now - 30)]);
+        assert_eq!(s.usage_count("live"), 7);
+        assert_eq!(s.usage_count("missing"), 0);
+        assert_eq!(s.tombstones_pruned_through(), Some(50));
+        assert!(s.get("live").is_some());
+    }
+
+    #[test]
+    fn test_record_backup_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = Storage::new_with_key(dir.path().to_path_buf(), test_key()).unwrap();
+        assert_eq!(storage.last_backup_at(), None);
+        storage.record_backup().unwrap();
+        let recorded = storage.last_backup_at().unwrap();
+
+        let reloaded = Storage::new_with_key(dir.path().to_path_buf(), test_key()).unwrap();
+        assert_eq!(reloaded.last_backup_at(), Some(recorded));
+    }
+
+    #[test]
+    fn test_changes_since_backup() {
+        let backend = MemoryBackend::new(test_key());
+        let mut s = Storage::with_backend(Box::new(backend.clone())).unwrap();
+        s.add(make_account("a")).unwrap();
+        s.add(make_account("b")).unwrap();
+        s.update("a", "New".into(), "label".into()).unwrap();
+        s.record_use("a").unwrap();
+        s.delete("b").unwrap();
+        assert_eq!(s.changes_since_backup(), 4);
+
+        s.record_backup().unwrap();
+        assert_eq!(s.changes_since_backup(), 0);
+        s.set_tags("a", vec!["work".into()]).unwrap();
+
+        let reloaded = Storage::with_backend(Box::new(backend)).unwrap();
+        assert_eq!(reloaded.changes_since_backup(), 1);
+    }
+
+    #[test]
+    fn test_backup_key_persists() {
+        let backend = MemoryBackend::new(test_key());
+        let mut s = Storage::with_backend(Box::new(backend.clone())).unwrap();
+        assert!(s.backup_key().is_none());
+        let key = BackupKey::derive("password1234", crate::backup::KdfPreset::Standard).unwrap();
+        s.set_backup_key(Some(key)).unwrap();
+
+        let mut reloaded = Storage::with_backend(Box::new(backend.clone())).unwrap();
+        assert!(reloaded.backup_key().is_some());
+        reloaded.set_backup_key(None).unwrap();
+        assert!(Storage::with_backend(Box::new(backend))
+            .unwrap()
+            .backup_key()
+            .is_none());
+    }
+
+    fn tombstone(id: &str, age_days: u64, now: u64) -> Tombstone {
+        Tombstone {
+            id: id.to_string(),
+            deleted_at: now - age_days * DAY_SECS,
+        }
+    }
+
+    #[test]
+    fn test_tombstones_kept_until_all_peers_synced() {
+        let now = now_secs();
+        let mut s = Storage::with_backend(Box::new(MemoryBackend::new(test_key()))).unwrap();
+        s.tombstones = vec![
+            tombstone("recent", 10, now),
+            tombstone("old", 120, now),
+            tombstone("ancient", 400, now),
+        ];
+
+        // Peer "laptop" last synced 200 days ago and hasn't seen "old" yet
+        let mut retention = TombstoneRetention::default();
+        retention
+            .peer_last_sync
+            .insert("phone".into(), now - DAY_SECS);
+        retention
+            .peer_last_sync
+            .insert("laptop".into(), now - 200 * DAY_SECS);
+        s.set_tombstone_retention(retention.clone());
+        s.save().unwrap();
+
+        let ids: Vec<&str> = s.tombstones().iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(ids, vec!["recent", "old"]);
+        assert_eq!(s.tombstones_pruned_through(), Some(now - 400 * DAY_SECS));
+
+        // Once the laptop syncs, the old tombstone can go
+        retention.peer_last_sync.insert("laptop".into(), now);
+        s.set_tombstone_retention(retention);
+        s.save().unwrap();
+
+        let ids: Vec<&str> = s.tombstones().iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(ids, vec!["recent"]);
+        assert_eq!(s.tombstones_pruned_through(), Some(now - 120 * DAY_SECS));
+    }
+
+    #[test]
+    fn test_tombstones_without_peers_use_minimum_retention() {
+        let now = now_secs();
+        let mut s = Storage::with_backend(Box::new(MemoryBackend::new(test_key()))).unwrap();
+        s.tombstones = vec![tombstone("recent", 89, now), tombstone("old", 91, now)];
+        s.save().unwrap();
+
+        let ids: Vec<&str> = s.tombstones().iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(ids, vec!["recent"]);
+    }
+
+    #[test]
+    fn test_tombstone_max_retention_is_configurable() {
+        let now = now_secs();
+        let mut s = Storage::with_backend(Box::new(MemoryBackend::new(test_key()))).unwrap();
+        s.tombstones = vec![tombstone("a", 150, now), tombstone("b", 100, now)];
+
+        let mut retention = TombstoneRetention {
+            max_days: 120,
+            ..Default::default()
+        };
+        retention.peer_last_sync.insert("stale".into(), 0);
+        s.set_tombstone_retention(retention);
+        s.save().unwrap();
+
+        let ids: Vec<&str> = s.tombstones().iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(ids, vec!["b"]);
+    }
+
+    #[test]
+    fn test_merge_tombstones_duplicates_and_combines_tags() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = Storage::new_with_key(dir.path().to_path_buf(), test_key()).unwrap();
+        storage.add(make_account("keep")).unwrap();
+        storage.add(make_account("dup1")).unwrap();
+        storage.add(make_account("dup2")).unwrap();
+        storage.add(make_account("other")).unwrap();
+        storage.set_tags("keep", vec!["Work".into()]).unwrap();
+        storage
+            .set_tags("dup1", vec!["work".into(), "admin".into()])
+            .unwrap();
+        storage.record_use("dup2").unwrap();
+
+        storage
+            .merge("keep", &["dup1".to_string(), "dup2".to_string()])
+            .unwrap();
+
+        let ids: Vec<&str> = storage.list().iter().map(|a| a.id.as_str()).collect();
+        assert_eq!(ids, vec!["keep", "other"]);
+        assert_eq!(storage.get("keep").unwrap().tags, vec!["Work", "admin"]);
+        assert_eq!(storage.usage_count("keep"), 1);
+        let tombstoned: Vec<&str> = storage.tombstones().iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(tombstoned, vec!["dup1", "dup2"]);
+
+        let reloaded = Storage::new_with_key(dir.path().to_path_buf(), test_key()).unwrap();
+        assert_eq!(reloaded.list().len(), 2);
+        assert_eq!(reloaded.tombstones().len(), 2);
+    }
+
+    #[test]
+    fn test_merge_rejects_invalid_ids() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = Storage::new_with_key(dir.path().to_path_buf(), test_key()).unwrap();
+        storage.add(make_account("a")).unwrap();
+        storage.add(make_account("b")).unwrap();
+
+        assert!(storage.merge("a", &["a".to_string()]).is_err());
+        assert!(storage.merge("a", &["missing".to_string()]).is_err());
+        assert!(storage.merge("missing", &["b".to_string()]).is_err());
+        assert_eq!(storage.list().len(), 2);
+        assert!(storage.tombstones().is_empty());
+    }
+
+    #[test]
+    fn test_usage_counts_persist_and_clear_on_delete() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = test_key();
+        {
+            let mut s = Storage::new_with_key(dir.path().to_path_buf(), key).unwrap();
+            s.add(make_account("a1")).unwrap();
+            s.record_use("a1").unwrap();
+            s.record_use("a1").unwrap();
+            assert!(s.record_use("missing").is_err());
+        }
+        let mut s = Storage::new_with_key(dir.path().to_path_buf(), key).unwrap();
+        assert_eq!(s.usage_count("a1"), 2);
+        s.delete("a1").unwrap();
+        assert_eq!(s.usage_count("a1"), 0);
+    }
 }
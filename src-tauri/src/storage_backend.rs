@@ -0,0 +1,290 @@
+//! Persistence backends for the encrypted vault.
+//!
+//! `Storage` handles encryption and the payload format; a backend only moves
+//! opaque encrypted bytes and provides the vault key. `FileBackend` is the
+//! on-disk store used by the app, `MemoryBackend` keeps everything in memory
+//! so commands can be tested without touching disk.
+
+use rand::{rngs::OsRng, RngCore};
+use std::fs;
+use std::path::{Path, PathBuf};
+#[cfg(test)]
+use std::sync::{Arc, Mutex};
+use zeroize::Zeroizing;
+
+use crate::keystore;
+
+pub trait StorageBackend: Send {
+    /// Load the vault encryption key, creating and persisting one if none exists.
+    fn load_or_create_key(&mut self) -> Result<Zeroizing<[u8; 32]>, String>;
+
+    /// Read the encrypted payload. Returns `None` if nothing has been saved yet.
+    fn read(&self) -> Result<Option<Vec<u8>>, String>;
+
+    /// Replace the encrypted payload atomically: after a crash, the previous
+    /// or the new payload is readable, never a partial write.
+    fn write(&mut self, data: &[u8]) -> Result<(), String>;
+
+    /// Move a payload that can't be decrypted out of the way, so a fresh vault
+    /// can be started without destroying the old data.
+    fn quarantine(&mut self) -> Result<(), String>;
+}
+
+/// Encrypted JSON file in the app data directory.
+pub struct FileBackend {
+    data_dir: PathBuf,
+    /// Key to use instead of the OS keychain.
+    fixed_key: Option<Zeroizing<[u8; 32]>>,
+}
+
+impl FileBackend {
+    pub fn new(data_dir: PathBuf) -> Result<Self, String> {
+        fs::create_dir_all(&data_dir).map_err(|e| {
+            tracing::error!(error = %e, path = %data_dir.display(), "Failed to create data directory");
+            "Failed to initialize storage".to_string()
+        })?;
+        Ok(Self {
+            data_dir,
+            fixed_key: None,
+        })
+    }
+
+    /// A file backend that uses the given key and never touches the keychain.
+    pub fn with_key(data_dir: PathBuf, key: [u8; 32]) -> Result<Self, String> {
+        let mut backend = Self::new(data_dir)?;
+        backend.fixed_key = Some(Zeroizing::new(key));
+        Ok(backend)
+    }
+
+    fn legacy_key_path(&self) -> PathBuf {
+        self.data_dir.join("ghost.key")
+    }
+
+    fn data_path(&self) -> PathBuf {
+        self.data_dir.join("accounts.enc")
+    }
+
+    fn read_key_file(path: &Path) -> Result<Zeroizing<[u8; 32]>, String> {
+        let bytes = fs::read(path).map_err(|e| {
+            tracing::error!(error = %e, "Failed to read key file");
+            "Failed to load encryption key".to_string()
+        })?;
+        if bytes.len() != 32 {
+            return Err("Invalid encryption key".to_string());
+        }
+        let mut key = Zeroizing::new([0u8; 32]);
+        key.copy_from_slice(&bytes);
+        Ok(key)
+    }
+}
+
+impl StorageBackend for FileBackend {
+    /// Load the encryption key with the following priority:
+    /// 1. OS keychain (desktop: Credential Manager / Keychain / Secret Service)
+    /// 2. Legacy plaintext file (ghost.key) — migrated to keychain then deleted
+    /// 3. Generate new key — stored in keychain, file fallback if keychain unavailable
+    fn load_or_create_key(&mut self) -> Result<Zeroizing<[u8; 32]>, String> {
+        if let Some(key) = &self.fixed_key {
+            return Ok(key.clone());
+        }
+
+        let legacy_path = self.legacy_key_path();
+
+        // 1. Try OS keychain
+        if let Some(key) = keystore::load_key() {
+            // Clean up legacy file if it still exists
+            if legacy_path.exists() {
+                let _ = fs::remove_file(&legacy_path);
+            }
+            return Ok(Zeroizing::new(key));
+        }
+
+        // 2. Migrate from legacy plaintext file → keychain
+        if legacy_path.exists() {
+            let key = Self::read_key_file(&legacy_path)?;
+            if keystore::store_key(&key) {
+                // Migrated successfully — delete the plaintext file
+                let _ = fs::remove_file(&legacy_path);
+            }
+            // Return key regardless of whether keychain store succeeded
+            // (on mobile, store_key returns false but the key is still valid)
+            return Ok(key);
+        }
+
+        // 3. Generate new key
+        let mut key = Zeroizing::new([0u8; 32]);
+        OsRng.fill_bytes(&mut *key);
+
+        if keystore::store_key(&key) {
+            // Stored securely in OS keychain — no file on disk
+            return Ok(key);
+        }
+
+        // Fallback: write to file (mobile platforms until keychain integration)
+        fs::write(&legacy_path, *key).map_err(|e| {
+            tracing::error!(error = %e, "Failed to write key file");
+            "Failed to initialize encryption".to_string()
+        })?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let perms = fs::Permissions::from_mode(0o600);
+            let _ = fs::set_permissions(&legacy_path, perms);
+        }
+
+        Ok(key)
+    }
+
+    fn read(&self) -> Result<Option<Vec<u8>>, String> {
+        let path = self.data_path();
+        if !path.exists() {
+            return Ok(None);
+        }
+        fs::read(&path).map(Some).map_err(|e| {
+            tracing::error!(error = %e, "Failed to read accounts file");
+            "Failed to load accounts".to_string()
+        })
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), String> {
+        let path = self.data_path();
+        let tmp_path = path.with_extension("enc.tmp");
+
+        fs::write(&tmp_path, data).map_err(|e| {
+            tracing::error!(error = %e, "Failed to write temporary accounts file");
+            "Failed to save accounts".to_string()
+        })?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let _ = fs::set_permissions(&tmp_path, fs::Permissions::from_mode(0o600));
+        }
+
+        fs::rename(&tmp_path, &path).map_err(|e| {
+            tracing::error!(error = %e, "Failed to rename temporary accounts file");
+            let _ = fs::remove_file(&tmp_path);
+            "Failed to save accounts".to_string()
+        })
+    }
+
+    fn quarantine(&mut self) -> Result<(), String> {
+        let path = self.data_path();
+        let backup_path = path.with_extension("enc.bak");
+        fs::rename(&path, &backup_path).map_err(|e| {
+            tracing::error!(error = %e, "Failed to back up unreadable accounts file");
+            "Failed to back up unreadable accounts".to_string()
+        })
+    }
+}
+
+/// In-memory backend. Clones share the same data, so a test can reopen
+/// `Storage` on a clone to check what was persisted.
+#[cfg(test)]
+#[derive(Clone)]
+pub struct MemoryBackend {
+    key: Zeroizing<[u8; 32]>,
+    state: Arc<Mutex<MemoryState>>,
+}
+
+#[cfg(test)]
+#[derive(Default)]
+struct MemoryState {
+    data: Option<Vec<u8>>,
+    quarantined: Option<Vec<u8>>,
+}
+
+#[cfg(test)]
+impl MemoryBackend {
+    pub fn new(key: [u8; 32]) -> Self {
+        Self {
+            key: Zeroizing::new(key),
+            state: Arc::new(Mutex::new(MemoryState::default())),
+        }
+    }
+
+    fn state(&self) -> Result<std::sync::MutexGuard<'_, MemoryState>, String> {
+        self.state
+            .lock()
+            .map_err(|_| "Storage unavailable".to_string())
+    }
+
+    /// The payload most recently moved aside by `quarantine`.
+    pub fn quarantined(&self) -> Option<Vec<u8>> {
+        self.state().ok()?.quarantined.clone()
+    }
+}
+
+#[cfg(test)]
+impl StorageBackend for MemoryBackend {
+    fn load_or_create_key(&mut self) -> Result<Zeroizing<[u8; 32]>, String> {
+        Ok(self.key.clone())
+    }
+
+    fn read(&self) -> Result<Option<Vec<u8>>, String> {
+        Ok(self.state()?.data.clone())
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), String> {
+        self.state()?.data = Some(data.to_vec());
+        Ok(())
+    }
+
+    fn quarantine(&mut self) -> Result<(), String> {
+        let mut state = self.state()?;
+        state.quarantined = state.data.take();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_backend_roundtrip_and_atomic_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut backend = FileBackend::with_key(dir.path().to_path_buf(), [1; 32]).unwrap();
+        assert_eq!(backend.read().unwrap(), None);
+
+        backend.write(b"first").unwrap();
+        backend.write(b"second").unwrap();
+        assert_eq!(backend.read().unwrap().as_deref(), Some(&b"second"[..]));
+        assert!(!dir.path().join("accounts.enc.tmp").exists());
+    }
+
+    #[test]
+    fn test_file_backend_fixed_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut backend = FileBackend::with_key(dir.path().to_path_buf(), [7; 32]).unwrap();
+        assert_eq!(*backend.load_or_create_key().unwrap(), [7; 32]);
+        assert!(!dir.path().join("ghost.key").exists());
+    }
+
+    #[test]
+    fn test_file_backend_quarantine() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut backend = FileBackend::with_key(dir.path().to_path_buf(), [1; 32]).unwrap();
+        backend.write(b"unreadable").unwrap();
+        backend.quarantine().unwrap();
+
+        assert_eq!(backend.read().unwrap(), None);
+        assert_eq!(
+            fs::read(dir.path().join("accounts.enc.bak")).unwrap(),
+            b"unreadable"
+        );
+    }
+
+    #[test]
+    fn test_memory_backend_clones_share_data() {
+        let mut backend = MemoryBackend::new([3; 32]);
+        let other = backend.clone();
+        backend.write(b"payload").unwrap();
+        assert_eq!(other.read().unwrap().as_deref(), Some(&b"payload"[..]));
+
+        backend.quarantine().unwrap();
+        assert_eq!(other.read().unwrap(), None);
+        assert_eq!(other.quarantined().as_deref(), Some(&b"payload"[..]));
+    }
+}
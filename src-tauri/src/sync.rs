@@ -10,7 +10,7 @@ use std::path::Path;
 use std::time::Instant;
 use zeroize::Zeroizing;
 
-use crate::storage::{Account, Tombstone};
+use crate::storage::{Account, Tombstone, TombstoneRetention};
 
 /// Unambiguous character set (excludes 0/O, 1/I/L) — matches pin.rs recovery codes.
 const CODE_CHARS: &[u8] = b"ABCDEFGHJKMNPQRSTUVWXYZ23456789";
@@ -230,6 +230,11 @@ pub struct MergeResult {
     pub auto_updated: Vec<Account>,
     /// Count of accounts that were identical.
     pub unchanged: usize,
+    /// Accounts the remote has that may have been deleted here, but whose
+    /// tombstones were already pruned. Only added if the user restores them.
+    pub possibly_resurrected: Vec<Account>,
+    /// The remote last synced before tombstones were pruned here.
+    pub stale_peer: bool,
 }
 
 /// A merge conflict where both devices changed the same account.
@@ -318,9 +323,43 @@ pub fn merge(
         remote_deletions,
         auto_updated,
         unchanged,
+        possibly_resurrected: Vec::new(),
+        stale_peer: false,
     }
 }
 
+/// Guard against resurrecting deleted accounts when the remote hasn't synced
+/// since tombstones were pruned here (`pruned_through` is the latest pruned
+/// deletion time). New accounts that predate the pruning can't be told apart
+/// from deleted ones, so they are held back in `possibly_resurrected` instead
+/// of being auto-added.
+pub fn flag_stale_peer(
+    result: &mut MergeResult,
+    last_sync_with_peer: Option<u64>,
+    pruned_through: Option<u64>,
+) {
+    let Some(pruned_through) = pruned_through else {
+        return;
+    };
+    if last_sync_with_peer.is_some_and(|last| last >= pruned_through) {
+        return;
+    }
+
+    let (held, to_add): (Vec<Account>, Vec<Account>) = result
+        .to_add
+        .drain(..)
+        .partition(|a| a.last_modified <= pruned_through);
+    result.to_add = to_add;
+    result.possibly_resurrected = held;
+    result.stale_peer = true;
+
+    tracing::warn!(
+        event = "sync_stale_peer",
+        held_back = result.possibly_resurrected.len(),
+        "Peer last synced before deletions were pruned; holding back possibly deleted accounts"
+    );
+}
+
 // ── Sync History ──────────────────────────────────────────────────
 
 /// Tracks the last sync timestamp with each peer device.
@@ -358,6 +397,14 @@ impl SyncHistory {
     pub fn record_sync(&mut self, device_id: &str, timestamp: u64) {
         self.peers.insert(device_id.to_string(), timestamp);
     }
+
+    /// Tombstone retention policy based on when each known peer last synced.
+    pub fn tombstone_retention(&self, max_days: u64) -> TombstoneRetention {
+        TombstoneRetention {
+            max_days,
+            peer_last_sync: self.peers.clone(),
+        }
+    }
 }
 
 // ── Session Encryption (transport-layer envelope) ─────────────────
@@ -430,6 +477,7 @@ mod tests {
             period: 30,
             icon: None,
             last_modified: modified,
+            tags: Vec::new(),
         }
     }
 
@@ -632,6 +680,54 @@ mod tests {
         assert_eq!(result.to_add.len(), 1);
     }
 
+    #[test]
+    fn test_flag_stale_peer_holds_back_old_accounts() {
+        let remote = vec![
+            make_account("old", "GitHub", 1000),
+            make_account("new", "Google", 5000),
+        ];
+        let mut result = merge(&[], &[], remote, &[], Some(500));
+        flag_stale_peer(&mut result, Some(500), Some(2000));
+
+        assert!(result.stale_peer);
+        assert_eq!(result.to_add.len(), 1);
+        assert_eq!(result.to_add[0].id, "new");
+        assert_eq!(result.possibly_resurrected.len(), 1);
+        assert_eq!(result.possibly_resurrected[0].id, "old");
+    }
+
+    #[test]
+    fn test_flag_stale_peer_never_synced() {
+        let remote = vec![make_account("old", "GitHub", 1000)];
+        let mut result = merge(&[], &[], remote, &[], None);
+        flag_stale_peer(&mut result, None, Some(2000));
+        assert!(result.stale_peer);
+        assert!(result.to_add.is_empty());
+    }
+
+    #[test]
+    fn test_flag_stale_peer_ignores_up_to_date_peer() {
+        let remote = vec![make_account("old", "GitHub", 1000)];
+        let mut result = merge(&[], &[], remote.clone(), &[], Some(3000));
+        flag_stale_peer(&mut result, Some(3000), Some(2000));
+        assert!(!result.stale_peer);
+        assert_eq!(result.to_add.len(), 1);
+
+        let mut result = merge(&[], &[], remote, &[], None);
+        flag_stale_peer(&mut result, None, None);
+        assert!(!result.stale_peer);
+        assert_eq!(result.to_add.len(), 1);
+    }
+
+    #[test]
+    fn test_sync_history_tombstone_retention() {
+        let mut history = SyncHistory::default();
+        history.record_sync("device-2", 1000);
+        let retention = history.tombstone_retention(200);
+        assert_eq!(retention.max_days, 200);
+        assert_eq!(retention.peer_last_sync.get("device-2"), Some(&1000));
+    }
+
     #[test]
     fn test_key_from_code_consistency() {
         let session = SyncSession::new();
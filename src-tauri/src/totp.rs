@@ -18,7 +18,7 @@ fn to_algorithm(name: &str) -> Result<Algorithm, String> {
     }
 }
 
-pub fn generate_code(account: &Account) -> Result<CodeResponse, String> {
+fn build_totp(account: &Account, issuer: Option<String>) -> Result<TOTP, String> {
     let algorithm = to_algorithm(&account.algorithm)?;
 
     let secret_bytes = Secret::Encoded(account.secret.clone())
@@ -29,15 +29,19 @@ pub fn generate_code(account: &Account) -> Result<CodeResponse, String> {
         })?;
 
     // Use new_unchecked to support real-world secrets that may be < 128 bits
-    let totp = TOTP::new_unchecked(
+    Ok(TOTP::new_unchecked(
         algorithm,
         account.digits as usize,
         1,
         account.period as u64,
         secret_bytes,
-        Some(account.issuer.clone()),
+        issuer,
         account.label.clone(),
-    );
+    ))
+}
+
+pub fn generate_code(account: &Account) -> Result<CodeResponse, String> {
+    let totp = build_totp(account, Some(account.issuer.clone()))?;
 
     let time = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
@@ -54,6 +58,28 @@ pub fn generate_code(account: &Account) -> Result<CodeResponse, String> {
     })
 }
 
+/// Build the `otpauth://` URI other authenticator apps scan to add `account`.
+pub fn otpauth_uri(account: &Account) -> Result<String, String> {
+    let issuer = Some(account.issuer.clone()).filter(|issuer| !issuer.is_empty());
+    Ok(build_totp(account, issuer)?.get_url())
+}
+
+/// Decode a Base32 secret, tolerating case, spaces, dashes, and padding.
+/// Returns `None` if the secret is not valid Base32.
+pub fn decode_secret(secret: &str) -> Option<Vec<u8>> {
+    let normalized: String = secret
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-' && *c != '=')
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    if normalized.is_empty() {
+        return None;
+    }
+    data_encoding::BASE32_NOPAD
+        .decode(normalized.as_bytes())
+        .ok()
+}
+
 pub fn parse_otpauth_uri(uri: &str) -> Result<Account, String> {
     let totp = TOTP::from_url_unchecked(uri).map_err(|e| {
         tracing::warn!(error = ?e, "Invalid otpauth URI");
@@ -68,17 +94,20 @@ pub fn parse_otpauth_uri(uri: &str) -> Result<Account, String> {
     .to_string();
 
     let secret = data_encoding::BASE32_NOPAD.encode(&totp.secret);
+    let issuer = totp.issuer.unwrap_or_default();
+    let icon = crate::icons::resolve(&issuer, &totp.account_name).map(str::to_string);
 
     Ok(Account {
         id: uuid::Uuid::new_v4().to_string(),
-        issuer: totp.issuer.unwrap_or_default(),
+        issuer,
         label: totp.account_name,
         secret,
         algorithm,
         digits: totp.digits as u32,
         period: totp.step as u32,
-        icon: None,
+        icon,
         last_modified: 0,
+        tags: Vec::new(),
     })
 }
 
@@ -97,6 +126,7 @@ mod tests {
             period: 30,
             icon: None,
             last_modified: 0,
+            tags: Vec::new(),
         }
     }
 
@@ -116,6 +146,19 @@ mod tests {
         assert_eq!(result.code.len(), 8);
     }
 
+    #[test]
+    fn test_decode_secret_normalizes() {
+        let expected = decode_secret("JBSWY3DPEHPK3PXP").unwrap();
+        assert_eq!(decode_secret("jbsw y3dp ehpk 3pxp").unwrap(), expected);
+        assert_eq!(decode_secret("JBSW-Y3DP-EHPK-3PXP").unwrap(), expected);
+        assert_eq!(
+            decode_secret("GEZDGNBV").unwrap(),
+            decode_secret("GEZDGNBV========").unwrap()
+        );
+        assert!(decode_secret("NOT*BASE32").is_none());
+        assert!(decode_secret("  ").is_none());
+    }
+
     #[test]
     fn test_parse_otpauth_uri() {
         let uri = "otpauth://totp/GitHub:user@example.com?secret=JBSWY3DPEHPK3PXP&issuer=GitHub&algorithm=SHA1&digits=6&period=30";
@@ -125,6 +168,7 @@ mod tests {
         assert_eq!(account.algorithm, "SHA1");
         assert_eq!(account.digits, 6);
         assert_eq!(account.period, 30);
+        assert_eq!(account.icon.as_deref(), Some("github"));
     }
 
     #[test]
@@ -134,6 +178,32 @@ mod tests {
         assert_eq!(account.issuer, "Service");
         assert_eq!(account.digits, 6);
         assert_eq!(account.period, 30);
+        assert_eq!(account.icon, None);
+    }
+
+    #[test]
+    fn test_otpauth_uri_roundtrip() {
+        let mut account = test_account();
+        account.issuer = "Acme & Co".into();
+        account.label = "alice@example.com".into();
+        account.algorithm = "SHA256".into();
+        account.digits = 8;
+        account.period = 60;
+
+        let uri = otpauth_uri(&account).unwrap();
+        assert!(uri.starts_with("otpauth://totp/Acme%20%26%20Co:alice%40example.com?"));
+        let parsed = parse_otpauth_uri(&uri).unwrap();
+        assert_eq!(parsed.issuer, account.issuer);
+        assert_eq!(parsed.label, account.label);
+        assert_eq!(parsed.secret, account.secret);
+        assert_eq!(parsed.algorithm, "SHA256");
+        assert_eq!(parsed.digits, 8);
+        assert_eq!(parsed.period, 60);
+
+        account.issuer = String::new();
+        assert!(otpauth_uri(&account)
+            .unwrap()
+            .starts_with("otpauth://totp/alice%40example.com?"));
     }
 
     #[test]
@@ -157,6 +227,7 @@ mod tests {
             period: 30,
             icon: None,
             last_modified: 0,
+            tags: Vec::new(),
         };
         let result = generate_code(&account).unwrap();
         assert_eq!(result.code.len(), 8);